@@ -3,16 +3,23 @@ pragma solidity ^0.8.20;
 
 import "@openzeppelin/contracts/utils/cryptography/EIP712.sol";
 import "@openzeppelin/contracts/utils/cryptography/ECDSA.sol";
+import "@openzeppelin/contracts/utils/cryptography/MerkleProof.sol";
 
 contract AuthChain is EIP712 {
     string private constant SIGNING_DOMAIN = "CertificateAuth";
-    string private constant SIGNATURE_VERSION = "1";
+    // Version 2 hashes string[] metadata as EIP-712 specifies, version 1 used abi.encode
+    string private constant SIGNATURE_VERSION = "2";
 
     bytes32 private constant CERTIFICATE_TYPE_HASH =
     keccak256(
         "Certificate(string name,string uniqueId,string serial,uint256 date,address owner,string[] metadata)"
     );
 
+    bytes32 private constant TRANSFER_TYPE_HASH =
+    keccak256(
+        "Transfer(string uniqueId,address from,address to,uint256 nonce,uint256 deadline)"
+    );
+
     address private immutable owner;
 
     struct Certificate {
@@ -30,14 +37,47 @@ contract AuthChain is EIP712 {
         string serial;
         uint256 date;
         address owner;
+        bool revoked;
+        RevocationReason revocationReason;
+    }
+
+    enum RevocationReason {
+        None,
+        Counterfeit,
+        Recalled,
+        Stolen,
+        IssuedInError
     }
 
     error InvalidSignature(address signer, bool result);
+    error Unauthorized(address caller);
+    error ItemNotFound(string uniqueId);
+    error ItemAlreadyRevoked(string uniqueId);
+    error InvalidRevocationReason();
+    error ItemRevoked(string uniqueId);
+    error InvalidRecipient(address to);
+    error TransferExpired(uint256 deadline);
+    error NotItemOwner(address signer, address owner);
+    error ItemAlreadyExists(string uniqueId);
+    error BatchAlreadyAnchored(bytes32 root);
 
     mapping(string id => Item) public items;
+    mapping(address owner => uint256) public nonces;
+    // Merkle roots of certificate batches, with the block they were anchored at
+    mapping(bytes32 root => uint256 blockNumber) public batchRoots;
 
     event ItemCreated(string name, bytes32 indexed uniqueId, address indexed owner);
     event DebugHash(bytes32 structHash, bytes32 digest, address signer);
+    event RevokedItem(bytes32 indexed uniqueId, RevocationReason reason);
+    event ItemTransferred(bytes32 indexed uniqueId, address indexed from, address indexed to);
+    // uniqueId is the hash of the new certificate, previousUniqueId the one it replaces
+    event ItemReissued(bytes32 indexed uniqueId, bytes32 indexed previousUniqueId, address indexed owner, string reason);
+    event BatchAnchored(bytes32 indexed root, uint256 count);
+
+    modifier onlyIssuer() {
+        if (msg.sender != owner) revert Unauthorized(msg.sender);
+        _;
+    }
 
     constructor(address _owner) EIP712(SIGNING_DOMAIN, SIGNATURE_VERSION) {
         owner = _owner;
@@ -52,6 +92,8 @@ contract AuthChain is EIP712 {
 
         Item storage item = items[certificate.uniqueId];
 
+        if (item.uniqueId != bytes32(0)) revert ItemAlreadyExists(certificate.uniqueId);
+
         item.name = certificate.name;
         item.serial = certificate.serial;
         item.uniqueId = structHash;
@@ -61,6 +103,101 @@ contract AuthChain is EIP712 {
         emit ItemCreated(item.name, item.uniqueId, item.owner);
     }
 
+    // Replace the certificate of an existing item, e.g. one issued in error; the
+    // ItemReissued event links the new certificate hash to the previous one
+    function reissueItem(Certificate memory certificate, bytes memory signature, string memory reason)
+    external
+    onlyIssuer
+    {
+        (bool is_valid, bytes32 structHash) = verifyAssetSignature(certificate, signature);
+
+        if (!is_valid) {
+            revert InvalidSignature(certificate.owner, is_valid);
+        }
+
+        Item storage item = items[certificate.uniqueId];
+
+        if (item.uniqueId == bytes32(0)) revert ItemNotFound(certificate.uniqueId);
+
+        bytes32 previousUniqueId = item.uniqueId;
+
+        item.name = certificate.name;
+        item.serial = certificate.serial;
+        item.uniqueId = structHash;
+        item.owner = certificate.owner;
+        item.date = certificate.date;
+        item.revoked = false;
+        item.revocationReason = RevocationReason.None;
+
+        emit ItemReissued(structHash, previousUniqueId, item.owner, reason);
+    }
+
+    // Anchor many certificates at once: the root of a Merkle tree whose leaves are
+    // keccak256(structHash) of each certificate, nodes hashing their sorted children
+    // as MerkleProof does. Proofs are kept off-chain by the issuer.
+    function anchorBatch(bytes32 root, uint256 count) external onlyIssuer {
+        if (batchRoots[root] != 0) revert BatchAlreadyAnchored(root);
+
+        batchRoots[root] = block.number;
+
+        emit BatchAnchored(root, count);
+    }
+
+    // The root a proof leads to from a certificate struct hash, and the block it was
+    // anchored at, 0 when it never was
+    function verifyBatchInclusion(bytes32 structHash, bytes32[] calldata proof)
+    external
+    view
+    returns (bytes32 root, uint256 anchoredAt)
+    {
+        root = MerkleProof.processProofCalldata(proof, keccak256(abi.encodePacked(structHash)));
+        anchoredAt = batchRoots[root];
+    }
+
+    function revokeItem(string memory uniqueId, RevocationReason reason) external onlyIssuer {
+        if (reason == RevocationReason.None) revert InvalidRevocationReason();
+
+        Item storage item = items[uniqueId];
+
+        if (item.uniqueId == bytes32(0)) revert ItemNotFound(uniqueId);
+        if (item.revoked) revert ItemAlreadyRevoked(uniqueId);
+
+        item.revoked = true;
+        item.revocationReason = reason;
+
+        emit RevokedItem(item.uniqueId, reason);
+    }
+
+    function transferItem(string memory uniqueId, address to, uint256 deadline, bytes memory signature) external {
+        if (block.timestamp > deadline) revert TransferExpired(deadline);
+        if (to == address(0)) revert InvalidRecipient(to);
+
+        Item storage item = items[uniqueId];
+
+        if (item.uniqueId == bytes32(0)) revert ItemNotFound(uniqueId);
+        if (item.revoked) revert ItemRevoked(uniqueId);
+
+        address from = item.owner;
+        bytes32 structHash = keccak256(
+            abi.encode(
+                TRANSFER_TYPE_HASH,
+                keccak256(bytes(uniqueId)),
+                from,
+                to,
+                nonces[from]++,
+                deadline
+            )
+        );
+
+        address signer = ECDSA.recover(_hashTypedDataV4(structHash), signature);
+
+        if (signer != from) revert NotItemOwner(signer, from);
+
+        item.owner = to;
+
+        emit ItemTransferred(item.uniqueId, from, to);
+    }
+
     function getItem(string memory itemId) external view returns (Item memory) {
         return items[itemId];
     }
@@ -69,7 +206,7 @@ contract AuthChain is EIP712 {
     public
     returns (bool, bytes32)
     {
-        bytes32 metadataHash = keccak256(abi.encode(certificate.metadata));
+        bytes32 metadataHash = hashStringArray(certificate.metadata);
         bytes32 structHash = keccak256(
             abi.encode(
                 CERTIFICATE_TYPE_HASH,
@@ -91,6 +228,17 @@ contract AuthChain is EIP712 {
         return (signer == owner, structHash);
     }
 
+    // EIP-712 encoding of string[]: keccak256 of the concatenated keccak256 of each element
+    function hashStringArray(string[] memory values) internal pure returns (bytes32) {
+        bytes32[] memory hashes = new bytes32[](values.length);
+
+        for (uint256 i = 0; i < values.length; i++) {
+            hashes[i] = keccak256(bytes(values[i]));
+        }
+
+        return keccak256(abi.encodePacked(hashes));
+    }
+
     function getOwner() external view returns (address) {
         return owner;
     }
@@ -6,6 +6,7 @@ contract Ownership {
     mapping(string => address) public assetOwner;
 
     event Owner(address owner);
+    event OwnershipTransferred(string assetId, address indexed from, address indexed to);
 
     function registerAsset(string memory assetId) external {
         require(assetOwner[assetId] == address(0), "Already registered");
@@ -14,6 +15,14 @@ contract Ownership {
         emit Owner(msg.sender);
     }
 
+    function transferAsset(string memory assetId, address to) external {
+        require(assetOwner[assetId] == msg.sender, "Not the owner");
+        require(to != address(0), "Invalid recipient");
+        assetOwner[assetId] = to;
+
+        emit OwnershipTransferred(assetId, msg.sender, to);
+    }
+
     function verifyOwnership(string memory assetId) external view returns (bool) {
         return assetOwner[assetId] == msg.sender;
     }
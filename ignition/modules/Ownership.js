@@ -14,6 +14,11 @@ module.exports = buildModule("OwnershipModule", (m) => {
   // });
 
   const ownership = m.contract("AuthChain", ["0xF2E7E2f51D7C9eEa9B0313C2eCa12f8e43bd1855"]);
-  return { ownership };
+
+  // the backend resolves these addresses from ignition/deployments/chain-<id>
+  const signatureVerifier = m.contract("SignatureVerifier");
+  const assetOwnership = m.contract("Ownership");
+
+  return { ownership, signatureVerifier, assetOwnership };
 
 });
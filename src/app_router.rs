@@ -1,26 +1,184 @@
 use axum::Router;
-use axum::routing::{get, post};
-use tower_http::cors::CorsLayer;
+use axum::extract::DefaultBodyLimit;
+use axum::middleware;
+use axum::routing::{get, post, MethodRouter};
+use axum::http::{header, HeaderName, HeaderValue, Method, Request};
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::set_header::SetResponseHeaderLayer;
+use tower_http::timeout::TimeoutLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
-use crate::certificate::{create_item, get_item, get_owner};
-use crate::models::cert_model::RouterPath;
+use crate::batch::{anchor_batch, anchoring_status, certificate_proof, queue_certificate, verify_proof};
+use crate::qr_code::{certificate_qr, verify_certificate_qr};
+use crate::document::certificate_document;
+use crate::certificate::{create_item, get_item, get_owner, reissue_item, revoke_item};
+use crate::chain_registry::list_chains;
+use crate::health::{health_live, health_ready, info};
+use crate::http_config::HttpConfig;
+use crate::idempotency::{IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED};
+use crate::metrics::{metrics, track_requests};
+use crate::models::cert_model::{ApiPaths, RouterPath};
+use crate::shutdown::reject_writes_while_draining;
+use crate::relayer::relayer_status;
 use crate::signature::signature;
 use crate::signature_verifier::{check_status, verify_signature};
-use crate::swagger_config::ApiDoc;
+use crate::swagger_config::{legacy_api_doc, ApiDocV1};
+use crate::transfer::{item_history, transfer_item};
+use crate::tx_report::{get_transaction, tracked_transactions};
+use crate::typed_data::{sign_typed_data, verify_typed_data};
+use crate::verify_ownership::{asset_owner, register_asset};
+use crate::tx_tracker::TxTracker;
 use crate::utility::AppState;
+use std::sync::Arc;
 
-pub fn paths(state: AppState, path: RouterPath) -> Router {
-    let app = Router::new()
+pub fn paths(state: AppState, path: RouterPath, http: &HttpConfig) -> Router {
+    let deprecated = SetResponseHeaderLayer::overriding(
+        HeaderName::from_static("deprecation"),
+        HeaderValue::from_static("true"),
+    );
+
+    let mut app = Router::new()
+        .merge(api_routes(&path.v1, &state.tx_tracker))
+        .merge(api_routes(&path.legacy, &state.tx_tracker).layer(deprecated)) // pre-v1 aliases
+        .route(&path.health_live, get(health_live))
+        .route(&path.health_ready, get(health_ready))
+        .route(&path.info, get(info))
+        .route(&path.metrics, get(metrics))
+        .route(&path.transaction, get(get_transaction)) // unversioned, not a deprecated alias
+        .merge(
+            SwaggerUi::new("/swagger-ui")
+                .url("/api-docs/v1/openapi.json", ApiDocV1::openapi())
+                .url("/api-docs/openapi.json", legacy_api_doc()),
+        )
+        .with_state(state)
+        .layer(TimeoutLayer::new(http.request_timeout))
+        .layer(RequestBodyLimitLayer::new(http.max_body_bytes))
+        .layer(DefaultBodyLimit::disable()) // the layer above sets the limit
+        .layer(middleware::from_fn(track_requests))
+        .layer(CompressionLayer::new())
+        .layer(SetResponseHeaderLayer::if_not_present(
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        ))
+        .layer(SetResponseHeaderLayer::if_not_present(
+            header::X_FRAME_OPTIONS,
+            HeaderValue::from_static("DENY"),
+        ))
+        .layer(SetResponseHeaderLayer::if_not_present(
+            header::REFERRER_POLICY,
+            HeaderValue::from_static("no-referrer"),
+        ));
+
+    if http.tls.is_some() {
+        app = app.layer(SetResponseHeaderLayer::if_not_present(
+            header::STRICT_TRANSPORT_SECURITY,
+            HeaderValue::from_static("max-age=31536000"),
+        ));
+    }
+
+    app.layer(PropagateRequestIdLayer::x_request_id()) // echo the id in the response
+        .layer(TraceLayer::new_for_http().make_span_with(request_span))
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid)) // keeps a caller supplied X-Request-Id
+        .layer(cors(http))
+}
+
+// Any origin unless CORS_ALLOWED_ORIGINS lists them
+fn cors(http: &HttpConfig) -> CorsLayer {
+    let Some(origins) = &http.cors_origins else {
+        return CorsLayer::permissive();
+    };
+
+    let request_id = HeaderName::from_static("x-request-id");
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins.clone()))
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            request_id.clone(),
+            HeaderName::from_static(IDEMPOTENCY_KEY),
+        ])
+        .expose_headers([
+            request_id,
+            HeaderName::from_static(IDEMPOTENT_REPLAYED),
+            HeaderName::from_static("deprecation"),
+        ])
+}
+
+// Span every log line of a request is recorded under, the query string is left out
+fn request_span<B>(request: &Request<B>) -> tracing::Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        request_id = %request_id,
+    )
+}
+
+// Routes of one API version
+fn api_routes(path: &ApiPaths, tracker: &Arc<TxTracker>) -> Router<AppState> {
+    let routes = Router::new()
+        .merge(chain_routes(path, tracker))
+        .nest(&path.chain_scope, chain_routes(path, tracker)) // same routes for a given chain
+        .route(&path.chains, get(list_chains));
+
+    if path.prefix.is_empty() {
+        routes
+    } else {
+        Router::new().nest(&path.prefix, routes)
+    }
+}
+
+// Routes served for the default chain, and for every chain under `chain_scope`
+fn chain_routes(path: &ApiPaths, tracker: &Arc<TxTracker>) -> Router<AppState> {
+    // the routes that send a transaction, refused once a shutdown drains
+    let write = |route: MethodRouter<AppState>| {
+        route.layer(middleware::from_fn_with_state(tracker.clone(), reject_writes_while_draining))
+    };
+
+    let routes = Router::new()
         .route(&path.verify, post(verify_signature))
         .route(&path.verify_status, get(check_status))
         .route(&path.signature, post(signature))
-        .route(&path.create_item, post(create_item))
+        .route(&path.create_item, write(post(create_item)))
         .route(&path.get_item, get(get_item))
         .route(&path.get_owner, get(get_owner))
-        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        .with_state(state)
-        .layer(CorsLayer::permissive()); // Optional: Enable CORS
+        .route(&path.revoke_item, write(post(revoke_item)))
+        .route(&path.transfer_item, write(post(transfer_item)))
+        .route(&path.item_history, get(item_history))
+        .route(&path.relayer_status, get(relayer_status))
+        .route(&path.sign_typed_data, post(sign_typed_data))
+        .route(&path.verify_typed_data, post(verify_typed_data))
+        .route(&path.ownership, write(post(register_asset)).get(asset_owner));
 
-    app
+    // routes added in v1, without a legacy alias
+    let v1_only = [
+        (&path.reissue_item, write(post(reissue_item))),
+        (&path.transaction, get(get_transaction)),
+        (&path.tracked_transactions, get(tracked_transactions)),
+        (&path.anchor_batch, write(post(anchor_batch))),
+        (&path.certificate_proof, get(certificate_proof)),
+        (&path.verify_proof, post(verify_proof)),
+        (&path.queue_certificate, post(queue_certificate)),
+        (&path.anchoring_status, get(anchoring_status)),
+        (&path.certificate_qr, post(certificate_qr)),
+        (&path.verify_certificate_qr, post(verify_certificate_qr)),
+        (&path.certificate_document, get(certificate_document)),
+    ];
+    v1_only
+        .into_iter()
+        .fold(routes, |routes, (path, route)| match path {
+            Some(path) => routes.route(path, route),
+            None => routes,
+        })
 }
\ No newline at end of file
@@ -1,40 +1,323 @@
 use std::env;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use anyhow::Error;
 use ethabi::ethereum_types::Address;
+use ethers::contract::ContractError;
+use ethers::types::transaction::eip712::EIP712Domain;
 use ethers::middleware::{Middleware, SignerMiddleware};
-use ethers::prelude::{Http, LocalWallet, Provider};
+use ethers::prelude::{LocalWallet, Provider};
 use ethers::signers::Signer;
+use ethers::utils::parse_ether;
+use sqlx::postgres::PgPoolOptions;
+use tracing::info;
+use crate::batch_store::{batch_proofs_dir, BatchStore};
+use crate::certificate_queue::{certificate_queue_file, CertificateQueue};
+use crate::certificate::AuthChain;
+use crate::chain_gateway::EthersGateway;
+use crate::chain_registry::{ChainContext, ChainRegistry};
+use crate::idempotency::{idempotency_file, IdempotencyStore};
+use crate::ignition::IgnitionDeployment;
+use crate::models::cert_model::auth_chain_domain;
+use crate::models::typed_data_model::TypedDataPolicy;
+use crate::relayer::{relayer_ledger_file, GasLedger, Relayer, RelayerConfig};
+use crate::rpc_client::{RetryConfig, RpcClient};
+use crate::tx_tracker::{resumed_tx_file, TxTracker};
 use crate::utility::AppState;
 
 pub async fn init_app_state() -> anyhow::Result<AppState, Error> {
     
-    // Initialize Ethereum client
-    let rpc_url = env::var("BASE_URL")?;
+    // Issuer wallet, the same key signs on every chain
     let private_key = env::var("PRIVATE_KEY")?;
+    let wallet = private_key.parse::<LocalWallet>()?;
+
+    // Relayer pays gas for user-signed requests, it falls back to the issuer wallet.
+    // Budget per chain, in that chain's native currency, spent amounts survive restarts
+    let relayer = RelayerConfig {
+        wallet: env::var("RELAYER_PRIVATE_KEY")
+            .ok()
+            .map(|key| key.parse::<LocalWallet>())
+            .transpose()?,
+        gas_budget: parse_ether(env::var("RELAYER_GAS_BUDGET_ETH").unwrap_or_else(|_| "0.05".to_string()))?,
+        ledger: Arc::new(GasLedger::open(relayer_ledger_file())?),
+    };
+
+    let retry = RetryConfig {
+        max_retries: env_number("RPC_MAX_RETRIES", 3)? as u32,
+        initial_backoff: Duration::from_millis(env_number("RPC_RETRY_BACKOFF_MS", 250)?),
+    };
+
+    // A single chain from BASE_URL and the *_CONTRACT variables, or every chain id listed
+    // in CHAINS (e.g. "84532,421614") from CHAIN_<id>_ prefixed ones, e.g. CHAIN_84532_BASE_URL
+    let chain_ids: Vec<Option<u64>> = match env::var("CHAINS") {
+        Ok(list) => list
+            .split(',')
+            .map(|id| id.trim().parse().map(Some))
+            .collect::<Result<_, _>>()
+            .map_err(|_| anyhow::anyhow!("Invalid CHAINS"))?,
+        Err(_) => vec![None],
+    };
+
+    // Transactions in flight on every chain, for a graceful shutdown, and what became
+    // of those resumed from earlier runs
+    let tx_tracker = Arc::new(TxTracker::open(resumed_tx_file())?);
+
+    let mut chains = Vec::new();
+    for expected_chain_id in chain_ids {
+        let prefix = expected_chain_id
+            .map(|chain_id| format!("CHAIN_{}_", chain_id))
+            .unwrap_or_default();
+
+        chains.push(
+            connect_chain(
+                &prefix,
+                expected_chain_id,
+                &wallet,
+                &relayer,
+                &retry,
+                &tx_tracker,
+            )
+            .await?,
+        );
+    }
+
+    // Chain of the routes without a /chains/{chain_id} prefix
+    let default_chain_id = match env::var("DEFAULT_CHAIN_ID") {
+        Ok(chain_id) => chain_id.parse().map_err(|_| anyhow::anyhow!("Invalid DEFAULT_CHAIN_ID"))?,
+        Err(_) => chains[0].chain_id,
+    };
+
+    // Typed data the issuer key may sign, e.g. "AssetOwnership:1:Asset:84532:0x3b5fFD911B70ed3E166e3197880809C1e85b34B3"
+    let typed_data_policy = TypedDataPolicy::parse(
+        &env::var("TYPED_DATA_ALLOW_LIST").unwrap_or_default(),
+    )?;
+
+    // Optional, only checked by /health/ready for now; connects on first use
+    let database = match env::var("DATABASE_URL") {
+        Ok(url) => Some(PgPoolOptions::new().max_connections(5).connect_lazy(&url)?),
+        Err(_) => None,
+    };
+
+    // Signers below this balance make the server not ready
+    let min_signer_balance = parse_ether(
+        env::var("MIN_SIGNER_BALANCE_ETH").unwrap_or_else(|_| "0.01".to_string()),
+    )?;
+
+    // How long the result of a certificate creation is replayed to retries, across restarts
+    let idempotency = IdempotencyStore::open(
+        idempotency_file(),
+        Duration::from_secs(env_number("IDEMPOTENCY_TTL_SECS", 24 * 60 * 60)?),
+    )?;
+
+    // Proofs of the certificates anchored in batches, kept next to the server
+    let batches = BatchStore::open(batch_proofs_dir())?;
+    // Certificates waiting for the batch worker, picked up again after a restart
+    let certificate_queue = CertificateQueue::open(certificate_queue_file())?;
+
+    // Initialize app state
+    let state = AppState {
+        chains: Arc::new(ChainRegistry::new(default_chain_id, chains)?),
+        wallet_address: wallet.address(), //will remove after test
+        typed_data_policy: Arc::new(typed_data_policy),
+        database,
+        min_signer_balance,
+        tx_tracker,
+        idempotency: Arc::new(idempotency),
+        batches: Arc::new(batches),
+        certificate_queue: Arc::new(certificate_queue),
+    };
     
-    let signature_verifier: Address = env::var("SIGNATURE_VERIFIER_CONTRACT")?
-        .parse()
-        .map_err(|_| anyhow::anyhow!("Invalid contract address"))?;
-    
-    let auth_chain: Address = env::var("AUTH_CHAIN_CONTRACT")?
+    Ok(state)
+}
+
+// Connect to one chain, reading its variables with `prefix`
+async fn connect_chain(
+    prefix: &str,
+    expected_chain_id: Option<u64>,
+    wallet: &LocalWallet,
+    relayer: &RelayerConfig,
+    retry: &RetryConfig,
+    tx_tracker: &Arc<TxTracker>,
+) -> anyhow::Result<ChainContext> {
+    let var = |key: &str| env::var(format!("{}{}", prefix, key));
+
+    // Initialize Ethereum client
+    // comma separated http(s)://, ws(s):// or IPC socket endpoints, in order of preference
+    let rpc_urls: Vec<String> = var("BASE_URL")
+        .map_err(|_| anyhow::anyhow!("{}BASE_URL is not set", prefix))?
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect();
+
+    let auth_chain_start_block: u64 = var("AUTH_CHAIN_START_BLOCK")
+        .unwrap_or_else(|_| "0".to_string())
         .parse()
-        .map_err(|_| anyhow::anyhow!("Invalid contract address"))?;
+        .map_err(|_| anyhow::anyhow!("Invalid start block"))?;
+
+    // endpoints that must agree on a read, 1 reads from the active endpoint only
+    let quorum = env_number(&format!("{}RPC_QUORUM", prefix), 1)? as usize;
 
-    let provider = Provider::<Http>::try_from(&rpc_url)?.interval(Duration::from_millis(1000));
+    let rpc_client = RpcClient::connect(&rpc_urls, retry.clone(), quorum).await?;
+    rpc_client.spawn_health_check(Duration::from_secs(env_number("RPC_HEALTH_CHECK_SECS", 15)?));
+
+    // only used when no endpoint pushes new blocks
+    let provider = Provider::new(rpc_client)
+        .interval(Duration::from_millis(env_number("RPC_POLL_INTERVAL_MS", 1000)?));
     let chain_id = provider.get_chainid().await?.as_u64();
-    
-    let wallet = private_key.parse::<LocalWallet>()?.with_chain_id(chain_id);
-    let eth_client = Arc::new(SignerMiddleware::new(provider, wallet.clone()));
 
-    // Initialize app state
-    let state = AppState {
+    if let Some(expected) = expected_chain_id {
+        if chain_id != expected {
+            return Err(anyhow::anyhow!("{}BASE_URL is chain {}, not {}", prefix, chain_id, expected));
+        }
+    }
+
+    let name = var("NAME").unwrap_or_else(|_| format!("chain-{}", chain_id));
+
+    // Contract addresses come from the env, else from the chain's Ignition deployment
+    let deployment = IgnitionDeployment::load(&ignition_dir(), chain_id)?;
+    let contract_address = |env_key: &str, contract_name: &str| -> anyhow::Result<Option<Address>> {
+        if let Ok(address) = var(env_key) {
+            return address
+                .parse()
+                .map(Some)
+                .map_err(|_| anyhow::anyhow!("Invalid contract address in {}{}", prefix, env_key));
+        }
+
+        let Some(deployment) = &deployment else { return Ok(None) };
+        let Some(contract) = deployment.contract(contract_name)? else { return Ok(None) };
+
+        info!(contract = contract_name, chain = %name, address = ?contract.address, future_id = %contract.future_id, "Contract from Ignition deployment");
+        let mismatches = contract.abi_mismatches(&artifacts_dir())?;
+        if !mismatches.is_empty() {
+            return Err(anyhow::anyhow!(
+                "{} at {:?} predates the compiled artifact, redeploy it: {}",
+                contract_name,
+                contract.address,
+                mismatches.join(", ")
+            ));
+        }
+        Ok(Some(contract.address))
+    };
+
+    let signature_verifier = contract_address("SIGNATURE_VERIFIER_CONTRACT", "SignatureVerifier")?
+        .ok_or_else(|| missing_contract(prefix, "SIGNATURE_VERIFIER_CONTRACT", chain_id))?;
+
+    let auth_chain = contract_address("AUTH_CHAIN_CONTRACT", "AuthChain")?
+        .ok_or_else(|| missing_contract(prefix, "AUTH_CHAIN_CONTRACT", chain_id))?;
+
+    check_auth_chain(&provider, chain_id, auth_chain).await?;
+
+    // Ownership contract is optional, its endpoints answer 503 without it
+    let ownership = contract_address("OWNERSHIP_CONTRACT", "Ownership")?;
+
+    let eth_client = Arc::new(SignerMiddleware::new(
+        provider.clone(),
+        wallet.clone().with_chain_id(chain_id),
+    ));
+
+    let relayer_client = match &relayer.wallet {
+        Some(relayer_wallet) => Arc::new(SignerMiddleware::new(
+            provider,
+            relayer_wallet.clone().with_chain_id(chain_id),
+        )),
+        None => eth_client.clone(),
+    };
+
+    let gateway = Arc::new(EthersGateway::new(
+        eth_client.clone(),
+        auth_chain,
+        auth_chain_start_block,
+        signature_verifier,
+        ownership,
+        tx_tracker.clone(),
+    ));
+
+    info!(chain = %name, chain_id, "Connected");
+
+    Ok(ChainContext {
+        chain_id,
+        name,
         eth_client,
+        gateway,
         signature_verifier,
         auth_chain,
-        wallet_address: wallet.address(), //will remove after test
+        ownership,
+        relayer: Arc::new(Relayer::new(
+            relayer_client,
+            auth_chain,
+            relayer.gas_budget,
+            relayer.ledger.clone(),
+            tx_tracker.clone(),
+        )),
+    })
+}
+
+// The address may come from the env, without an Ignition ABI to compare: reading an item
+// through the bindings fails to decode against a contract returning another Item.
+// Certificates are signed for `auth_chain_domain`, a contract with another domain would
+// reject every one of them
+async fn check_auth_chain(provider: &Provider<RpcClient>, chain_id: u64, auth_chain: Address) -> anyhow::Result<()> {
+    let contract = AuthChain::new(auth_chain, Arc::new(provider.clone()));
+
+    let (_, name, version, deployed_chain_id, verifying_contract, _, _) = contract
+        .eip_712_domain()
+        .call()
+        .await
+        .map_err(|e| anyhow::anyhow!("AuthChain at {:?} has no EIP-712 domain, redeploy it: {}", auth_chain, e))?;
+    let expected = auth_chain_domain(chain_id, auth_chain);
+    let deployed = EIP712Domain {
+        name: Some(name),
+        version: Some(version),
+        chain_id: Some(deployed_chain_id),
+        verifying_contract: Some(verifying_contract),
+        salt: None,
     };
-    
-    Ok(state)
-}
\ No newline at end of file
+    if deployed != expected {
+        return Err(anyhow::anyhow!(
+            "AuthChain at {:?} signs for the domain {:?}, the server for {:?}, redeploy it",
+            auth_chain,
+            deployed,
+            expected
+        ));
+    }
+
+    match contract.get_item(String::new()).call().await {
+        Ok(_) | Err(ContractError::Revert(_)) => Ok(()),
+        Err(ContractError::DecodingError(_) | ContractError::AbiError(_) | ContractError::DetokenizationError(_)) => {
+            Err(anyhow::anyhow!(
+                "AuthChain at {:?} does not return the Item of the compiled artifact, redeploy it",
+                auth_chain
+            ))
+        }
+        Err(e) => Err(anyhow::anyhow!("AuthChain at {:?} could not be read: {}", auth_chain, e)),
+    }
+}
+
+fn missing_contract(prefix: &str, env_key: &str, chain_id: u64) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Set {}{} or deploy it with Ignition to {}/chain-{}",
+        prefix,
+        env_key,
+        ignition_dir().display(),
+        chain_id
+    )
+}
+
+// Ignition deployment folders, one chain-<id> folder per chain
+fn ignition_dir() -> PathBuf {
+    PathBuf::from(env::var("IGNITION_DEPLOYMENTS_DIR").unwrap_or_else(|_| "ignition/deployments".to_string()))
+}
+
+// Hardhat artifacts the contract bindings are generated from
+fn artifacts_dir() -> PathBuf {
+    PathBuf::from(env::var("HARDHAT_ARTIFACTS_DIR").unwrap_or_else(|_| "artifacts".to_string()))
+}
+
+pub(crate) fn env_number(key: &str, default: u64) -> anyhow::Result<u64> {
+    match env::var(key) {
+        Ok(value) => value.parse().map_err(|_| anyhow::anyhow!("Invalid {}", key)),
+        Err(_) => Ok(default),
+    }
+}
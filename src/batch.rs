@@ -0,0 +1,301 @@
+use crate::batch_store::{BatchReservation, BatchStore, StoredBatch, StoredLeaf};
+use crate::certificate::sign_certificate;
+use crate::chain_gateway::{ApiError, GatewayError, Write};
+use crate::chain_registry::{Chain, ChainContext};
+use crate::merkle::MerkleTree;
+use crate::models::batch_model::{
+    AnchoredBatch, BatchDTO, BatchLeaf, CertificateProof, ProofVerification, ProofVerificationDTO, QueueCertificateDTO,
+    QueuedCertificate,
+};
+use crate::models::cert_model::{Certificate, ItemPath};
+use crate::models::simulation_model::{DryRunQuery, WriteResult};
+use crate::utility::{unix_now, AppState};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use ethers::types::{transaction::eip712::Eip712, Signature, H256};
+use ethers::utils::hex::ToHexExt;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{error, info, instrument, Instrument};
+
+// Handler for POST /v1/certificates/batches
+#[utoipa::path(
+    post,
+    path = "/v1/certificates/batches",
+    request_body = BatchDTO,
+    params(DryRunQuery),
+    responses(
+        (status = 200, description = "Merkle root of the certificates anchored with AuthChain.anchorBatch, proofs at /v1/certificates/{unique_id}/proof. A Simulation with ?dry_run=true", body = AnchoredBatch),
+        (status = 400, description = "Empty batch, invalid certificate or unique id given twice"),
+        (status = 409, description = "A certificate is already batched or anchored as an item, or the root already anchored"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, count = batch.certificates.len()))]
+pub async fn anchor_batch(
+    State(state): State<AppState>,
+    Chain(chain): Chain,
+    Query(query): Query<DryRunQuery>,
+    Json(batch): Json<BatchDTO>,
+) -> Result<Json<WriteResult<AnchoredBatch>>, ApiError> {
+    let mut unique_ids = HashSet::new();
+    let mut leaves = Vec::with_capacity(batch.certificates.len());
+    for dto in batch.certificates {
+        if !unique_ids.insert(dto.unique_id.clone()) {
+            return Err(ApiError {
+                status: StatusCode::BAD_REQUEST,
+                message: Some(format!("{} is in the batch twice", dto.unique_id)),
+            });
+        }
+        let certificate = Certificate::from_dto(dto, chain.auth_chain_domain()).map_err(|_| StatusCode::BAD_REQUEST)?;
+        let struct_hash = certificate.struct_hash().map_err(|_| StatusCode::BAD_REQUEST)?;
+        leaves.push(StoredLeaf {
+            unique_id: certificate.unique_id,
+            struct_hash: H256::from(struct_hash),
+            signature: None,
+        });
+    }
+
+    if leaves.is_empty() {
+        return Err(StatusCode::BAD_REQUEST.into());
+    }
+
+    if query.dry_run {
+        let unique_ids: Vec<String> = leaves.iter().map(|leaf| leaf.unique_id.clone()).collect();
+        reject_created_items(&chain, &unique_ids).await?;
+        let _reservation = reserve(&state.batches, chain.chain_id, &unique_ids)?;
+        let root = batch_root(&leaves);
+        let simulation = chain
+            .gateway
+            .simulate(Write::AnchorBatch { root, count: leaves.len() })
+            .await?;
+        return Ok(Json(WriteResult::Simulated(simulation)));
+    }
+
+    // Keeps going when the caller gives up, a root on-chain without its proofs is useless
+    let anchoring = tokio::spawn(
+        async move { anchor_leaves(&state.batches, &chain, leaves).await }.in_current_span(),
+    );
+    let anchored = anchoring
+        .await
+        .map_err(|_| ApiError::from(StatusCode::INTERNAL_SERVER_ERROR))??;
+
+    Ok(Json(WriteResult::Sent(anchored)))
+}
+
+// Anchor the root of a non-empty batch with AuthChain.anchorBatch and store its proofs
+pub(crate) async fn anchor_leaves(
+    batches: &Arc<BatchStore>,
+    chain: &ChainContext,
+    leaves: Vec<StoredLeaf>,
+) -> Result<AnchoredBatch, ApiError> {
+    let unique_ids: Vec<String> = leaves.iter().map(|leaf| leaf.unique_id.clone()).collect();
+    reject_created_items(chain, &unique_ids).await?;
+    let reservation = reserve(batches, chain.chain_id, &unique_ids)?;
+
+    let root = batch_root(&leaves);
+    let count = leaves.len();
+    let tx_hash = chain.gateway.anchor_batch(root, count).await?;
+    info!(root = ?root, count, tx_hash = ?tx_hash, "Batch anchored");
+
+    let anchored = AnchoredBatch {
+        root: root.encode_hex_with_prefix(),
+        count,
+        tx_hash: tx_hash.encode_hex_with_prefix(),
+        certificates: leaves
+            .iter()
+            .map(|leaf| BatchLeaf {
+                unique_id: leaf.unique_id.clone(),
+                struct_hash: leaf.struct_hash.encode_hex_with_prefix(),
+            })
+            .collect(),
+    };
+    reservation
+        .commit(StoredBatch {
+            chain_id: chain.chain_id,
+            root,
+            tx_hash,
+            leaves,
+        })
+        .map_err(|e| {
+            error!(root = ?root, tx_hash = ?tx_hash, error = %e, "Batch proofs not stored");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(anchored)
+}
+
+// An item created with createItem already has a proof of authenticity, a leaf for its
+// unique id could name another owner
+pub(crate) async fn reject_created_items(chain: &ChainContext, unique_ids: &[String]) -> Result<(), ApiError> {
+    for unique_id in unique_ids {
+        match chain.gateway.get_item(unique_id).await {
+            Ok(_) => {
+                return Err(ApiError {
+                    status: StatusCode::CONFLICT,
+                    message: Some(format!("{} is already anchored as an item", unique_id)),
+                })
+            }
+            Err(GatewayError::NotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+fn reserve(batches: &Arc<BatchStore>, chain_id: u64, unique_ids: &[String]) -> Result<BatchReservation, ApiError> {
+    batches.reserve(chain_id, unique_ids).map_err(|unique_id| ApiError {
+        status: StatusCode::CONFLICT,
+        message: Some(format!("{} is already batched", unique_id)),
+    })
+}
+
+fn batch_root(leaves: &[StoredLeaf]) -> H256 {
+    let struct_hashes: Vec<H256> = leaves.iter().map(|leaf| leaf.struct_hash).collect();
+    MerkleTree::new(&struct_hashes).map(|tree| tree.root()).unwrap_or_default()
+}
+
+// Handler for GET /v1/certificates/{unique_id}/proof
+#[utoipa::path(
+    get,
+    path = "/v1/certificates/{unique_id}/proof",
+    params(
+        ("unique_id" = String, Path, description = "Unique ID of a batched certificate")
+    ),
+    responses(
+        (status = 200, description = "Inclusion proof of the certificate in its anchored batch", body = CertificateProof),
+        (status = 404, description = "The certificate is in no batch")
+    )
+)]
+pub async fn certificate_proof(
+    State(state): State<AppState>,
+    Chain(chain): Chain,
+    Path(ItemPath { unique_id }): Path<ItemPath>,
+) -> Result<Json<CertificateProof>, StatusCode> {
+    state
+        .batches
+        .proof(chain.chain_id, &unique_id)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+// Handler for POST /v1/proofs/verifications
+#[utoipa::path(
+    post,
+    path = "/v1/proofs/verifications",
+    request_body = ProofVerificationDTO,
+    responses(
+        (status = 200, description = "Whether the proof leads from the certificate to a root anchored on-chain", body = ProofVerification),
+        (status = 400, description = "Invalid certificate or proof"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, unique_id = %dto.certificate.unique_id))]
+pub async fn verify_proof(
+    Chain(chain): Chain,
+    Json(dto): Json<ProofVerificationDTO>,
+) -> Result<Json<ProofVerification>, StatusCode> {
+    let certificate = Certificate::from_dto(dto.certificate, chain.auth_chain_domain()).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let struct_hash = H256::from(certificate.struct_hash().map_err(|_| StatusCode::BAD_REQUEST)?);
+    let proof = dto
+        .proof
+        .iter()
+        .map(|sibling| sibling.parse::<H256>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    // the contract folds the proof itself, the roots it knows are the ones that count
+    let (root, anchored_at) = chain
+        .gateway
+        .batch_inclusion(struct_hash, proof)
+        .await
+        .map_err(StatusCode::from)?;
+
+    Ok(Json(ProofVerification {
+        included: anchored_at.is_some(),
+        struct_hash: struct_hash.encode_hex_with_prefix(),
+        root: root.encode_hex_with_prefix(),
+        anchored_at,
+    }))
+}
+
+// Handler for POST /v1/certificates/queue
+#[utoipa::path(
+    post,
+    path = "/v1/certificates/queue",
+    request_body = QueueCertificateDTO,
+    responses(
+        (status = 202, description = "Queued for the batch worker, or already queued: anchoring status at /v1/certificates/{unique_id}/anchoring", body = QueuedCertificate),
+        (status = 400, description = "Invalid certificate, or a signature not from the issuer"),
+        (status = 409, description = "Another certificate is queued under the unique id, or it is anchored as an item"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, unique_id = %dto.certificate.unique_id))]
+pub async fn queue_certificate(
+    State(state): State<AppState>,
+    Chain(chain): Chain,
+    Json(dto): Json<QueueCertificateDTO>,
+) -> Result<(StatusCode, Json<QueuedCertificate>), ApiError> {
+    let certificate =
+        Certificate::from_dto(dto.certificate.clone(), chain.auth_chain_domain()).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let struct_hash = H256::from(certificate.struct_hash().map_err(|_| StatusCode::BAD_REQUEST)?);
+    reject_created_items(&chain, std::slice::from_ref(&certificate.unique_id)).await?;
+
+    let signature = match dto.signature {
+        // signed offline: only the issuer signature makes a certificate
+        Some(signature) => {
+            let signature: Signature = signature.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+            let digest = certificate.encode_eip712().map_err(|_| StatusCode::BAD_REQUEST)?;
+            let issuer = chain.gateway.get_owner().await?;
+            if signature.recover(digest).ok() != Some(issuer) {
+                return Err(GatewayError::InvalidSignature.into());
+            }
+            signature
+        }
+        None => sign_certificate(&chain, &certificate).await?,
+    };
+
+    let queued = state
+        .certificate_queue
+        .enqueue(
+            chain.chain_id,
+            dto.certificate,
+            signature.to_vec().encode_hex_with_prefix(),
+            struct_hash,
+            unix_now(),
+        )
+        .ok_or_else(|| ApiError {
+            status: StatusCode::CONFLICT,
+            message: Some(format!("Another certificate is queued as {}", certificate.unique_id)),
+        })?;
+
+    info!(status = ?queued.status, "Certificate queued");
+    Ok((StatusCode::ACCEPTED, Json(queued)))
+}
+
+// Handler for GET /v1/certificates/{unique_id}/anchoring
+#[utoipa::path(
+    get,
+    path = "/v1/certificates/{unique_id}/anchoring",
+    params(
+        ("unique_id" = String, Path, description = "Unique ID of a queued certificate")
+    ),
+    responses(
+        (status = 200, description = "Anchoring status of the certificate, its root and transaction once anchored", body = QueuedCertificate),
+        (status = 404, description = "The certificate was never queued")
+    )
+)]
+pub async fn anchoring_status(
+    State(state): State<AppState>,
+    Chain(chain): Chain,
+    Path(ItemPath { unique_id }): Path<ItemPath>,
+) -> Result<Json<QueuedCertificate>, StatusCode> {
+    state
+        .certificate_queue
+        .status(chain.chain_id, &unique_id)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
@@ -0,0 +1,222 @@
+use crate::merkle::MerkleTree;
+use crate::models::batch_model::CertificateProof;
+use ethers::types::H256;
+use ethers::utils::hex::ToHexExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+// A certificate batch whose Merkle root went on-chain with anchorBatch; its leaves are
+// enough to rebuild the tree and every proof
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct StoredBatch {
+    pub chain_id: u64,
+    pub root: H256,
+    pub tx_hash: H256,
+    pub leaves: Vec<StoredLeaf>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct StoredLeaf {
+    pub unique_id: String,
+    pub struct_hash: H256,
+    #[serde(default)]
+    pub signature: Option<String>, // issuer signature of certificates queued signed
+}
+
+struct Batch {
+    stored: StoredBatch,
+    tree: MerkleTree,
+}
+
+#[derive(Default)]
+struct Batches {
+    by_item: HashMap<(u64, String), (Arc<Batch>, usize)>, // batch and leaf index by chain and unique id
+    reserved: HashSet<(u64, String)>,                     // in batches being anchored
+}
+
+// Off-chain side of batch anchoring: the proofs of every anchored certificate, by chain
+// and unique id. With a directory each batch is also written to its own JSON file and
+// read back on start; without one they only live as long as the process.
+#[derive(Default)]
+pub struct BatchStore {
+    dir: Option<PathBuf>,
+    batches: Mutex<Batches>,
+}
+
+impl BatchStore {
+    pub fn open(dir: PathBuf) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        let store = Self {
+            dir: Some(dir.clone()),
+            batches: Mutex::default(),
+        };
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                let stored: StoredBatch = serde_json::from_str(&fs::read_to_string(&path)?)
+                    .map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e))?;
+                store.insert(stored)?;
+            }
+        }
+        Ok(store)
+    }
+
+    // Whether the unique id is in an anchored batch, or one being anchored
+    pub fn is_batched(&self, chain_id: u64, unique_id: &str) -> bool {
+        let batches = self.batches.lock().unwrap();
+        let key = (chain_id, unique_id.to_string());
+        batches.by_item.contains_key(&key) || batches.reserved.contains(&key)
+    }
+
+    // Hold the unique ids while their batch is anchored; an id already batched, or held
+    // by another batch, comes back as the error
+    pub fn reserve(self: &Arc<Self>, chain_id: u64, unique_ids: &[String]) -> Result<BatchReservation, String> {
+        let mut batches = self.batches.lock().unwrap();
+        let keys: Vec<(u64, String)> = unique_ids.iter().map(|id| (chain_id, id.clone())).collect();
+
+        if let Some((_, taken)) = keys
+            .iter()
+            .find(|key| batches.by_item.contains_key(*key) || batches.reserved.contains(*key))
+        {
+            return Err(taken.clone());
+        }
+        batches.reserved.extend(keys.iter().cloned());
+
+        Ok(BatchReservation {
+            store: self.clone(),
+            keys,
+        })
+    }
+
+    pub fn proof(&self, chain_id: u64, unique_id: &str) -> Option<CertificateProof> {
+        let batches = self.batches.lock().unwrap();
+        let (batch, index) = batches.by_item.get(&(chain_id, unique_id.to_string()))?;
+        let leaf = &batch.stored.leaves[*index];
+
+        Some(CertificateProof {
+            unique_id: leaf.unique_id.clone(),
+            struct_hash: leaf.struct_hash.encode_hex_with_prefix(),
+            signature: leaf.signature.clone(),
+            leaf_index: *index,
+            proof: batch
+                .tree
+                .proof(*index)?
+                .iter()
+                .map(|sibling| sibling.encode_hex_with_prefix())
+                .collect(),
+            root: batch.stored.root.encode_hex_with_prefix(),
+            tx_hash: batch.stored.tx_hash.encode_hex_with_prefix(),
+        })
+    }
+
+    fn insert(&self, stored: StoredBatch) -> anyhow::Result<()> {
+        let struct_hashes: Vec<H256> = stored.leaves.iter().map(|leaf| leaf.struct_hash).collect();
+        let tree = MerkleTree::new(&struct_hashes).ok_or_else(|| anyhow::anyhow!("Empty batch {:?}", stored.root))?;
+        if tree.root() != stored.root {
+            anyhow::bail!("Batch {:?} does not hash to its root", stored.root);
+        }
+
+        let batch = Arc::new(Batch { stored, tree });
+        let mut batches = self.batches.lock().unwrap();
+        for (index, leaf) in batch.stored.leaves.iter().enumerate() {
+            batches
+                .by_item
+                .insert((batch.stored.chain_id, leaf.unique_id.clone()), (batch.clone(), index));
+        }
+        Ok(())
+    }
+}
+
+// Unique ids held by a batch being anchored, released when dropped
+pub struct BatchReservation {
+    store: Arc<BatchStore>,
+    keys: Vec<(u64, String)>,
+}
+
+impl BatchReservation {
+    // The root is on-chain: serve the proofs, and keep them in the store directory
+    pub fn commit(self, stored: StoredBatch) -> anyhow::Result<()> {
+        let file = self
+            .store
+            .dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}-{:?}.json", stored.chain_id, stored.root)));
+        let json = serde_json::to_string_pretty(&stored)?;
+
+        self.store.insert(stored)?;
+        if let Some(file) = file {
+            fs::write(file, json)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BatchReservation {
+    fn drop(&mut self) {
+        let mut batches = self.store.batches.lock().unwrap();
+        for key in &self.keys {
+            batches.reserved.remove(key);
+        }
+    }
+}
+
+// Where batch proofs are kept between runs
+pub fn batch_proofs_dir() -> PathBuf {
+    PathBuf::from(std::env::var("BATCH_PROOFS_DIR").unwrap_or_else(|_| "batch_proofs".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::process_proof;
+
+    fn batch(chain_id: u64, unique_ids: &[&str]) -> StoredBatch {
+        let leaves: Vec<StoredLeaf> = unique_ids
+            .iter()
+            .map(|id| StoredLeaf { unique_id: id.to_string(), struct_hash: H256::random(), signature: None })
+            .collect();
+        let struct_hashes: Vec<H256> = leaves.iter().map(|leaf| leaf.struct_hash).collect();
+        StoredBatch {
+            chain_id,
+            root: MerkleTree::new(&struct_hashes).unwrap().root(),
+            tx_hash: H256::random(),
+            leaves,
+        }
+    }
+
+    #[test]
+    fn proofs_survive_a_restart() {
+        let dir = std::env::temp_dir().join(format!("batch-proofs-test-{}", std::process::id()));
+        let store = Arc::new(BatchStore::open(dir.clone()).unwrap());
+        let ids = ["XM-0001".to_string(), "XM-0002".to_string(), "XM-0003".to_string()];
+
+        let reservation = store.reserve(31337, &ids).unwrap();
+        assert_eq!(store.reserve(31337, &ids[2..]).err().as_deref(), Some("XM-0003"));
+        let stored = batch(31337, &["XM-0001", "XM-0002", "XM-0003"]);
+        reservation.commit(stored.clone()).unwrap();
+        assert!(store.reserve(31337, &ids[..1]).is_err());
+        assert!(store.reserve(84532, &ids[..1]).is_ok());
+
+        let reopened = BatchStore::open(dir.clone()).unwrap();
+        let proof = reopened.proof(31337, "XM-0002").unwrap();
+        let siblings: Vec<H256> = proof.proof.iter().map(|sibling| sibling.parse().unwrap()).collect();
+        assert_eq!(proof.leaf_index, 1);
+        assert_eq!(process_proof(stored.leaves[1].struct_hash, &siblings), stored.root);
+        assert!(reopened.proof(84532, "XM-0002").is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_batches_release_their_ids() {
+        let store = Arc::new(BatchStore::default());
+        let ids = ["XM-0001".to_string()];
+
+        drop(store.reserve(31337, &ids).unwrap());
+        assert!(store.reserve(31337, &ids).is_ok());
+    }
+}
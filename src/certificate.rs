@@ -1,20 +1,24 @@
-use crate::models::cert_model::{Certificate, CertificateDTO, ItemCreatedEvent, ItemEvent, Item, ItemInput};
-use crate::utility::{to_bytes, AppState};
+use crate::batch_store::BatchStore;
+use crate::chain_gateway::{ApiError, GatewayError, Write};
+use crate::chain_registry::{Chain, ChainContext};
+use crate::idempotency::{Claim, IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED};
+use crate::metrics::{CertificateOutcome, METRICS};
+use crate::models::simulation_model::{DryRunQuery, WriteResult};
+use crate::utility::AppState;
+use tracing::{error, info, instrument, Instrument};
+use crate::models::cert_model::{self, CertificateDTO, CreatedItem, ItemPath, ReissueDTO, ReissuedItem, RevokeDTO, RevokedItem};
 use axum::{
-    extract::{State, Path},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
     Json,
 
 };
-use ethers::utils::keccak256;
 use ethers::{
-    abi::RawLog,
-    contract::{abigen, EthEvent, EthLogDecode},
+    contract::abigen,
     prelude::*,
     signers::Signer,
-    types::Signature,
+    types::{transaction::eip712::Eip712, Signature},
 };
-use sha2::Digest;
 
 // abi path
 abigen!(
@@ -25,138 +29,276 @@ abigen!(
 
 #[utoipa::path(
     post,
-    path = "/create_item",
+    path = "/v1/certificates",
     request_body = CertificateDTO,
+    params(
+        DryRunQuery,
+        ("Idempotency-Key" = Option<String>, Header, description = "Retries with the same key get the original result instead of a second transaction")
+    ),
     responses(
-        (status = 200, description = "Successful Item Creation", body = String),
-        (status = 400, description = "Invalid input"),
+        (status = 200, description = "Successful Item Creation, or the result of the identical earlier request (Idempotent-Replayed header). A Simulation with ?dry_run=true", body = CreatedItem),
+        (status = 400, description = "Invalid input, or the transaction would revert"),
+        (status = 409, description = "The unique id is being or was anchored with another certificate, or is in a Merkle batch"),
+        (status = 422, description = "The Idempotency-Key was used for another certificate"),
         (status = 500, description = "Internal server error")
     )
 )]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, unique_id = %cert.unique_id))]
 pub async fn create_item(
     State(state): State<AppState>,
+    Chain(chain): Chain,
+    Query(query): Query<DryRunQuery>,
+    headers: HeaderMap,
     Json(cert): Json<CertificateDTO>,
-) -> anyhow::Result<Json<String>, axum::http::StatusCode> {
-    let certificate: Certificate = cert
-        .clone()
-        .try_into()
+) -> anyhow::Result<(HeaderMap, Json<WriteResult<CreatedItem>>), ApiError> {
+    let certificate = cert_model::Certificate::from_dto(cert, chain.auth_chain_domain())
         .map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    // accessing the wallet from SignerMiddleware
-    // Sign the certificate
-    let signature: Signature = state
-        .eth_client
-        .signer()
-        .sign_typed_data(&certificate)
-        .await
-        .map_err(|e| {
-            eprintln!("Signature error: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    eprintln!("Signature: {:?}", signature);
+    if query.dry_run {
+        reject_batched(&state.batches, chain.chain_id, &certificate.unique_id)?;
+        let signature = sign_certificate(&chain, &certificate).await?;
+        let simulation = chain.gateway.simulate(Write::CreateItem { certificate, signature }).await?;
+        return Ok((HeaderMap::new(), Json(WriteResult::Simulated(simulation))));
+    }
 
-    // Convert to contract certificate
-    let contract_cert: auth_chain::Certificate = certificate.into();
-    let sig_bytes = to_bytes(signature);
+    let key = headers
+        .get(IDEMPOTENCY_KEY)
+        .map(|key| key.to_str())
+        .transpose()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let fingerprint = H256::from(certificate.struct_hash().map_err(|_| StatusCode::BAD_REQUEST)?);
 
-    // Call create_item
-    let contract = AuthChain::new(state.auth_chain, state.eth_client.clone());
+    // Replay the result of an identical request, or wait for it while it is processed
+    let guard = loop {
+        match state.idempotency.claim(chain.chain_id, key, &certificate.unique_id, fingerprint) {
+            Claim::New(guard) => break guard,
+            Claim::Done(result) => return Ok(replayed(result)),
+            Claim::InFlight(mut pending) => {
+                let result = pending.wait_for(Option::is_some).await.map(|result| result.clone());
+                match result {
+                    Ok(Some(result)) => return Ok(replayed(result)),
+                    _ => continue, // it failed, this request goes ahead
+                }
+            }
+            Claim::KeyReused => return Err(StatusCode::UNPROCESSABLE_ENTITY.into()),
+            Claim::Conflict => return Err(StatusCode::CONFLICT.into()),
+        }
+    };
 
-    let receipt = contract
-        .create_item(contract_cert, sig_bytes)
-        .send()
-        .await
-        .map_err(|e| {
-            eprintln!("Transaction send error: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
+    // Keeps going when the caller gives up, so its retry gets the result instead of
+    // sending a second transaction
+    let anchoring = tokio::spawn(
+        async move {
+            let result = anchor_certificate(&chain, &state.batches, certificate, fingerprint).await?;
+            guard.complete(result.clone());
+            Ok::<_, ApiError>(result)
+        }
+        .in_current_span(),
+    );
+    let result = anchoring
         .await
-        .map_err(|e| {
-            eprintln!("Transaction confirmation error: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_| ApiError::from(StatusCode::INTERNAL_SERVER_ERROR))??;
 
-    if receipt.status != Some(1.into()) {
-        return Err(StatusCode::BAD_REQUEST);
-    }
+    Ok((HeaderMap::new(), Json(WriteResult::Sent(result))))
+}
 
+fn replayed(result: CreatedItem) -> (HeaderMap, Json<WriteResult<CreatedItem>>) {
+    let mut headers = HeaderMap::new();
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    (headers, Json(WriteResult::Sent(result)))
+}
 
-    let event_signature = H256::from(keccak256("ItemCreated(string,bytes32,address)"));
+// Sign the certificate with the issuer key and anchor it with AuthChain.createItem
+async fn anchor_certificate(
+    chain: &ChainContext,
+    batches: &BatchStore,
+    certificate: cert_model::Certificate,
+    fingerprint: H256,
+) -> Result<CreatedItem, ApiError> {
+    reject_batched(batches, chain.chain_id, &certificate.unique_id)?;
 
+    // AuthChain refuses a second certificate for a unique id, find out before signing
+    match chain.gateway.get_item(&certificate.unique_id).await {
+        Ok(item) if item.unique_id.parse::<H256>().ok() == Some(fingerprint) => {
+            // the same certificate, anchored by an earlier request
+            let item = CreatedItem::new(&certificate.unique_id, certificate.name, fingerprint, certificate.owner);
+            return Ok(item);
+        }
+        Ok(_) => return Err(GatewayError::Conflict("Item already exists".to_string()).into()),
+        Err(GatewayError::NotFound) => {}
+        Err(e) => return Err(e.into()),
+    }
 
-    let mut event_res = ItemCreatedEvent::init();
+    let signature = sign_certificate(chain, &certificate).await.inspect_err(|_| {
+        METRICS.certificate(chain.chain_id, CertificateOutcome::Failed);
+    })?;
 
-    for log in receipt.logs.iter() {
-        let raw_log = RawLog {
-            topics: log.topics.clone(),
-            data: log.data.clone().to_vec(),
-        };
+    METRICS.certificate(chain.chain_id, CertificateOutcome::Signed);
 
-        if let Ok(event) = <ItemCreatedEvent as EthEvent>::decode_log(&raw_log) {
-          
+    let event_res = chain
+        .gateway
+        .create_item(certificate, signature)
+        .await
+        .map_err(|e| {
+            METRICS.certificate(chain.chain_id, CertificateOutcome::Failed);
+            ApiError::from(e)
+        })?;
 
-           event_res = ItemCreatedEvent::new(event.name.clone(), event.unique_id, event.owner);
+    METRICS.certificate(chain.chain_id, CertificateOutcome::Anchored);
 
-            println!("📦 ItemCreated:");
-            println!("    name: {}", event.name);
-            println!("    unique_id: {:?}", event.unique_id);
-            println!("    owner: {:?}", event.owner);
-        }
+    Ok(event_res)
+}
+
+// A unique id anchored in a Merkle batch already has a proof of authenticity, a second
+// one through createItem could name another owner
+fn reject_batched(batches: &BatchStore, chain_id: u64, unique_id: &str) -> Result<(), ApiError> {
+    if batches.is_batched(chain_id, unique_id) {
+        return Err(GatewayError::Conflict(format!("{} is anchored in a batch", unique_id)).into());
     }
+    Ok(())
+}
 
-    Ok(Json(format!("Event: {:?}", event_res)))
+// accessing the wallet from SignerMiddleware
+pub(crate) async fn sign_certificate(
+    chain: &ChainContext,
+    certificate: &cert_model::Certificate,
+) -> Result<Signature, StatusCode> {
+    chain
+        .eth_client
+        .signer()
+        .sign_typed_data(certificate)
+        .await
+        .map_err(|e| {
+            error!(error = ?e, "Certificate signing failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
 }
 
 #[utoipa::path(
     get,
-    path = "/get_owner",
+    path = "/v1/issuers",
     responses(
         (status = 200, description = "Owner Address", body = String)
     )
 )]
 pub async fn get_owner(
-    State(state): State<AppState>,
+    Chain(chain): Chain,
 ) -> anyhow::Result<Json<Address>, axum::http::StatusCode> {
-    let contract = AuthChain::new(state.auth_chain, state.eth_client.clone());
-
-    let owner = contract.get_owner().call().await.map_err(|e| {
-        eprintln!("Transaction send error: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let owner = chain.gateway.get_owner().await.map_err(StatusCode::from)?;
 
 
       Ok(Json(owner))
 }
 #[utoipa::path(
     get,
-    path = "/get_item/{item_id}",
+    path = "/v1/items/{unique_id}",
     params(
-        ("item_id" = String, Path, description = "Item ID to retrieve")
+        ("unique_id" = String, Path, description = "Item ID to retrieve")
     ),
     responses(
-        (status = 200, description = "Item retrieved successfully", body = Item),
-        (status = 400, description = "Invalid item ID"),
+        (status = 200, description = "Item retrieved successfully", body = cert_model::Item),
+        (status = 404, description = "Item not found"),
         (status = 500, description = "Internal server error")
     )
 )]
 pub async fn get_item(
-    State(state): State<AppState>,
-    Path(item_id): Path<String>,
-) -> Result<Json<Item>, StatusCode> {
-    let contract = AuthChain::new(state.auth_chain, state.eth_client.clone());
+    Chain(chain): Chain,
+    Path(path): Path<ItemPath>,
+) -> Result<Json<cert_model::Item>, StatusCode> {
+    let item = chain.gateway.get_item(&path.unique_id).await.map_err(StatusCode::from)?;
 
-    let item = contract
-        .get_item(item_id)
-        .call()
-        .await
-        .map_err(|e| {
-            eprintln!("Contract call error: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    Ok(Json(item))
+}
 
-    let item: Item = item.into(); //convert contract Item to Rust Item
+#[utoipa::path(
+    post,
+    path = "/v1/certificates/{unique_id}/revocation",
+    params(
+        ("unique_id" = String, Path, description = "Unique ID of the certificate to revoke")
+    ),
+    params(DryRunQuery),
+    request_body = RevokeDTO,
+    responses(
+        (status = 200, description = "Certificate revoked, a Simulation with ?dry_run=true", body = RevokedItem),
+        (status = 403, description = "Backend wallet is not the issuer"),
+        (status = 404, description = "Certificate not found"),
+        (status = 409, description = "Certificate already revoked"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, unique_id = %unique_id))]
+pub async fn revoke_item(
+    Chain(chain): Chain,
+    Path(ItemPath { unique_id }): Path<ItemPath>,
+    Query(query): Query<DryRunQuery>,
+    Json(revoke): Json<RevokeDTO>,
+) -> Result<Json<WriteResult<RevokedItem>>, ApiError> {
+    if query.dry_run {
+        let write = Write::RevokeItem { unique_id, reason: revoke.reason };
+        return Ok(Json(WriteResult::Simulated(chain.gateway.simulate(write).await?)));
+    }
 
-    Ok(Json(item))
+    let tx_hash = chain.gateway.revoke_item(&unique_id, revoke.reason).await?;
+
+    info!(reason = ?revoke.reason, tx_hash = ?tx_hash, "RevokedItem");
+
+    Ok(Json(WriteResult::Sent(RevokedItem {
+        unique_id,
+        reason: revoke.reason,
+        tx_hash: format!("{:?}", tx_hash),
+    })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/certificates/{unique_id}/reissues",
+    params(
+        ("unique_id" = String, Path, description = "Unique ID of the certificate to replace")
+    ),
+    params(DryRunQuery),
+    request_body = ReissueDTO,
+    responses(
+        (status = 200, description = "Certificate reissued, the previous one stays in the item history. A Simulation with ?dry_run=true", body = ReissuedItem),
+        (status = 400, description = "Invalid input, a certificate for another unique id, or the transaction would revert"),
+        (status = 403, description = "Backend wallet is not the issuer"),
+        (status = 404, description = "Certificate not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, unique_id = %unique_id))]
+pub async fn reissue_item(
+    Chain(chain): Chain,
+    Path(ItemPath { unique_id }): Path<ItemPath>,
+    Query(query): Query<DryRunQuery>,
+    Json(reissue): Json<ReissueDTO>,
+) -> Result<Json<WriteResult<ReissuedItem>>, ApiError> {
+    if reissue.certificate.unique_id != unique_id || reissue.reason.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST.into());
+    }
+    let certificate = cert_model::Certificate::from_dto(reissue.certificate, chain.auth_chain_domain())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let hash = H256::from(certificate.struct_hash().map_err(|_| StatusCode::BAD_REQUEST)?);
+
+    let previous = chain.gateway.get_item(&unique_id).await?;
+
+    let signature = sign_certificate(&chain, &certificate).await?;
+    if query.dry_run {
+        let write = Write::ReissueItem { certificate, signature, reason: reissue.reason };
+        return Ok(Json(WriteResult::Simulated(chain.gateway.simulate(write).await?)));
+    }
+
+    let tx_hash = chain
+        .gateway
+        .reissue_item(certificate, signature, &reissue.reason)
+        .await?;
+
+    info!(previous_hash = %previous.unique_id, hash = ?hash, reason = %reissue.reason, tx_hash = ?tx_hash, "ItemReissued");
+
+    Ok(Json(WriteResult::Sent(ReissuedItem {
+        unique_id,
+        previous_hash: previous.unique_id,
+        hash: format!("{:?}", hash),
+        reason: reissue.reason,
+        tx_hash: format!("{:?}", tx_hash),
+    })))
 }
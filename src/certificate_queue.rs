@@ -0,0 +1,350 @@
+use crate::app_state::env_number;
+use crate::batch::anchor_leaves;
+use crate::batch_store::StoredLeaf;
+use crate::chain_registry::ChainContext;
+use crate::models::batch_model::{AnchoringStatus, QueuedCertificate};
+use crate::models::cert_model::CertificateDTO;
+use crate::utility::{unix_now, AppState};
+use ethers::types::H256;
+use ethers::utils::hex::ToHexExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+// A signed certificate waiting for the batch worker, with its anchoring status
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct QueueEntry {
+    pub certificate: CertificateDTO,
+    pub signature: String,
+    pub struct_hash: H256,
+    pub status: QueuedCertificate,
+}
+
+// When the batch worker anchors the queue, and how it retries
+#[derive(Clone, Debug)]
+pub struct BatchWorkerConfig {
+    pub poll: Duration,      // how often the queue is looked at
+    pub max_size: usize,     // a batch goes out as soon as it has this many certificates
+    pub max_wait: Duration,  // or once its oldest certificate waited this long
+    pub max_attempts: u32,   // failed batches are retried, each certificate this many times at most
+    pub backoff: Duration,   // wait before the first retry, doubled for every other one
+}
+
+impl BatchWorkerConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+        Ok(Self {
+            poll: Duration::from_secs(env_number("BATCH_POLL_SECS", default.poll.as_secs())?),
+            max_size: env_number("BATCH_MAX_SIZE", default.max_size as u64)?.max(1) as usize,
+            max_wait: Duration::from_secs(env_number("BATCH_MAX_WAIT_SECS", default.max_wait.as_secs())?),
+            max_attempts: env_number("BATCH_MAX_ATTEMPTS", default.max_attempts as u64)?.max(1) as u32,
+            backoff: Duration::from_secs(env_number("BATCH_RETRY_BACKOFF_SECS", default.backoff.as_secs())?),
+        })
+    }
+}
+
+impl Default for BatchWorkerConfig {
+    fn default() -> Self {
+        Self {
+            poll: Duration::from_secs(10),
+            max_size: 256,
+            max_wait: Duration::from_secs(300),
+            max_attempts: 5,
+            backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+// Certificates signed but not anchored yet, by chain and unique id. With a file the
+// queue is written to it on every change and read back on start.
+#[derive(Default)]
+pub struct CertificateQueue {
+    file: Option<PathBuf>,
+    entries: Mutex<HashMap<(u64, String), QueueEntry>>,
+}
+
+impl CertificateQueue {
+    pub fn open(file: PathBuf) -> anyhow::Result<Self> {
+        let mut entries = HashMap::new();
+        if file.exists() {
+            let persisted: Vec<QueueEntry> = serde_json::from_str(&fs::read_to_string(&file)?)
+                .map_err(|e| anyhow::anyhow!("Invalid {}: {}", file.display(), e))?;
+            for entry in persisted {
+                entries.insert((entry.status.chain_id, entry.status.unique_id.clone()), entry);
+            }
+        }
+
+        Ok(Self {
+            file: Some(file),
+            entries: Mutex::new(entries),
+        })
+    }
+
+    // Queue a certificate; queuing it again returns its status, a different certificate
+    // under the same unique id is refused
+    pub fn enqueue(
+        &self,
+        chain_id: u64,
+        certificate: CertificateDTO,
+        signature: String,
+        struct_hash: H256,
+        now: u64,
+    ) -> Option<QueuedCertificate> {
+        let mut entries = self.entries.lock().unwrap();
+        let key = (chain_id, certificate.unique_id.clone());
+        if let Some(entry) = entries.get(&key) {
+            return (entry.struct_hash == struct_hash).then(|| entry.status.clone());
+        }
+
+        let status = QueuedCertificate {
+            unique_id: certificate.unique_id.clone(),
+            chain_id,
+            struct_hash: struct_hash.encode_hex_with_prefix(),
+            status: AnchoringStatus::Queued,
+            attempts: 0,
+            last_error: None,
+            root: None,
+            tx_hash: None,
+            queued_at: now,
+            next_attempt_at: now,
+        };
+        entries.insert(
+            key,
+            QueueEntry {
+                certificate,
+                signature,
+                struct_hash,
+                status: status.clone(),
+            },
+        );
+        self.persist(&entries);
+        Some(status)
+    }
+
+    pub fn status(&self, chain_id: u64, unique_id: &str) -> Option<QueuedCertificate> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&(chain_id, unique_id.to_string()))
+            .map(|entry| entry.status.clone())
+    }
+
+    // The next batch of a chain: queued certificates due for an attempt, oldest first,
+    // once there are `max_size` of them or the oldest waited `max_wait`
+    pub fn due(&self, chain_id: u64, config: &BatchWorkerConfig, now: u64) -> Vec<QueueEntry> {
+        let entries = self.entries.lock().unwrap();
+        let mut due: Vec<&QueueEntry> = entries
+            .values()
+            .filter(|entry| {
+                entry.status.chain_id == chain_id
+                    && entry.status.status == AnchoringStatus::Queued
+                    && entry.status.next_attempt_at <= now
+            })
+            .collect();
+        due.sort_by_key(|entry| (entry.status.queued_at, entry.status.unique_id.clone()));
+
+        let waited_enough = due
+            .first()
+            .is_some_and(|oldest| oldest.status.queued_at + config.max_wait.as_secs() <= now);
+        if due.len() < config.max_size && !waited_enough {
+            return Vec::new();
+        }
+        due.into_iter().take(config.max_size).cloned().collect()
+    }
+
+    pub fn anchored(&self, chain_id: u64, unique_ids: &[String], root: &str, tx_hash: &str) {
+        self.update(chain_id, unique_ids, |status| {
+            status.status = AnchoringStatus::Anchored;
+            status.attempts += 1;
+            status.last_error = None;
+            status.root = Some(root.to_string());
+            status.tx_hash = Some(tx_hash.to_string());
+        });
+    }
+
+    // Retry after the backoff, or give up once the certificate had `max_attempts`
+    pub fn failed(&self, chain_id: u64, unique_ids: &[String], error: &str, config: &BatchWorkerConfig, now: u64) {
+        self.update(chain_id, unique_ids, |status| {
+            status.attempts += 1;
+            status.last_error = Some(error.to_string());
+            if status.attempts >= config.max_attempts {
+                status.status = AnchoringStatus::Failed;
+            } else {
+                let backoff = config.backoff.as_secs().saturating_mul(1 << (status.attempts - 1).min(16));
+                status.next_attempt_at = now + backoff;
+            }
+        });
+    }
+
+    // Give up at once, e.g. when the unique id was batched with another certificate
+    pub fn rejected(&self, chain_id: u64, unique_id: &str, error: &str) {
+        self.update(chain_id, &[unique_id.to_string()], |status| {
+            status.attempts += 1;
+            status.status = AnchoringStatus::Failed;
+            status.last_error = Some(error.to_string());
+        });
+    }
+
+    fn update(&self, chain_id: u64, unique_ids: &[String], change: impl Fn(&mut QueuedCertificate)) {
+        let mut entries = self.entries.lock().unwrap();
+        for unique_id in unique_ids {
+            if let Some(entry) = entries.get_mut(&(chain_id, unique_id.clone())) {
+                change(&mut entry.status);
+            }
+        }
+        self.persist(&entries);
+    }
+
+    fn persist(&self, entries: &HashMap<(u64, String), QueueEntry>) {
+        let Some(file) = &self.file else { return };
+
+        let mut persisted: Vec<&QueueEntry> = entries.values().collect();
+        persisted.sort_by_key(|entry| (entry.status.chain_id, entry.status.queued_at, entry.status.unique_id.clone()));
+        let written = serde_json::to_string_pretty(&persisted)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| fs::write(file, json).map_err(anyhow::Error::from));
+        if let Err(e) = written {
+            error!(error = %e, file = %file.display(), "Certificate queue not persisted");
+        }
+    }
+}
+
+// Where the certificate queue is kept between runs
+pub fn certificate_queue_file() -> PathBuf {
+    PathBuf::from(std::env::var("CERTIFICATE_QUEUE_FILE").unwrap_or_else(|_| "certificate_queue.json".to_string()))
+}
+
+// Anchor the queued certificates of every chain as they come due, until shutdown starts
+pub fn spawn_batch_worker(state: AppState, config: BatchWorkerConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.poll);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            // queued certificates wait for the next run rather than a shutdown
+            if state.tx_tracker.is_draining() {
+                break;
+            }
+            // and a shutdown that starts mid-run sends no further batch
+            for chain in state.chains.chains() {
+                if state.tx_tracker.is_draining() {
+                    break;
+                }
+                while !state.tx_tracker.is_draining() && anchor_due(&state, chain, &config, unix_now()).await > 0 {}
+            }
+        }
+    });
+}
+
+// Anchor the next batch of `chain` if one is due; the number of certificates anchored
+pub async fn anchor_due(state: &AppState, chain: &ChainContext, config: &BatchWorkerConfig, now: u64) -> usize {
+    let queue = &state.certificate_queue;
+    let mut leaves = Vec::new();
+    for entry in queue.due(chain.chain_id, config, now) {
+        let unique_id = entry.status.unique_id;
+        // batched by POST /v1/certificates/batches, or by a run that stopped before
+        // recording it
+        match state.batches.proof(chain.chain_id, &unique_id) {
+            Some(proof) if proof.struct_hash == entry.status.struct_hash => {
+                queue.anchored(chain.chain_id, &[unique_id], &proof.root, &proof.tx_hash);
+            }
+            Some(_) => queue.rejected(chain.chain_id, &unique_id, "Batched with another certificate"),
+            // created with createItem since it was queued, one failing id would hold up the batch
+            None if chain.gateway.get_item(&unique_id).await.is_ok() => {
+                queue.rejected(chain.chain_id, &unique_id, "Anchored as an item")
+            }
+            None => leaves.push(StoredLeaf {
+                unique_id,
+                struct_hash: entry.struct_hash,
+                signature: Some(entry.signature),
+            }),
+        }
+    }
+    if leaves.is_empty() {
+        return 0;
+    }
+
+    let unique_ids: Vec<String> = leaves.iter().map(|leaf| leaf.unique_id.clone()).collect();
+    match anchor_leaves(&state.batches, chain, leaves).await {
+        Ok(anchored) => {
+            info!(chain_id = chain.chain_id, root = %anchored.root, count = anchored.count, "Queued certificates anchored");
+            queue.anchored(chain.chain_id, &unique_ids, &anchored.root, &anchored.tx_hash);
+            anchored.count
+        }
+        Err(e) => {
+            let message = e.message.unwrap_or_else(|| e.status.to_string());
+            warn!(chain_id = chain.chain_id, count = unique_ids.len(), error = %message, "Batch anchoring failed");
+            queue.failed(chain.chain_id, &unique_ids, &message, config, now);
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn certificate(unique_id: &str) -> CertificateDTO {
+        CertificateDTO {
+            name: "Redmi Note 14".to_string(),
+            unique_id: unique_id.to_string(),
+            serial: "XM12345".to_string(),
+            date: 1746057600,
+            owner: "0x0000000000000000000000000000000000000001".to_string(),
+            metadata: vec![],
+        }
+    }
+
+    #[test]
+    fn batches_by_size_or_age() {
+        let queue = CertificateQueue::default();
+        let config = BatchWorkerConfig {
+            max_size: 2,
+            max_wait: Duration::from_secs(60),
+            ..BatchWorkerConfig::default()
+        };
+
+        let hash = H256::random();
+        queue.enqueue(31337, certificate("XM-0001"), "0x01".to_string(), hash, 1000).unwrap();
+        assert!(queue.enqueue(31337, certificate("XM-0001"), "0x01".to_string(), hash, 1001).is_some());
+        assert!(queue.enqueue(31337, certificate("XM-0001"), "0x01".to_string(), H256::random(), 1001).is_none());
+
+        assert!(queue.due(31337, &config, 1010).is_empty());
+        assert_eq!(queue.due(31337, &config, 1060).len(), 1); // waited long enough
+
+        queue.enqueue(31337, certificate("XM-0002"), "0x02".to_string(), H256::random(), 1020).unwrap();
+        queue.enqueue(31337, certificate("XM-0003"), "0x03".to_string(), H256::random(), 1030).unwrap();
+        let due: Vec<String> = queue.due(31337, &config, 1030).into_iter().map(|e| e.status.unique_id).collect();
+        assert_eq!(due, ["XM-0001", "XM-0002"]); // full, oldest first
+        assert!(queue.due(84532, &config, 2000).is_empty());
+    }
+
+    #[test]
+    fn retries_with_backoff_then_gives_up() {
+        let queue = CertificateQueue::default();
+        let config = BatchWorkerConfig {
+            max_wait: Duration::ZERO,
+            max_attempts: 3,
+            backoff: Duration::from_secs(30),
+            ..BatchWorkerConfig::default()
+        };
+        let ids = ["XM-0001".to_string()];
+        queue.enqueue(31337, certificate("XM-0001"), "0x01".to_string(), H256::random(), 1000).unwrap();
+
+        queue.failed(31337, &ids, "node down", &config, 1000);
+        assert!(queue.due(31337, &config, 1029).is_empty());
+        assert_eq!(queue.due(31337, &config, 1030).len(), 1);
+        queue.failed(31337, &ids, "node down", &config, 1030);
+        assert_eq!(queue.status(31337, "XM-0001").unwrap().next_attempt_at, 1090);
+        queue.failed(31337, &ids, "node down", &config, 1090);
+
+        let status = queue.status(31337, "XM-0001").unwrap();
+        assert_eq!(status.status, AnchoringStatus::Failed);
+        assert_eq!(status.attempts, 3);
+        assert_eq!(status.last_error.as_deref(), Some("node down"));
+        assert!(queue.due(31337, &config, 5000).is_empty());
+    }
+}
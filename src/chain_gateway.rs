@@ -0,0 +1,596 @@
+use crate::certificate::{auth_chain, AuthChain, AuthChainCalls, AuthChainErrors, AuthChainEvents};
+use crate::metrics::METRICS;
+use crate::models::cert_model::{Certificate, CreatedItem, Item, RevocationReason};
+use crate::models::sig_model::Asset;
+use crate::models::simulation_model::Simulation;
+use crate::models::transfer_model::{ProvenanceAction, ProvenanceEntry};
+use crate::rpc_client::wait_for_receipt;
+use crate::tx_tracker::{PendingTx, TxTracker};
+use crate::signature_verifier::{SignatureVerifier, SignatureVerifierErrors};
+use crate::utility::{to_bytes, EthClient};
+use crate::verify_ownership::Ownership;
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use ethers::{
+    abi::{AbiDecode, RawLog},
+    contract::{ContractError, EthEvent},
+    prelude::*,
+    types::Signature,
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
+use serde_json::json;
+use tracing::{error, info, instrument, warn, Span};
+
+// Gas limit sent with a write, in percent of the estimate: state can shift between
+// the estimate and inclusion, and an exact limit then runs out of gas
+pub(crate) const GAS_MARGIN_PERCENT: u64 = 120;
+
+// Why a chain call failed, independent of the transport behind the gateway
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GatewayError {
+    NotFound,
+    Unauthorized,
+    InvalidSignature,
+    Conflict(String),
+    Reverted(String),
+    Unavailable(String),
+    Rpc(String),
+}
+
+impl From<GatewayError> for StatusCode {
+    fn from(err: GatewayError) -> Self {
+        match err {
+            GatewayError::NotFound => StatusCode::NOT_FOUND,
+            GatewayError::Unauthorized => StatusCode::FORBIDDEN,
+            GatewayError::InvalidSignature | GatewayError::Reverted(_) => StatusCode::BAD_REQUEST,
+            GatewayError::Conflict(_) => StatusCode::CONFLICT,
+            GatewayError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            GatewayError::Rpc(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+// Error response of the write routes: the status, and the decoded revert or conflict
+// when there is one, so a caller learns why the transaction would fail
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: Option<String>,
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        Self { status, message: None }
+    }
+}
+
+impl From<GatewayError> for ApiError {
+    fn from(err: GatewayError) -> Self {
+        let message = match &err {
+            GatewayError::InvalidSignature => Some("Invalid signature".to_string()),
+            GatewayError::Conflict(message)
+            | GatewayError::Reverted(message)
+            | GatewayError::Unavailable(message) => Some(message.clone()),
+            _ => None, // RPC details stay in the logs
+        };
+        Self { status: err.into(), message }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self.message {
+            Some(message) => (self.status, Json(json!({ "error": message }))).into_response(),
+            None => self.status.into_response(),
+        }
+    }
+}
+
+// A contract write, sent by the gateway methods or only simulated
+pub enum Write {
+    CreateItem { certificate: Certificate, signature: Signature },
+    RevokeItem { unique_id: String, reason: RevocationReason },
+    ReissueItem { certificate: Certificate, signature: Signature, reason: String },
+    RegisterAsset { asset_id: String },
+    AnchorBatch { root: H256, count: usize },
+}
+
+impl Write {
+    pub fn action(&self) -> &'static str {
+        match self {
+            Write::CreateItem { .. } => "create_item",
+            Write::RevokeItem { .. } => "revoke_item",
+            Write::ReissueItem { .. } => "reissue_item",
+            Write::RegisterAsset { .. } => "register_asset",
+            Write::AnchorBatch { .. } => "anchor_batch",
+        }
+    }
+
+    // unique id, asset id or batch root the transaction is about
+    pub fn subject(&self) -> String {
+        match self {
+            Write::CreateItem { certificate, .. } | Write::ReissueItem { certificate, .. } => certificate.unique_id.clone(),
+            Write::RevokeItem { unique_id, .. } => unique_id.clone(),
+            Write::RegisterAsset { asset_id } => asset_id.clone(),
+            Write::AnchorBatch { root, .. } => format!("{:?}", root),
+        }
+    }
+}
+
+// The contract calls behind the HTTP handlers
+#[async_trait]
+pub trait ChainGateway: Send + Sync {
+    // anchor an issuer-signed certificate with AuthChain.createItem
+    async fn create_item(
+        &self,
+        certificate: Certificate,
+        signature: Signature,
+    ) -> Result<CreatedItem, GatewayError>;
+
+    async fn get_item(&self, unique_id: &str) -> Result<Item, GatewayError>;
+
+    // the issuer AuthChain accepts certificate signatures from
+    async fn get_owner(&self) -> Result<Address, GatewayError>;
+
+    async fn revoke_item(
+        &self,
+        unique_id: &str,
+        reason: RevocationReason,
+    ) -> Result<H256, GatewayError>;
+
+    // replace the certificate of an existing item with AuthChain.reissueItem, issuer only
+    async fn reissue_item(
+        &self,
+        certificate: Certificate,
+        signature: Signature,
+        reason: &str,
+    ) -> Result<H256, GatewayError>;
+
+    // created/transferred/revoked/reissued events of an item, oldest first
+    async fn item_history(&self, unique_id: &str) -> Result<Vec<ProvenanceEntry>, GatewayError>;
+
+    // the certificate and issuer signature an item was last written with, read back from
+    // its createItem or reissueItem calldata, and that transaction
+    async fn signed_certificate(&self, unique_id: &str) -> Result<(auth_chain::Certificate, Signature, H256), GatewayError>;
+
+    // SignatureVerifier.verifyAssetSignature, which reverts on a signer mismatch
+    async fn verify_asset(
+        &self,
+        asset: Asset,
+        signature: Signature,
+        signer: Address,
+    ) -> Result<bool, GatewayError>;
+
+    // Ownership.registerAsset from the backend wallet
+    async fn register_asset(&self, asset_id: &str) -> Result<H256, GatewayError>;
+
+    async fn get_asset_owner(&self, asset_id: &str) -> Result<Address, GatewayError>;
+
+    // AuthChain.anchorBatch of the Merkle root of `count` certificates
+    async fn anchor_batch(&self, root: H256, count: usize) -> Result<H256, GatewayError>;
+
+    // AuthChain.verifyBatchInclusion: the root the proof leads to from a certificate
+    // struct hash, and the block it was anchored at if it was
+    async fn batch_inclusion(&self, struct_hash: H256, proof: Vec<H256>) -> Result<(H256, Option<u64>), GatewayError>;
+
+    // any transaction of the chain with its receipt, None while it is pending
+    async fn transaction(&self, tx_hash: H256) -> Result<(Transaction, Option<TransactionReceipt>), GatewayError>;
+
+    // eth_call and eth_estimateGas of a write, with the revert it would hit; nothing is sent
+    async fn simulate(&self, write: Write) -> Result<Simulation, GatewayError>;
+}
+
+// Gateway backed by the deployed contracts through the SignerMiddleware client
+pub struct EthersGateway {
+    client: Arc<EthClient>,
+    auth_chain: Address,
+    auth_chain_start_block: u64,
+    signature_verifier: Address,
+    ownership: Option<Address>,
+    tracker: Arc<TxTracker>,
+}
+
+impl EthersGateway {
+    pub fn new(
+        client: Arc<EthClient>,
+        auth_chain: Address,
+        auth_chain_start_block: u64,
+        signature_verifier: Address,
+        ownership: Option<Address>,
+        tracker: Arc<TxTracker>,
+    ) -> Self {
+        Self {
+            client,
+            auth_chain,
+            auth_chain_start_block,
+            signature_verifier,
+            ownership,
+            tracker,
+        }
+    }
+
+    fn auth_chain(&self) -> AuthChain<EthClient> {
+        AuthChain::new(self.auth_chain, self.client.clone())
+    }
+
+    fn ownership(&self) -> Result<Ownership<EthClient>, GatewayError> {
+        let address = self
+            .ownership
+            .ok_or_else(|| GatewayError::Unavailable("OWNERSHIP_CONTRACT is not set".to_string()))?;
+        Ok(Ownership::new(address, self.client.clone()))
+    }
+
+    async fn on_chain_item(&self, unique_id: &str) -> Result<auth_chain::Item, GatewayError> {
+        let item = self
+            .auth_chain()
+            .get_item(unique_id.to_string())
+            .call()
+            .await
+            .map_err(rpc_error)?;
+
+        if item.unique_id == [0u8; 32] {
+            return Err(GatewayError::NotFound);
+        }
+        Ok(item)
+    }
+
+    // The AuthChain or Ownership contract call behind a write
+    fn write_call(&self, write: &Write) -> Result<ContractCall<EthClient, ()>, GatewayError> {
+        Ok(match write {
+            Write::CreateItem { certificate, signature } => self
+                .auth_chain()
+                .create_item(certificate.clone().into(), to_bytes(*signature)),
+            Write::RevokeItem { unique_id, reason } => self
+                .auth_chain()
+                .revoke_item(unique_id.clone(), (*reason).into()),
+            Write::ReissueItem { certificate, signature, reason } => self
+                .auth_chain()
+                .reissue_item(certificate.clone().into(), to_bytes(*signature), reason.clone()),
+            Write::RegisterAsset { asset_id } => self.ownership()?.register_asset(asset_id.clone()),
+            Write::AnchorBatch { root, count } => self.auth_chain().anchor_batch(root.0, (*count).into()),
+        })
+    }
+
+    // Simulate the write, a revert surfaces here before anything is paid for
+    async fn estimate(&self, write: &Write, call: &ContractCall<EthClient, ()>) -> Result<U256, GatewayError> {
+        let revert = |e: ContractError<EthClient>| {
+            warn!(action = write.action(), error = ?e, "Transaction simulation failed");
+            match write {
+                Write::RegisterAsset { .. } => ownership_error(e),
+                _ => auth_chain_error(e),
+            }
+        };
+
+        call.call().await.map_err(revert)?;
+        call.estimate_gas().await.map_err(revert)
+    }
+
+    // Simulate, then send with the estimate plus a margin and wait for the receipt
+    async fn send(&self, write: Write) -> Result<TransactionReceipt, GatewayError> {
+        let call = self.write_call(&write)?;
+        let gas = self.estimate(&write, &call).await?;
+
+        let call = call.gas(gas * GAS_MARGIN_PERCENT / 100);
+        let pending_tx = call.send().await.map_err(|e| {
+            error!(error = ?e, "Transaction send failed");
+            match write {
+                Write::RegisterAsset { .. } => ownership_error(e),
+                _ => auth_chain_error(e),
+            }
+        })?;
+        self.confirm(*pending_tx, write.action(), &write.subject()).await
+    }
+
+    // The event of type E emitted by AuthChain in a receipt; logs of other contracts
+    // and events are skipped, a log that matches but does not decode is an error
+    fn receipt_event<E: EthEvent>(&self, receipt: &TransactionReceipt) -> Result<Option<E>, GatewayError> {
+        let log = receipt
+            .logs
+            .iter()
+            .find(|log| log.address == self.auth_chain && log.topics.first() == Some(&E::signature()));
+        let Some(log) = log else { return Ok(None) };
+
+        let raw_log = RawLog {
+            topics: log.topics.clone(),
+            data: log.data.to_vec(),
+        };
+        E::decode_log(&raw_log)
+            .map(Some)
+            .map_err(|e| GatewayError::Rpc(format!("{} event does not decode: {}", E::name(), e)))
+    }
+
+    // Wait for the receipt of a sent transaction, a failed status is a revert.
+    // The transaction stays tracked until its receipt is in, so a shutdown can persist it.
+    async fn confirm(&self, tx_hash: H256, action: &str, subject: &str) -> Result<TransactionReceipt, GatewayError> {
+        let client = &self.client;
+        // the calling gateway method declares the field
+        Span::current().record("tx_hash", tracing::field::debug(tx_hash));
+        info!("Transaction sent");
+
+        self.tracker
+            .track(PendingTx::new(client.signer().chain_id(), tx_hash, action, subject));
+
+        let sent = Instant::now();
+        let receipt = wait_for_receipt(client.provider(), tx_hash)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Transaction confirmation failed");
+                GatewayError::Rpc(e.to_string())
+            })?;
+
+        self.tracker.finish(tx_hash);
+        let receipt = receipt.ok_or_else(|| GatewayError::Rpc("transaction dropped".to_string()))?;
+
+        METRICS.transaction(client.signer().chain_id(), sent, &receipt);
+        info!(
+            block = ?receipt.block_number,
+            gas_used = ?receipt.gas_used,
+            status = ?receipt.status,
+            "Transaction confirmed"
+        );
+
+        if receipt.status != Some(1.into()) {
+            return Err(GatewayError::Reverted(format!("{:?}", receipt.transaction_hash)));
+        }
+        Ok(receipt)
+    }
+}
+
+fn rpc_error(e: impl std::fmt::Debug) -> GatewayError {
+    error!(error = ?e, "Contract call failed");
+    GatewayError::Rpc(format!("{:?}", e))
+}
+
+fn ownership_error(e: ContractError<EthClient>) -> GatewayError {
+    match e.as_revert() {
+        Some(_) => GatewayError::Conflict("Asset already registered".to_string()),
+        None => rpc_error(e),
+    }
+}
+
+fn auth_chain_error(e: ContractError<EthClient>) -> GatewayError {
+    match e.decode_contract_revert::<AuthChainErrors>() {
+        Some(AuthChainErrors::Unauthorized(_)) => GatewayError::Unauthorized,
+        Some(AuthChainErrors::ItemNotFound(_)) => GatewayError::NotFound,
+        Some(AuthChainErrors::ItemAlreadyRevoked(_)) => GatewayError::Conflict("Item already revoked".to_string()),
+        Some(AuthChainErrors::ItemAlreadyExists(_)) => GatewayError::Conflict("Item already exists".to_string()),
+        Some(AuthChainErrors::BatchAlreadyAnchored(_)) => GatewayError::Conflict("Batch already anchored".to_string()),
+        Some(AuthChainErrors::InvalidSignature(_)) => GatewayError::InvalidSignature,
+        Some(other) => GatewayError::Reverted(format!("{:?}", other)),
+        None => rpc_error(e),
+    }
+}
+
+#[async_trait]
+impl ChainGateway for EthersGateway {
+    #[instrument(skip_all, fields(unique_id = %certificate.unique_id, tx_hash))]
+    async fn create_item(
+        &self,
+        certificate: Certificate,
+        signature: Signature,
+    ) -> Result<CreatedItem, GatewayError> {
+        let unique_id = certificate.unique_id.clone();
+        let receipt = self.send(Write::CreateItem { certificate, signature }).await?;
+
+        // a mined createItem always emits ItemCreated, without it the receipt is not ours
+        let created = self
+            .receipt_event::<auth_chain::ItemCreatedFilter>(&receipt)?
+            .ok_or_else(|| {
+                error!(tx_hash = ?receipt.transaction_hash, "ItemCreated event missing from the receipt");
+                GatewayError::Rpc("ItemCreated event missing from the receipt".to_string())
+            })?;
+        info!(name = %created.name, item_id = ?H256::from(created.unique_id), owner = ?created.owner, "ItemCreated");
+
+        let mut item = CreatedItem::new(&unique_id, created.name, created.unique_id.into(), created.owner);
+        item.tx_hash = Some(format!("{:?}", receipt.transaction_hash));
+        item.signature_check = self
+            .receipt_event::<auth_chain::DebugHashFilter>(&receipt)?
+            .map(Into::into);
+        Ok(item)
+    }
+
+    async fn get_item(&self, unique_id: &str) -> Result<Item, GatewayError> {
+        let item = self.on_chain_item(unique_id).await?;
+        Ok(Item::from_contract(item, self.client.signer().chain_id())) //convert contract Item to Rust Item
+    }
+
+    async fn get_owner(&self) -> Result<Address, GatewayError> {
+        self.auth_chain().get_owner().call().await.map_err(rpc_error)
+    }
+
+    #[instrument(skip(self), fields(tx_hash))]
+    async fn revoke_item(
+        &self,
+        unique_id: &str,
+        reason: RevocationReason,
+    ) -> Result<H256, GatewayError> {
+        let receipt = self
+            .send(Write::RevokeItem { unique_id: unique_id.to_string(), reason })
+            .await?;
+
+        Ok(receipt.transaction_hash)
+    }
+
+    #[instrument(skip_all, fields(unique_id = %certificate.unique_id, tx_hash))]
+    async fn reissue_item(
+        &self,
+        certificate: Certificate,
+        signature: Signature,
+        reason: &str,
+    ) -> Result<H256, GatewayError> {
+        let receipt = self
+            .send(Write::ReissueItem { certificate, signature, reason: reason.to_string() })
+            .await?;
+
+        Ok(receipt.transaction_hash)
+    }
+
+    async fn item_history(&self, unique_id: &str) -> Result<Vec<ProvenanceEntry>, GatewayError> {
+        let item = self.on_chain_item(unique_id).await?;
+
+        // Every lifecycle event indexes the item's on-chain id as its first topic, a reissue
+        // links to the id of the certificate it replaced
+        let mut history: Vec<(LogMeta, ProvenanceEntry)> = Vec::new();
+        let mut next = Some(H256::from(item.unique_id));
+        let mut seen = HashSet::new();
+
+        while let Some(on_chain_id) = next.take().filter(|id| seen.insert(*id)) {
+            let events = self
+                .auth_chain()
+                .events()
+                .from_block(self.auth_chain_start_block)
+                .topic1(on_chain_id)
+                .query_with_meta()
+                .await
+                .map_err(rpc_error)?;
+
+            for (event, meta) in events {
+                let (action, from, to, reason, note) = match event {
+                    AuthChainEvents::ItemCreatedFilter(e) => {
+                        (ProvenanceAction::Created, None, Some(e.owner), None, None)
+                    }
+                    AuthChainEvents::ItemTransferredFilter(e) => {
+                        (ProvenanceAction::Transferred, Some(e.from), Some(e.to), None, None)
+                    }
+                    AuthChainEvents::RevokedItemFilter(e) => (
+                        ProvenanceAction::Revoked,
+                        None,
+                        None,
+                        RevocationReason::try_from(e.reason).ok(),
+                        None,
+                    ),
+                    AuthChainEvents::ItemReissuedFilter(e) => {
+                        next = Some(H256::from(e.previous_unique_id));
+                        (ProvenanceAction::Reissued, None, Some(e.owner), None, Some(e.reason))
+                    }
+                    _ => continue,
+                };
+
+                let entry = ProvenanceEntry {
+                    action,
+                    from: from.map(|a| format!("{:?}", a)),
+                    to: to.map(|a| format!("{:?}", a)),
+                    reason,
+                    note,
+                    block_number: meta.block_number.as_u64(),
+                    tx_hash: format!("{:?}", meta.transaction_hash),
+                };
+                history.push((meta, entry));
+            }
+        }
+
+        history.sort_by_key(|(meta, _)| (meta.block_number, meta.log_index));
+
+        Ok(history.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    async fn signed_certificate(&self, unique_id: &str) -> Result<(auth_chain::Certificate, Signature, H256), GatewayError> {
+        let written = self
+            .item_history(unique_id)
+            .await?
+            .into_iter()
+            .rev()
+            .find(|entry| matches!(entry.action, ProvenanceAction::Created | ProvenanceAction::Reissued))
+            .ok_or(GatewayError::NotFound)?;
+        let tx_hash: H256 = written
+            .tx_hash
+            .parse()
+            .map_err(|_| GatewayError::Rpc(format!("Invalid transaction hash {}", written.tx_hash)))?;
+
+        let (tx, _) = self.transaction(tx_hash).await?;
+        let (certificate, signature) = match AuthChainCalls::decode(&tx.input) {
+            Ok(AuthChainCalls::CreateItem(call)) => (call.certificate, call.signature),
+            Ok(AuthChainCalls::ReissueItem(call)) => (call.certificate, call.signature),
+            // e.g. sent through a multisig, the certificate is somewhere in its calldata
+            _ => return Err(GatewayError::Rpc(format!("{:?} is not a direct AuthChain call", tx_hash))),
+        };
+        let signature = Signature::try_from(signature.as_ref()).map_err(|e| GatewayError::Rpc(e.to_string()))?;
+        Ok((certificate, signature, tx_hash))
+    }
+
+    async fn verify_asset(
+        &self,
+        asset: Asset,
+        signature: Signature,
+        signer: Address,
+    ) -> Result<bool, GatewayError> {
+        let contract = SignatureVerifier::new(self.signature_verifier, self.client.clone());
+
+        contract
+            .verify_asset_signature(asset.into(), to_bytes(signature), signer)
+            .call()
+            .await
+            .map_err(|e| match e.decode_contract_revert::<SignatureVerifierErrors>() {
+                Some(SignatureVerifierErrors::INVALID_SIGNATURE(_)) => GatewayError::InvalidSignature,
+                _ => rpc_error(e),
+            })
+    }
+
+    #[instrument(skip(self), fields(tx_hash))]
+    async fn register_asset(&self, asset_id: &str) -> Result<H256, GatewayError> {
+        let receipt = self
+            .send(Write::RegisterAsset { asset_id: asset_id.to_string() })
+            .await?;
+
+        Ok(receipt.transaction_hash)
+    }
+
+    async fn get_asset_owner(&self, asset_id: &str) -> Result<Address, GatewayError> {
+        let owner = self
+            .ownership()?
+            .get_owner(asset_id.to_string())
+            .call()
+            .await
+            .map_err(rpc_error)?;
+
+        if owner == Address::zero() {
+            return Err(GatewayError::NotFound);
+        }
+        Ok(owner)
+    }
+
+    #[instrument(skip(self), fields(tx_hash))]
+    async fn anchor_batch(&self, root: H256, count: usize) -> Result<H256, GatewayError> {
+        let receipt = self.send(Write::AnchorBatch { root, count }).await?;
+
+        info!("BatchAnchored");
+        Ok(receipt.transaction_hash)
+    }
+
+    async fn batch_inclusion(&self, struct_hash: H256, proof: Vec<H256>) -> Result<(H256, Option<u64>), GatewayError> {
+        let proof = proof.into_iter().map(|sibling| sibling.0).collect();
+        let (root, anchored_at) = self
+            .auth_chain()
+            .verify_batch_inclusion(struct_hash.0, proof)
+            .call()
+            .await
+            .map_err(rpc_error)?;
+
+        Ok((H256::from(root), (!anchored_at.is_zero()).then(|| anchored_at.as_u64())))
+    }
+
+    async fn transaction(&self, tx_hash: H256) -> Result<(Transaction, Option<TransactionReceipt>), GatewayError> {
+        let provider = self.client.provider();
+        let tx = provider
+            .get_transaction(tx_hash)
+            .await
+            .map_err(rpc_error)?
+            .ok_or(GatewayError::NotFound)?;
+        let receipt = provider.get_transaction_receipt(tx_hash).await.map_err(rpc_error)?;
+        Ok((tx, receipt))
+    }
+
+    #[instrument(skip_all, fields(action = write.action(), subject = write.subject()))]
+    async fn simulate(&self, write: Write) -> Result<Simulation, GatewayError> {
+        let call = self.write_call(&write)?;
+        let gas = self.estimate(&write, &call).await?;
+        let gas_price = self.client.get_gas_price().await.map_err(rpc_error)?;
+
+        info!(gas = %gas, "Transaction simulated");
+        Ok(Simulation::new(write.action(), &write.subject(), gas, gas_price))
+    }
+}
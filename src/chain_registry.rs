@@ -0,0 +1,130 @@
+use crate::chain_gateway::ChainGateway;
+use crate::models::cert_model::auth_chain_domain;
+use crate::models::chain_model::ChainInfo;
+use crate::models::sig_model::signature_verifier_domain;
+use crate::relayer::Relayer;
+use crate::utility::{AppState, EthClient};
+use axum::{
+    extract::{FromRequestParts, RawPathParams, State},
+    http::{request::Parts, StatusCode},
+    Json,
+};
+use ethers::types::{transaction::eip712::EIP712Domain, Address};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+// Client, contracts and relayer of one chain
+pub struct ChainContext {
+    pub chain_id: u64,
+    pub name: String,
+    pub eth_client: Arc<EthClient>,
+    pub gateway: Arc<dyn ChainGateway>,
+    pub signature_verifier: Address,
+    pub auth_chain: Address,
+    pub ownership: Option<Address>,
+    pub relayer: Arc<Relayer>,
+}
+
+impl ChainContext {
+    pub fn auth_chain_domain(&self) -> EIP712Domain {
+        auth_chain_domain(self.chain_id, self.auth_chain)
+    }
+
+    pub fn signature_verifier_domain(&self) -> EIP712Domain {
+        signature_verifier_domain(self.chain_id, self.signature_verifier)
+    }
+
+    pub fn info(&self, default: bool) -> ChainInfo {
+        ChainInfo {
+            chain_id: self.chain_id,
+            name: self.name.clone(),
+            auth_chain: format!("{:?}", self.auth_chain),
+            signature_verifier: format!("{:?}", self.signature_verifier),
+            ownership: self.ownership.map(|address| format!("{:?}", address)),
+            default,
+        }
+    }
+}
+
+// Chains the server is deployed on, keyed by chain id
+pub struct ChainRegistry {
+    default_chain_id: u64,
+    chains: BTreeMap<u64, Arc<ChainContext>>,
+}
+
+impl ChainRegistry {
+    pub fn new(default_chain_id: u64, chains: Vec<ChainContext>) -> anyhow::Result<Self> {
+        let mut registry = BTreeMap::new();
+
+        for chain in chains {
+            let chain_id = chain.chain_id;
+            if registry.insert(chain_id, Arc::new(chain)).is_some() {
+                return Err(anyhow::anyhow!("Chain {} is configured twice", chain_id));
+            }
+        }
+
+        if !registry.contains_key(&default_chain_id) {
+            return Err(anyhow::anyhow!("Default chain {} is not configured", default_chain_id));
+        }
+
+        Ok(Self {
+            default_chain_id,
+            chains: registry,
+        })
+    }
+
+    pub fn get(&self, chain_id: u64) -> Option<Arc<ChainContext>> {
+        self.chains.get(&chain_id).cloned()
+    }
+
+    pub fn default_chain(&self) -> Arc<ChainContext> {
+        self.chains[&self.default_chain_id].clone()
+    }
+
+    pub fn chains(&self) -> impl Iterator<Item = &Arc<ChainContext>> {
+        self.chains.values()
+    }
+}
+
+// The chain a request is for: the {chain_id} path parameter, else the default chain
+pub struct Chain(pub Arc<ChainContext>);
+
+impl FromRequestParts<AppState> for Chain {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, StatusCode> {
+        let chain_id = match RawPathParams::from_request_parts(parts, state).await {
+            Ok(params) => params
+                .iter()
+                .find(|(key, _)| *key == "chain_id")
+                .map(|(_, value)| value.parse::<u64>().map_err(|_| StatusCode::BAD_REQUEST))
+                .transpose()?,
+            Err(_) => None, // route without path parameters
+        };
+
+        match chain_id {
+            Some(chain_id) => state.chains.get(chain_id).map(Chain).ok_or(StatusCode::NOT_FOUND),
+            None => Ok(Chain(state.chains.default_chain())),
+        }
+    }
+}
+
+// Handler for GET /v1/chains
+#[utoipa::path(
+    get,
+    path = "/v1/chains",
+    responses(
+        (status = 200, description = "Chains served under /chains/{chain_id}", body = Vec<ChainInfo>)
+    )
+)]
+pub async fn list_chains(State(state): State<AppState>) -> Json<Vec<ChainInfo>> {
+    let default_chain_id = state.chains.default_chain().chain_id;
+
+    let chains = state
+        .chains
+        .chains()
+        .map(|chain| chain.info(chain.chain_id == default_chain_id))
+        .collect();
+
+    Json(chains)
+}
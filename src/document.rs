@@ -0,0 +1,408 @@
+use crate::chain_gateway::ApiError;
+use crate::chain_registry::Chain;
+use crate::models::cert_model::{Certificate, Item, ItemPath, RevocationReason};
+use crate::models::document_model::{DocumentFormat, DocumentQuery};
+use crate::qr_code::{encode, SignedCertificate};
+use axum::{
+    extract::{Path, Query},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
+use ethers::types::{Address, H256};
+use image::{imageops, GrayImage, Luma};
+use imageproc::drawing::{draw_filled_rect_mut, draw_text_mut};
+use imageproc::rect::Rect;
+use printpdf::{BuiltinFont, Line, Mm, PdfDocument, Point, Pt};
+use qrcode::{Color, QrCode};
+use tracing::instrument;
+
+// A4 in PDF points, the PNG is rendered at 2 pixels a point (144 dpi)
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const PX_PER_PT: f32 = 2.0;
+const MARGIN: f32 = 60.0;
+const VALUE_X: f32 = 170.0;
+const QR_SIZE: f32 = 180.0;
+const WRAP: usize = 66; // characters a line of a value holds, a transaction hash fits
+const MAX_LINES: usize = 3;
+
+// DejaVu Sans draws the PNG, the PDF uses the standard Helvetica; see assets/fonts/LICENSE
+static REGULAR_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+static BOLD_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf");
+
+// Printable certificate of authenticity: the fields of an anchored item, its issuer and
+// chain reference, and the QR code of the signed certificate, which verifies offline
+pub struct CertificateDocument {
+    pub fields: Vec<(&'static str, String)>,
+    pub qr: QrCode,
+}
+
+// What goes on the page, in points from the bottom left like PDF; `y` of a text is its
+// baseline, of the QR code its top
+enum Mark {
+    Text { bold: bool, size: f32, x: f32, y: f32, text: String },
+    Rule { y: f32 },
+    Qr { x: f32, y: f32 },
+}
+
+impl CertificateDocument {
+    // `item` as on-chain now, `signed` the certificate it was last written with in `tx_hash`
+    pub fn new(
+        item: &Item,
+        signed: &SignedCertificate,
+        issuer: Address,
+        chain_name: &str,
+        tx_hash: H256,
+    ) -> anyhow::Result<Self> {
+        let certificate = &signed.certificate;
+        let status = match (item.revoked, item.revocation_reason) {
+            (false, _) => "Valid".to_string(),
+            (true, Some(reason)) => format!("Revoked, {}", reason_text(reason)),
+            (true, None) => "Revoked".to_string(),
+        };
+
+        let mut fields = vec![
+            ("Product", item.name.clone()),
+            ("Serial number", item.serial.clone()),
+            ("Unique ID", certificate.unique_id.clone()),
+            ("Issued", format_date(&item.date)),
+            ("Owner", item.owner.clone()),
+            ("Status", status),
+            ("Issuer", format!("{:?}", issuer)),
+            ("Chain", format!("{} ({})", chain_name, item.chain_id)),
+            ("AuthChain", format!("{:?}", certificate.domain.verifying_contract.unwrap_or_default())),
+            ("Transaction", format!("{:?}", tx_hash)),
+            ("Certificate hash", item.unique_id.clone()),
+        ];
+        if !certificate.metadata.is_empty() {
+            fields.push(("Details", certificate.metadata.join(", ")));
+        }
+
+        Ok(Self {
+            fields,
+            qr: QrCode::new(encode(signed)?.as_bytes())?,
+        })
+    }
+
+    fn layout(&self) -> Vec<Mark> {
+        let text = |bold, size, x, y, text: &str| Mark::Text { bold, size, x, y, text: text.to_string() };
+        let mut marks = vec![
+            text(true, 20.0, MARGIN, 770.0, "Certificate of Authenticity"),
+            text(false, 9.0, MARGIN, 748.0, "Issued on-chain with AuthChain."),
+            text(false, 9.0, MARGIN, 735.0, "Scan the code to verify it offline."),
+            Mark::Qr { x: PAGE_WIDTH - MARGIN - QR_SIZE, y: PAGE_HEIGHT - 50.0 },
+            Mark::Rule { y: 600.0 },
+        ];
+
+        let mut y = 575.0;
+        for (label, value) in &self.fields {
+            marks.push(text(true, 10.0, MARGIN, y, label));
+            for line in wrap(value) {
+                marks.push(text(false, 10.0, VALUE_X, y, &line));
+                y -= 14.0;
+            }
+            y -= 8.0;
+        }
+        marks
+    }
+
+    pub fn to_pdf(&self) -> anyhow::Result<Vec<u8>> {
+        let pt = |pt: f32| Mm::from(Pt(pt));
+        let (pdf, page, layer) =
+            PdfDocument::new("Certificate of Authenticity", pt(PAGE_WIDTH), pt(PAGE_HEIGHT), "Certificate");
+        let regular = pdf.add_builtin_font(BuiltinFont::Helvetica)?;
+        let bold = pdf.add_builtin_font(BuiltinFont::HelveticaBold)?;
+        let layer = pdf.get_page(page).get_layer(layer);
+
+        for mark in self.layout() {
+            match mark {
+                Mark::Text { bold: is_bold, size, x, y, text } => {
+                    layer.use_text(text, size, pt(x), pt(y), if is_bold { &bold } else { &regular })
+                }
+                Mark::Rule { y } => {
+                    layer.set_outline_thickness(0.5);
+                    layer.add_line(Line {
+                        points: vec![
+                            (Point::new(pt(MARGIN), pt(y)), false),
+                            (Point::new(pt(PAGE_WIDTH - MARGIN), pt(y)), false),
+                        ],
+                        is_closed: false,
+                    });
+                }
+                Mark::Qr { x, y } => {
+                    // vector modules, one rectangle per run of dark ones after a 4 module quiet zone
+                    let module = QR_SIZE / (self.qr.width() + 8) as f32;
+                    for (row, start, length) in dark_runs(&self.qr) {
+                        let left = x + (start + 4) as f32 * module;
+                        let bottom = y - (row + 5) as f32 * module;
+                        layer.add_rect(printpdf::Rect::new(
+                            pt(left),
+                            pt(bottom),
+                            pt(left + length as f32 * module),
+                            pt(bottom + module),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(pdf.save_to_bytes()?)
+    }
+
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        let regular = FontRef::try_from_slice(REGULAR_FONT)?;
+        let bold = FontRef::try_from_slice(BOLD_FONT)?;
+        let px = |pt: f32| (pt * PX_PER_PT).round() as i32;
+        let black = Luma([0]);
+        let mut page = GrayImage::from_pixel(px(PAGE_WIDTH) as u32, px(PAGE_HEIGHT) as u32, Luma([0xff]));
+
+        for mark in self.layout() {
+            match mark {
+                Mark::Text { bold: is_bold, size, x, y, text } => {
+                    let font = if is_bold { &bold } else { &regular };
+                    let scale = PxScale::from(size * PX_PER_PT);
+                    // imageproc takes the top of the line, the layout gives the baseline
+                    let top = px(PAGE_HEIGHT - y) - font.as_scaled(scale).ascent().round() as i32;
+                    draw_text_mut(&mut page, black, px(x), top, scale, font, &text);
+                }
+                Mark::Rule { y } => draw_filled_rect_mut(
+                    &mut page,
+                    Rect::at(px(MARGIN), px(PAGE_HEIGHT - y)).of_size(px(PAGE_WIDTH - 2.0 * MARGIN) as u32, 1),
+                    black,
+                ),
+                Mark::Qr { x, y } => {
+                    // whole pixels a module keep the code sharp
+                    let module = (px(QR_SIZE) as u32 / (self.qr.width() as u32 + 8)).max(1);
+                    let code = self
+                        .qr
+                        .render::<Luma<u8>>()
+                        .quiet_zone(true)
+                        .module_dimensions(module, module)
+                        .build();
+                    imageops::overlay(&mut page, &code, px(x).into(), px(PAGE_HEIGHT - y).into());
+                }
+            }
+        }
+
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, page.width(), page.height());
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let dots_per_meter = ((72.0 * PX_PER_PT) / 0.0254).round() as u32;
+        encoder.set_pixel_dims(Some(png::PixelDimensions {
+            xppu: dots_per_meter,
+            yppu: dots_per_meter,
+            unit: png::Unit::Meter,
+        })); // prints at A4
+        encoder.write_header()?.write_image_data(page.as_raw())?;
+        Ok(png)
+    }
+}
+
+// Rows of a QR code as (row, first column, length) of each run of dark modules
+fn dark_runs(qr: &QrCode) -> Vec<(usize, usize, usize)> {
+    let width = qr.width();
+    let colors = qr.to_colors();
+    let mut runs = Vec::new();
+    for (row, modules) in colors.chunks(width).enumerate() {
+        let mut column = 0;
+        while column < width {
+            if modules[column] == Color::Dark {
+                let start = column;
+                while column < width && modules[column] == Color::Dark {
+                    column += 1;
+                }
+                runs.push((row, start, column - start));
+            } else {
+                column += 1;
+            }
+        }
+    }
+    runs
+}
+
+// Lines of at most WRAP characters, the last one cut short with "..." past MAX_LINES
+fn wrap(value: &str) -> Vec<String> {
+    let chars: Vec<char> = value.chars().collect();
+    let mut lines: Vec<String> = chars.chunks(WRAP).map(|line| line.iter().collect()).collect();
+    if lines.len() > MAX_LINES {
+        lines.truncate(MAX_LINES);
+        let last = &mut lines[MAX_LINES - 1];
+        *last = last.chars().take(WRAP - 3).collect::<String>() + "...";
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+fn reason_text(reason: RevocationReason) -> &'static str {
+    match reason {
+        RevocationReason::Counterfeit => "counterfeit",
+        RevocationReason::Recalled => "recalled",
+        RevocationReason::Stolen => "stolen",
+        RevocationReason::IssuedInError => "issued in error",
+    }
+}
+
+// YYYY-MM-DD in UTC of a unix timestamp, anything else as it is
+fn format_date(timestamp: &str) -> String {
+    let Ok(seconds) = timestamp.parse::<u64>() else {
+        return timestamp.to_string();
+    };
+
+    // days to civil date, from Howard Hinnant's chrono-compatible algorithms
+    let z = (seconds / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// Handler for GET /v1/certificates/{unique_id}/document
+#[utoipa::path(
+    get,
+    path = "/v1/certificates/{unique_id}/document",
+    params(
+        ("unique_id" = String, Path, description = "Unique ID of an anchored certificate"),
+        DocumentQuery
+    ),
+    responses(
+        (status = 200, description = "Printable A4 certificate of authenticity with the QR code of the signed certificate", content(
+            (String = "application/pdf"),
+            (String = "image/png")
+        )),
+        (status = 400, description = "Unknown format"),
+        (status = 404, description = "Certificate not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, unique_id = %unique_id, format = ?query.format))]
+pub async fn certificate_document(
+    Chain(chain): Chain,
+    Path(ItemPath { unique_id }): Path<ItemPath>,
+    Query(query): Query<DocumentQuery>,
+) -> Result<Response, ApiError> {
+    let item = chain.gateway.get_item(&unique_id).await?;
+    let (certificate, signature, tx_hash) = chain.gateway.signed_certificate(&unique_id).await?;
+    let issuer = chain.gateway.get_owner().await?;
+
+    let signed = SignedCertificate {
+        certificate: Certificate::from_contract(certificate, chain.auth_chain_domain()),
+        signature,
+    };
+    let document = CertificateDocument::new(&item, &signed, issuer, &chain.name, tx_hash)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let (content_type, extension, body) = match query.format {
+        DocumentFormat::Pdf => ("application/pdf", "pdf", document.to_pdf()),
+        DocumentFormat::Png => ("image/png", "png", document.to_png()),
+    };
+    let body = body.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let name: String = unique_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let disposition = format!("inline; filename=\"certificate-{}.{}\"", name, extension);
+
+    Ok(([(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)], body).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::cert_model::auth_chain_domain;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::U256;
+    use printpdf::lopdf;
+
+    async fn document(revoked: bool) -> CertificateDocument {
+        let issuer = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let certificate = Certificate {
+            name: "Redmi Note 14 (Ünïcode)".to_string(),
+            unique_id: "XM-0001".to_string(),
+            serial: "XM12345".to_string(),
+            date: U256::from(1746057600u64),
+            owner: Address::random(),
+            metadata: vec!["color:black".to_string(), "x".repeat(300)],
+            domain: auth_chain_domain(31337, Address::random()),
+        };
+        let item = Item {
+            name: certificate.name.clone(),
+            serial: certificate.serial.clone(),
+            date: certificate.date.to_string(),
+            unique_id: format!("{:?}", H256::random()),
+            owner: format!("{:?}", certificate.owner),
+            revoked,
+            revocation_reason: revoked.then_some(RevocationReason::IssuedInError),
+            chain_id: 31337,
+        };
+        let signature = issuer.sign_typed_data(&certificate).await.unwrap();
+        let signed = SignedCertificate { certificate, signature };
+        CertificateDocument::new(&item, &signed, issuer.address(), "anvil", H256::random()).unwrap()
+    }
+
+    #[test]
+    fn dates_are_utc_days() {
+        assert_eq!(format_date("1746057600"), "2025-05-01");
+        assert_eq!(format_date("0"), "1970-01-01");
+        assert_eq!(format_date("951782400"), "2000-02-29");
+        assert_eq!(format_date("2025-05-01"), "2025-05-01");
+    }
+
+    #[tokio::test]
+    async fn renders_pdf_and_png() {
+        let document = document(true).await;
+        assert!(document.fields.contains(&("Status", "Revoked, issued in error".to_string())));
+        assert!(document.fields.contains(&("Issued", "2025-05-01".to_string())));
+
+        let pdf = lopdf::Document::load_mem(&document.to_pdf().unwrap()).unwrap();
+        let pages = pdf.get_pages();
+        assert_eq!(pages.len(), 1);
+        let media_box: Vec<f32> = pdf
+            .get_object(pages[&1])
+            .and_then(lopdf::Object::as_dict)
+            .and_then(|page| page.get(b"MediaBox"))
+            .and_then(lopdf::Object::as_array)
+            .unwrap()
+            .iter()
+            .map(|n| n.as_float().unwrap().round()) // printpdf sizes pages in mm
+            .collect();
+        assert_eq!(media_box, [0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT]);
+        // the shown strings, decoded with the encoding the fonts declare
+        let fonts = pdf.get_page_fonts(pages[&1]);
+        assert!(fonts.values().all(|font| font.get_font_encoding() == "WinAnsiEncoding"));
+        let content = lopdf::content::Content::decode(&pdf.get_page_content(pages[&1]).unwrap()).unwrap();
+        let lines: Vec<String> = content
+            .operations
+            .iter()
+            .filter(|operation| operation.operator == "Tj")
+            .map(|operation| {
+                let bytes = operation.operands[0].as_str().unwrap();
+                lopdf::Document::decode_text(Some("WinAnsiEncoding"), bytes)
+            })
+            .collect();
+        assert!(lines.contains(&"Certificate of Authenticity".to_string()));
+        assert!(lines.contains(&"Redmi Note 14 (Ünïcode)".to_string()));
+        assert!(lines.last().unwrap().ends_with("...")); // cut after three lines
+
+        let png = document.to_png().unwrap();
+        let page = image::load_from_memory(&png).unwrap().to_luma8();
+        assert_eq!(page.dimensions(), (1190, 1684));
+        let info = png::Decoder::new(png.as_slice()).read_info().unwrap().info().clone();
+        assert_eq!(info.pixel_dims.unwrap().xppu, 5669); // 144 dpi
+        // the QR code is drawn pixel for pixel at the top right
+        let module = 360 / (document.qr.width() as u32 + 8);
+        let code = document.qr.render::<Luma<u8>>().quiet_zone(true).module_dimensions(module, module).build();
+        let drawn = imageops::crop_imm(&page, 1190 - 120 - 360, 100, code.width(), code.height()).to_image();
+        assert_eq!(drawn, code);
+        // and the text around it
+        let dark = page.pixels().filter(|pixel| pixel.0[0] < 0x80).count();
+        assert!(dark > code.pixels().filter(|pixel| pixel.0[0] < 0x80).count() + 10_000);
+    }
+}
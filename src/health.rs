@@ -0,0 +1,196 @@
+use crate::chain_registry::ChainContext;
+use crate::models::health_model::{ChainReadiness, HealthCheck, Readiness, ServiceInfo};
+use crate::utility::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use ethers::prelude::*;
+use ethers::utils::format_ether;
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+// How long one readiness check may take before it counts as failed
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Handler for the liveness probe, the process is up and serving requests
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    responses(
+        (status = 200, description = "Server is running")
+    )
+)]
+pub async fn health_live() -> StatusCode {
+    StatusCode::OK
+}
+
+// Handler for the readiness probe, every chain and the database are usable
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    responses(
+        (status = 200, description = "Ready to serve requests", body = Readiness),
+        (status = 503, description = "A check failed", body = Readiness)
+    )
+)]
+pub async fn health_ready(State(state): State<AppState>) -> (StatusCode, Json<Readiness>) {
+    let mut chains = Vec::new();
+    for chain in state.chains.chains() {
+        chains.push(chain_readiness(chain, state.min_signer_balance).await);
+    }
+
+    let database = match &state.database {
+        Some(pool) => Some(
+            check("database", async {
+                sqlx::query("SELECT 1")
+                    .execute(pool)
+                    .await
+                    .map(|_| "reachable".to_string())
+                    .map_err(|e| e.to_string())
+            })
+            .await,
+        ),
+        None => None,
+    };
+
+    let ready = chains.iter().flat_map(|chain| &chain.checks).all(|check| check.ok)
+        && database.as_ref().is_none_or(|check| check.ok);
+
+    if !ready {
+        warn!(failed = %failed_checks(&chains, database.as_ref()), "Not ready");
+    }
+
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    (status, Json(Readiness { ready, chains, database }))
+}
+
+// Handler for the build and deployment of the running server
+#[utoipa::path(
+    get,
+    path = "/info",
+    responses(
+        (status = 200, description = "Version, contracts and issuer", body = ServiceInfo)
+    )
+)]
+pub async fn info(State(state): State<AppState>) -> Json<ServiceInfo> {
+    let default_chain_id = state.chains.default_chain().chain_id;
+
+    Json(ServiceInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: env!("GIT_HASH").to_string(),
+        issuer: format!("{:?}", state.wallet_address),
+        default_chain_id,
+        chains: state
+            .chains
+            .chains()
+            .map(|chain| chain.info(chain.chain_id == default_chain_id))
+            .collect(),
+    })
+}
+
+async fn chain_readiness(chain: &ChainContext, min_signer_balance: U256) -> ChainReadiness {
+    let client = &chain.eth_client;
+    let mut checks = vec![check("rpc", async {
+        client
+            .get_block_number()
+            .await
+            .map(|block| format!("block {}", block))
+            .map_err(|e| e.to_string())
+    })
+    .await];
+
+    // no point in the remaining checks when the node does not answer
+    if checks[0].ok {
+        checks.push(
+            check("chain_id", async {
+                let chain_id = client.get_chainid().await.map_err(|e| e.to_string())?.as_u64();
+                if chain_id == chain.chain_id {
+                    Ok(format!("{}", chain_id))
+                } else {
+                    Err(format!("node is chain {}, configured for {}", chain_id, chain.chain_id))
+                }
+            })
+            .await,
+        );
+
+        let mut contracts = vec![
+            ("AuthChain", chain.auth_chain),
+            ("SignatureVerifier", chain.signature_verifier),
+        ];
+        contracts.extend(chain.ownership.map(|address| ("Ownership", address)));
+
+        for (name, address) in contracts {
+            checks.push(
+                check(&format!("contract:{}", name), async {
+                    let code = client.get_code(address, None).await.map_err(|e| e.to_string())?;
+                    if code.is_empty() {
+                        Err(format!("no code at {:?}", address))
+                    } else {
+                        Ok(format!("{:?}", address))
+                    }
+                })
+                .await,
+            );
+        }
+
+        let mut signers = vec![("issuer", client.address())];
+        if chain.relayer.address() != client.address() {
+            signers.push(("relayer", chain.relayer.address()));
+        }
+
+        for (name, address) in signers {
+            checks.push(
+                check(&format!("balance:{}", name), async {
+                    let balance = client.get_balance(address, None).await.map_err(|e| e.to_string())?;
+                    if balance >= min_signer_balance {
+                        Ok(format_ether(balance))
+                    } else {
+                        Err(format!(
+                            "{:?} has {}, below {}",
+                            address,
+                            format_ether(balance),
+                            format_ether(min_signer_balance)
+                        ))
+                    }
+                })
+                .await,
+            );
+        }
+    }
+
+    ChainReadiness {
+        chain_id: chain.chain_id,
+        name: chain.name.clone(),
+        checks,
+    }
+}
+
+// Run one check with CHECK_TIMEOUT
+async fn check(name: &str, probe: impl Future<Output = Result<String, String>>) -> HealthCheck {
+    let (ok, detail) = match tokio::time::timeout(CHECK_TIMEOUT, probe).await {
+        Ok(Ok(detail)) => (true, detail),
+        Ok(Err(err)) => (false, err),
+        Err(_) => (false, format!("timed out after {}s", CHECK_TIMEOUT.as_secs())),
+    };
+
+    HealthCheck {
+        name: name.to_string(),
+        ok,
+        detail,
+    }
+}
+
+fn failed_checks(chains: &[ChainReadiness], database: Option<&HealthCheck>) -> String {
+    chains
+        .iter()
+        .flat_map(|chain| {
+            chain
+                .checks
+                .iter()
+                .filter(|check| !check.ok)
+                .map(move |check| format!("{} {}: {}", chain.name, check.name, check.detail))
+        })
+        .chain(database.filter(|check| !check.ok).map(|check| format!("database: {}", check.detail)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,87 @@
+use axum::http::HeaderValue;
+use std::env;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::app_state::env_number;
+
+// PEM files the server terminates TLS with
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+// How the server listens and the HTTP layers around the routes
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    pub bind: SocketAddr,
+    pub tls: Option<TlsConfig>,
+    pub cors_origins: Option<Vec<HeaderValue>>, // None allows any origin
+    pub max_body_bytes: usize,
+    pub request_timeout: Duration,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            tls: None,
+            cors_origins: None,
+            max_body_bytes: 1024 * 1024,
+            // create_item waits for the receipt, leave room for a slow block
+            request_timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+impl HttpConfig {
+    // BIND_ADDRESS and PORT, TLS_CERT_PATH and TLS_KEY_PATH (both or neither),
+    // CORS_ALLOWED_ORIGINS (comma separated, unset or "*" allows any),
+    // MAX_BODY_BYTES and REQUEST_TIMEOUT_SECS
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = HttpConfig::default();
+
+        let address = env::var("BIND_ADDRESS").unwrap_or_else(|_| default.bind.ip().to_string());
+        let port = env_number("PORT", default.bind.port() as u64)?;
+        let bind = format!("{}:{}", address, port)
+            .parse()
+            .or_else(|_| format!("[{}]:{}", address, port).parse()) // IPv6
+            .map_err(|_| anyhow::anyhow!("Invalid BIND_ADDRESS or PORT"))?;
+
+        let tls = match (env::var("TLS_CERT_PATH"), env::var("TLS_KEY_PATH")) {
+            (Ok(cert_path), Ok(key_path)) => Some(TlsConfig {
+                cert_path: cert_path.into(),
+                key_path: key_path.into(),
+            }),
+            (Err(_), Err(_)) => None,
+            _ => return Err(anyhow::anyhow!("Set both TLS_CERT_PATH and TLS_KEY_PATH")),
+        };
+
+        let cors_origins = match env::var("CORS_ALLOWED_ORIGINS") {
+            Ok(origins) if origins.trim() != "*" => Some(
+                origins
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|origin| !origin.is_empty())
+                    .map(|origin| {
+                        HeaderValue::from_str(origin)
+                            .map_err(|_| anyhow::anyhow!("Invalid origin in CORS_ALLOWED_ORIGINS: {}", origin))
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            _ => None,
+        };
+
+        Ok(Self {
+            bind,
+            tls,
+            cors_origins,
+            max_body_bytes: env_number("MAX_BODY_BYTES", default.max_body_bytes as u64)? as usize,
+            request_timeout: Duration::from_secs(env_number(
+                "REQUEST_TIMEOUT_SECS",
+                default.request_timeout.as_secs(),
+            )?),
+        })
+    }
+}
@@ -0,0 +1,280 @@
+use crate::utility::unix_now;
+use crate::models::cert_model::CreatedItem;
+use ethers::types::H256;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::error;
+
+// Header a client sets to make retries of a write safe
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+// Set on responses replayed from an earlier request
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+// A request being processed, or its result kept for `ttl`
+struct Slot {
+    names: Vec<String>, // the Idempotency-Key and the unique id it is stored under
+    fingerprint: H256,  // what the request asked for, a reused key must ask for the same
+    result: watch::Sender<Option<CreatedItem>>,
+    finished_at: Mutex<Option<u64>>, // unix seconds
+}
+
+// A finished request as written to the file
+#[derive(Serialize, Deserialize)]
+struct StoredResult {
+    names: Vec<String>,
+    fingerprint: H256,
+    result: CreatedItem,
+    finished_at: u64,
+}
+
+// How a request relates to the ones seen before
+pub enum Claim {
+    // first of its kind, the caller does the work and completes the guard
+    New(IdempotencyGuard),
+    // the result of an earlier identical request
+    Done(CreatedItem),
+    // an identical request is still being processed, wait for it
+    InFlight(watch::Receiver<Option<CreatedItem>>),
+    // the key or the unique id was used for a different request
+    KeyReused,
+    Conflict,
+}
+
+// Results of certificate creations by chain, under the Idempotency-Key of the request
+// and under the unique id of the item, so a retry with or without the header never
+// sends a second transaction for the same item. Failed requests are forgotten, they
+// can be retried. With a file the finished results are written to it on every change
+// and read back on start, like the pending transactions; requests in flight during a
+// restart are caught by the on-chain lookup of the unique id instead.
+pub struct IdempotencyStore {
+    file: Option<PathBuf>,
+    slots: Mutex<HashMap<String, Arc<Slot>>>,
+    ttl: Duration,
+}
+
+impl Default for IdempotencyStore {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(24 * 60 * 60))
+    }
+}
+
+impl IdempotencyStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            file: None,
+            slots: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    pub fn open(file: PathBuf, ttl: Duration) -> anyhow::Result<Self> {
+        let mut slots = HashMap::new();
+        if file.exists() {
+            let persisted: Vec<StoredResult> = serde_json::from_str(&fs::read_to_string(&file)?)
+                .map_err(|e| anyhow::anyhow!("Invalid {}: {}", file.display(), e))?;
+            for stored in persisted {
+                let slot = Arc::new(Slot {
+                    names: stored.names,
+                    fingerprint: stored.fingerprint,
+                    result: watch::channel(Some(stored.result)).0,
+                    finished_at: Mutex::new(Some(stored.finished_at)),
+                });
+                for name in &slot.names {
+                    slots.insert(name.clone(), slot.clone());
+                }
+            }
+        }
+
+        Ok(Self {
+            file: Some(file),
+            slots: Mutex::new(slots),
+            ttl,
+        })
+    }
+
+    pub fn claim(self: &Arc<Self>, chain_id: u64, key: Option<&str>, unique_id: &str, fingerprint: H256) -> Claim {
+        let key = key.map(|key| format!("{}:key:{}", chain_id, key));
+        let item = format!("{}:item:{}", chain_id, unique_id);
+
+        let mut slots = self.slots.lock().unwrap();
+        let now = unix_now();
+        slots.retain(|_, slot| {
+            slot.finished_at
+                .lock()
+                .unwrap()
+                .is_none_or(|finished_at| now.saturating_sub(finished_at) < self.ttl.as_secs())
+        });
+
+        if let Some(slot) = key.as_ref().and_then(|key| slots.get(key)) {
+            if slot.fingerprint != fingerprint {
+                return Claim::KeyReused;
+            }
+            return Self::existing(slot);
+        }
+        if let Some(slot) = slots.get(&item) {
+            if slot.fingerprint != fingerprint {
+                return Claim::Conflict;
+            }
+            return Self::existing(slot);
+        }
+
+        let slot = Arc::new(Slot {
+            names: key.into_iter().chain([item]).collect(),
+            fingerprint,
+            result: watch::channel(None).0,
+            finished_at: Mutex::new(None),
+        });
+        for name in &slot.names {
+            slots.insert(name.clone(), slot.clone());
+        }
+
+        Claim::New(IdempotencyGuard {
+            store: self.clone(),
+            slot,
+            completed: false,
+        })
+    }
+
+    fn existing(slot: &Slot) -> Claim {
+        match slot.result.borrow().clone() {
+            Some(result) => Claim::Done(result),
+            None => Claim::InFlight(slot.result.subscribe()),
+        }
+    }
+
+    // Write the finished results, each once under the first of its names
+    fn persist(&self, slots: &HashMap<String, Arc<Slot>>) {
+        let Some(file) = &self.file else { return };
+
+        let mut persisted: Vec<StoredResult> = slots
+            .iter()
+            .filter(|(name, slot)| slot.names.first() == Some(*name))
+            .filter_map(|(_, slot)| {
+                Some(StoredResult {
+                    names: slot.names.clone(),
+                    fingerprint: slot.fingerprint,
+                    result: slot.result.borrow().clone()?,
+                    finished_at: (*slot.finished_at.lock().unwrap())?,
+                })
+            })
+            .collect();
+        persisted.sort_by(|a, b| (a.finished_at, &a.names).cmp(&(b.finished_at, &b.names)));
+
+        let written = serde_json::to_string_pretty(&persisted)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| fs::write(file, json).map_err(anyhow::Error::from));
+        if let Err(e) = written {
+            error!(error = %e, file = %file.display(), "Idempotency results not persisted");
+        }
+    }
+}
+
+// Where the results replayed to retries are kept between runs
+pub fn idempotency_file() -> PathBuf {
+    PathBuf::from(std::env::var("IDEMPOTENCY_FILE").unwrap_or_else(|_| "idempotency.json".to_string()))
+}
+
+// Held while a claimed request is processed, dropping it without `complete`
+// releases the key and the unique id
+pub struct IdempotencyGuard {
+    store: Arc<IdempotencyStore>,
+    slot: Arc<Slot>,
+    completed: bool,
+}
+
+impl IdempotencyGuard {
+    pub fn complete(mut self, result: CreatedItem) {
+        *self.slot.finished_at.lock().unwrap() = Some(unix_now());
+        self.slot.result.send_replace(Some(result));
+        self.completed = true;
+        self.store.persist(&self.store.slots.lock().unwrap());
+    }
+}
+
+impl Drop for IdempotencyGuard {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        let mut slots = self.store.slots.lock().unwrap();
+        for name in &self.slot.names {
+            if slots.get(name).is_some_and(|slot| Arc::ptr_eq(slot, &self.slot)) {
+                slots.remove(name);
+            }
+        }
+        // waiting requests see the sender go away and claim again
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Address;
+
+    #[test]
+    fn replays_by_key_and_by_unique_id() {
+        let store = Arc::new(IdempotencyStore::default());
+        let fingerprint = H256::random();
+
+        let Claim::New(guard) = store.claim(31337, Some("retry-1"), "XM-0001", fingerprint) else {
+            panic!("first request must be new");
+        };
+        assert!(matches!(store.claim(31337, None, "XM-0001", fingerprint), Claim::InFlight(_)));
+        let created = CreatedItem::new("XM-0001", "Redmi Note 14".to_string(), fingerprint, Address::random());
+        guard.complete(created.clone());
+
+        assert!(matches!(store.claim(31337, Some("retry-1"), "XM-0001", fingerprint), Claim::Done(r) if r == created));
+        assert!(matches!(store.claim(31337, Some("retry-2"), "XM-0001", fingerprint), Claim::Done(_)));
+        assert!(matches!(store.claim(31337, Some("retry-1"), "XM-0002", H256::random()), Claim::KeyReused));
+        assert!(matches!(store.claim(31337, None, "XM-0001", H256::random()), Claim::Conflict));
+        // other chains are independent
+        assert!(matches!(store.claim(84532, Some("retry-1"), "XM-0001", fingerprint), Claim::New(_)));
+    }
+
+    #[test]
+    fn failures_are_forgotten() {
+        let store = Arc::new(IdempotencyStore::default());
+        let fingerprint = H256::random();
+
+        let Claim::New(guard) = store.claim(31337, Some("retry-1"), "XM-0001", fingerprint) else {
+            panic!("first request must be new");
+        };
+        let Claim::InFlight(waiting) = store.claim(31337, Some("retry-1"), "XM-0001", fingerprint) else {
+            panic!("second request must wait");
+        };
+        drop(guard);
+
+        assert!(waiting.has_changed().is_err());
+        assert!(matches!(store.claim(31337, Some("retry-1"), "XM-0001", fingerprint), Claim::New(_)));
+    }
+
+    #[test]
+    fn results_survive_a_restart() {
+        let file = std::env::temp_dir().join(format!("idempotency-test-{}.json", std::process::id()));
+        let _ = fs::remove_file(&file);
+        let ttl = Duration::from_secs(60);
+        let fingerprint = H256::random();
+
+        let store = Arc::new(IdempotencyStore::open(file.clone(), ttl).unwrap());
+        let Claim::New(guard) = store.claim(31337, Some("retry-1"), "XM-0001", fingerprint) else {
+            panic!("first request must be new");
+        };
+        let created = CreatedItem::new("XM-0001", "Redmi Note 14".to_string(), fingerprint, Address::random());
+        guard.complete(created.clone());
+        let Claim::New(_in_flight) = store.claim(31337, None, "XM-0002", H256::random()) else {
+            panic!("first request must be new");
+        };
+
+        let restarted = Arc::new(IdempotencyStore::open(file.clone(), ttl).unwrap());
+        assert!(matches!(restarted.claim(31337, Some("retry-1"), "XM-0003", fingerprint), Claim::Done(r) if r == created));
+        assert!(matches!(restarted.claim(31337, None, "XM-0001", H256::random()), Claim::Conflict));
+        // only finished requests are kept
+        assert!(matches!(restarted.claim(31337, None, "XM-0002", H256::random()), Claim::New(_)));
+        fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+use ethers::abi::{Abi, ParamType};
+use ethers::types::Address;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Hardhat artifact, as written by `npx hardhat compile` and copied by Ignition
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Artifact {
+    contract_name: String,
+    source_name: String,
+    abi: Abi,
+}
+
+// A contract Ignition deployed on a chain
+#[derive(Debug, Clone)]
+pub struct DeployedContract {
+    pub future_id: String, // e.g. "OwnershipModule#AuthChain"
+    pub contract_name: String,
+    pub address: Address,
+    pub source_name: Option<String>,
+    abi: Option<Abi>,
+}
+
+// Contents of ignition/deployments/chain-<id>
+#[derive(Debug)]
+pub struct IgnitionDeployment {
+    pub chain_id: u64,
+    pub folder: PathBuf,
+    contracts: HashMap<String, Vec<DeployedContract>>, // by contract name
+}
+
+impl IgnitionDeployment {
+    // Read `<deployments>/chain-<chain_id>`, None when the chain has no deployment folder
+    pub fn load(deployments: &Path, chain_id: u64) -> anyhow::Result<Option<Self>> {
+        let folder = deployments.join(format!("chain-{}", chain_id));
+        let addresses_file = folder.join("deployed_addresses.json");
+
+        if !addresses_file.exists() {
+            return Ok(None);
+        }
+
+        let addresses: HashMap<String, Address> = serde_json::from_str(&fs::read_to_string(&addresses_file)?)
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", addresses_file.display(), e))?;
+
+        let mut contracts: HashMap<String, Vec<DeployedContract>> = HashMap::new();
+
+        for (future_id, address) in addresses {
+            let artifact_file = folder.join("artifacts").join(format!("{}.json", future_id));
+            // a missing artifact is fine, one that is there but unreadable would hide the ABI check
+            let artifact = if artifact_file.exists() {
+                let artifact = serde_json::from_str::<Artifact>(&fs::read_to_string(&artifact_file)?)
+                    .map_err(|e| anyhow::anyhow!("Invalid {}: {}", artifact_file.display(), e))?;
+                Some(artifact)
+            } else {
+                None
+            };
+
+            // the artifact names the contract, the future id usually ends with it
+            let contract_name = match &artifact {
+                Some(artifact) => artifact.contract_name.clone(),
+                None => future_id.rsplit('#').next().unwrap_or(&future_id).to_string(),
+            };
+
+            contracts.entry(contract_name.clone()).or_default().push(DeployedContract {
+                future_id,
+                contract_name,
+                address,
+                source_name: artifact.as_ref().map(|a| a.source_name.clone()),
+                abi: artifact.map(|a| a.abi),
+            });
+        }
+
+        Ok(Some(Self {
+            chain_id,
+            folder,
+            contracts,
+        }))
+    }
+
+    // The single deployment of `contract_name`, an error when several modules deployed it
+    pub fn contract(&self, contract_name: &str) -> anyhow::Result<Option<&DeployedContract>> {
+        match self.contracts.get(contract_name).map(Vec::as_slice) {
+            None | Some([]) => Ok(None),
+            Some([contract]) => Ok(Some(contract)),
+            Some(several) => Err(anyhow::anyhow!(
+                "{} is deployed several times in {} ({}), set its address explicitly",
+                contract_name,
+                self.folder.display(),
+                several.iter().map(|c| c.future_id.as_str()).collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+}
+
+impl DeployedContract {
+    // Functions of the compiled artifact the deployed contract does not have, or returns
+    // other types from, i.e. the deployment predates the bindings the server was built with
+    pub fn abi_mismatches(&self, artifacts: &Path) -> anyhow::Result<Vec<String>> {
+        let (Some(deployed), Some(source_name)) = (&self.abi, &self.source_name) else {
+            return Ok(Vec::new());
+        };
+
+        let artifact_file = artifacts
+            .join(source_name)
+            .join(format!("{}.json", self.contract_name));
+        let compiled: Artifact = serde_json::from_str(&fs::read_to_string(&artifact_file)?)
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", artifact_file.display(), e))?;
+
+        // a changed return type keeps the selector, the outputs are compared as well
+        let deployed: BTreeMap<[u8; 4], Vec<ParamType>> = deployed
+            .functions()
+            .map(|f| (f.short_signature(), f.outputs.iter().map(|o| o.kind.clone()).collect()))
+            .collect();
+
+        Ok(compiled
+            .abi
+            .functions()
+            .filter_map(|f| {
+                let outputs: Vec<ParamType> = f.outputs.iter().map(|o| o.kind.clone()).collect();
+                match deployed.get(&f.short_signature()) {
+                    None => Some(format!("{} is missing", f.name)),
+                    Some(deployed) if *deployed != outputs => Some(format!(
+                        "{} returns ({}) instead of ({})",
+                        f.name,
+                        param_types(deployed),
+                        param_types(&outputs)
+                    )),
+                    Some(_) => None,
+                }
+            })
+            .collect())
+    }
+}
+
+fn param_types(types: &[ParamType]) -> String {
+    types.iter().map(ToString::to_string).collect::<Vec<_>>().join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_base_sepolia_deployment() {
+        let deployment = IgnitionDeployment::load(Path::new("ignition/deployments"), 84532)
+            .unwrap()
+            .unwrap();
+
+        let auth_chain = deployment.contract("AuthChain").unwrap().unwrap();
+        assert_eq!(
+            auth_chain.address,
+            "0xC14CDcDb51EF45111dd2024AB1c003F49144928f".parse::<Address>().unwrap()
+        );
+        assert_eq!(auth_chain.future_id, "OwnershipModule#AuthChain");
+        assert!(deployment.contract("SignatureVerifier").unwrap().is_none());
+
+        // deployed before revocation and transfers were added
+        let mismatches = auth_chain.abi_mismatches(Path::new("artifacts")).unwrap();
+        assert!(mismatches.contains(&"revokeItem is missing".to_string()));
+        assert!(mismatches.contains(&"transferItem is missing".to_string()));
+        assert!(!mismatches.iter().any(|m| m.starts_with("createItem ")));
+    }
+
+    #[test]
+    fn missing_folder_is_not_an_error() {
+        assert!(IgnitionDeployment::load(Path::new("ignition/deployments"), 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn names_contracts_without_artifacts_after_the_future_id() {
+        let deployments = std::env::temp_dir().join(format!("ignition-test-{}", std::process::id()));
+        let folder = deployments.join("chain-31337");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(
+            folder.join("deployed_addresses.json"),
+            r#"{
+                "VerifierModule#SignatureVerifier": "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+                "A#Ownership": "0xe7f1725E7734CE288F8367e1Bb143E90bb3F0512",
+                "B#Ownership": "0x9fE46736679d2D9a65F0992F2272dE9f3c7fa6e0"
+            }"#,
+        )
+        .unwrap();
+
+        let deployment = IgnitionDeployment::load(&deployments, 31337).unwrap().unwrap();
+        fs::remove_dir_all(&deployments).unwrap();
+
+        let verifier = deployment.contract("SignatureVerifier").unwrap().unwrap();
+        assert_eq!(
+            verifier.address,
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3".parse::<Address>().unwrap()
+        );
+        assert!(verifier.abi_mismatches(Path::new("artifacts")).unwrap().is_empty());
+        assert!(deployment.contract("Ownership").is_err());
+    }
+
+    #[test]
+    fn invalid_artifacts_are_an_error() {
+        let deployments = std::env::temp_dir().join(format!("ignition-invalid-{}", std::process::id()));
+        let folder = deployments.join("chain-31337");
+        fs::create_dir_all(folder.join("artifacts")).unwrap();
+        fs::write(
+            folder.join("deployed_addresses.json"),
+            r#"{ "OwnershipModule#AuthChain": "0x5FbDB2315678afecb367f032d93F642f64180aa3" }"#,
+        )
+        .unwrap();
+        fs::write(folder.join("artifacts").join("OwnershipModule#AuthChain.json"), "{ truncated").unwrap();
+
+        let loaded = IgnitionDeployment::load(&deployments, 31337);
+        fs::remove_dir_all(&deployments).unwrap();
+
+        let error = loaded.unwrap_err().to_string();
+        assert!(error.contains("OwnershipModule#AuthChain.json"), "{}", error);
+    }
+}
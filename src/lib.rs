@@ -0,0 +1,32 @@
+pub mod server;
+pub mod signature_verifier;
+pub mod models;
+pub mod utility;
+pub mod signature;
+pub mod verify_ownership;
+pub mod certificate;
+pub mod qr_code;
+pub mod swagger_config;
+pub mod app_router;
+pub mod app_state;
+pub mod transfer;
+pub mod relayer;
+pub mod typed_data;
+pub mod chain_gateway;
+pub mod memory_gateway;
+pub mod rpc_client;
+pub mod chain_registry;
+pub mod ignition;
+pub mod health;
+pub mod metrics;
+pub mod telemetry;
+pub mod tx_tracker;
+pub mod shutdown;
+pub mod http_config;
+pub mod idempotency;
+pub mod tx_report;
+pub mod merkle;
+pub mod batch_store;
+pub mod batch;
+pub mod certificate_queue;
+pub mod document;
@@ -1,16 +1,4 @@
-mod server;
-mod signature_verifier;
-mod models;
-mod utility;
-mod signature;
-mod verify_ownership;
-mod certificate;
-mod qr_code;
-mod swagger_config;
-mod app_router;
-mod app_state;
-
-use server::*;
+use rust_solidity_test::server::*;
 
 
 #[tokio::main]
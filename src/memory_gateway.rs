@@ -0,0 +1,388 @@
+use crate::certificate::auth_chain;
+use crate::chain_gateway::{ChainGateway, GatewayError, Write};
+use crate::merkle;
+use crate::models::cert_model::{Certificate, CreatedItem, Item, RevocationReason, SignatureCheck};
+use crate::models::sig_model::Asset;
+use crate::models::simulation_model::Simulation;
+use crate::models::transfer_model::{ProvenanceAction, ProvenanceEntry};
+use async_trait::async_trait;
+use ethers::types::transaction::eip712::Eip712;
+use ethers::types::{Address, Signature, Transaction, TransactionReceipt, H256};
+use ethers::utils::hex::ToHexExt;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// What a simulated write reports, 1 gwei per gas
+const SIMULATED_GAS: u64 = 100_000;
+const SIMULATED_GAS_PRICE: u64 = 1_000_000_000;
+
+#[derive(Default)]
+struct Ledger {
+    items: HashMap<String, Item>,
+    history: HashMap<String, Vec<ProvenanceEntry>>,
+    written: HashMap<String, (Certificate, Signature, H256)>, // as the calldata of the last createItem/reissueItem
+    assets: HashMap<String, Address>,
+    batch_roots: HashMap<H256, u64>, // block each batch root was anchored at
+    block_number: u64,
+}
+
+impl Ledger {
+    // Every write lands in its own block with a fake transaction hash
+    fn next_tx(&mut self) -> (u64, H256) {
+        self.block_number += 1;
+        (self.block_number, H256::from_low_u64_be(self.block_number))
+    }
+}
+
+// In-memory stand-in for the contracts, enforcing the same rules they do.
+// Lets handlers be unit-tested without a node.
+pub struct InMemoryGateway {
+    chain_id: u64,
+    issuer: Address,
+    backend: Address,
+    ledger: Mutex<Ledger>,
+    failure: Mutex<Option<GatewayError>>,
+}
+
+impl InMemoryGateway {
+    // `issuer` signs certificates, `backend` is the wallet sending transactions
+    pub fn new(chain_id: u64, issuer: Address, backend: Address) -> Self {
+        Self {
+            chain_id,
+            issuer,
+            backend,
+            ledger: Mutex::new(Ledger::default()),
+            failure: Mutex::new(None),
+        }
+    }
+
+    // Make every following call fail with `error`, e.g. to simulate an unreachable RPC
+    pub fn fail_with(&self, error: GatewayError) {
+        *self.failure.lock().unwrap() = Some(error);
+    }
+
+    fn check_failure(&self) -> Result<(), GatewayError> {
+        match self.failure.lock().unwrap().clone() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    // The struct hash of an issuer-signed certificate, as verifyAssetSignature computes it
+    fn verify_certificate(&self, certificate: &Certificate, signature: &Signature) -> Result<H256, GatewayError> {
+        // the contract hashes under its own domain, a certificate for another chain cannot verify
+        let digest = certificate
+            .encode_eip712()
+            .map_err(|e| GatewayError::Rpc(e.to_string()))?;
+        if certificate.domain.chain_id != Some(self.chain_id.into())
+            || signature.recover(digest).ok() != Some(self.issuer)
+        {
+            return Err(GatewayError::InvalidSignature);
+        }
+
+        Ok(H256::from(
+            certificate
+                .struct_hash()
+                .map_err(|e| GatewayError::Rpc(e.to_string()))?,
+        ))
+    }
+
+    fn item(&self, certificate: &Certificate, struct_hash: H256) -> Item {
+        Item {
+            name: certificate.name.clone(),
+            serial: certificate.serial.clone(),
+            date: certificate.date.to_string(),
+            unique_id: struct_hash.encode_hex_with_prefix(),
+            owner: certificate.owner.encode_hex_with_prefix(),
+            revoked: false,
+            revocation_reason: None,
+            chain_id: self.chain_id,
+        }
+    }
+}
+
+#[async_trait]
+impl ChainGateway for InMemoryGateway {
+    async fn create_item(
+        &self,
+        certificate: Certificate,
+        signature: Signature,
+    ) -> Result<CreatedItem, GatewayError> {
+        self.check_failure()?;
+
+        let struct_hash = self.verify_certificate(&certificate, &signature)?;
+
+        let mut ledger = self.ledger.lock().unwrap();
+        if ledger.items.contains_key(&certificate.unique_id) {
+            return Err(GatewayError::Conflict("Item already exists".to_string()));
+        }
+        let (block_number, tx_hash) = ledger.next_tx();
+
+        ledger
+            .items
+            .insert(certificate.unique_id.clone(), self.item(&certificate, struct_hash));
+        ledger
+            .written
+            .insert(certificate.unique_id.clone(), (certificate.clone(), signature, tx_hash));
+        ledger
+            .history
+            .entry(certificate.unique_id.clone())
+            .or_default()
+            .push(ProvenanceEntry {
+                action: ProvenanceAction::Created,
+                from: None,
+                to: Some(format!("{:?}", certificate.owner)),
+                reason: None,
+                note: None,
+                block_number,
+                tx_hash: format!("{:?}", tx_hash),
+            });
+
+        let digest = certificate
+            .encode_eip712()
+            .map_err(|e| GatewayError::Rpc(e.to_string()))?;
+        let mut created = CreatedItem::new(&certificate.unique_id, certificate.name, struct_hash, certificate.owner);
+        created.tx_hash = Some(format!("{:?}", tx_hash));
+        created.signature_check = Some(SignatureCheck {
+            struct_hash: struct_hash.encode_hex_with_prefix(),
+            digest: H256::from(digest).encode_hex_with_prefix(),
+            signer: self.issuer.encode_hex_with_prefix(),
+        });
+        Ok(created)
+    }
+
+    async fn get_item(&self, unique_id: &str) -> Result<Item, GatewayError> {
+        self.check_failure()?;
+
+        self.ledger
+            .lock()
+            .unwrap()
+            .items
+            .get(unique_id)
+            .cloned()
+            .ok_or(GatewayError::NotFound)
+    }
+
+    async fn get_owner(&self) -> Result<Address, GatewayError> {
+        self.check_failure()?;
+        Ok(self.issuer)
+    }
+
+    async fn revoke_item(
+        &self,
+        unique_id: &str,
+        reason: RevocationReason,
+    ) -> Result<H256, GatewayError> {
+        self.check_failure()?;
+
+        if self.backend != self.issuer {
+            return Err(GatewayError::Unauthorized);
+        }
+
+        let mut ledger = self.ledger.lock().unwrap();
+        let item = ledger.items.get_mut(unique_id).ok_or(GatewayError::NotFound)?;
+
+        if item.revoked {
+            return Err(GatewayError::Conflict("Item already revoked".to_string()));
+        }
+        item.revoked = true;
+        item.revocation_reason = Some(reason);
+
+        let (block_number, tx_hash) = ledger.next_tx();
+        ledger
+            .history
+            .entry(unique_id.to_string())
+            .or_default()
+            .push(ProvenanceEntry {
+                action: ProvenanceAction::Revoked,
+                from: None,
+                to: None,
+                reason: Some(reason),
+                note: None,
+                block_number,
+                tx_hash: format!("{:?}", tx_hash),
+            });
+
+        Ok(tx_hash)
+    }
+
+    async fn reissue_item(
+        &self,
+        certificate: Certificate,
+        signature: Signature,
+        reason: &str,
+    ) -> Result<H256, GatewayError> {
+        self.check_failure()?;
+
+        if self.backend != self.issuer {
+            return Err(GatewayError::Unauthorized);
+        }
+        let struct_hash = self.verify_certificate(&certificate, &signature)?;
+
+        let mut ledger = self.ledger.lock().unwrap();
+        if !ledger.items.contains_key(&certificate.unique_id) {
+            return Err(GatewayError::NotFound);
+        }
+        let (block_number, tx_hash) = ledger.next_tx();
+
+        ledger
+            .items
+            .insert(certificate.unique_id.clone(), self.item(&certificate, struct_hash));
+        ledger
+            .written
+            .insert(certificate.unique_id.clone(), (certificate.clone(), signature, tx_hash));
+        ledger
+            .history
+            .entry(certificate.unique_id.clone())
+            .or_default()
+            .push(ProvenanceEntry {
+                action: ProvenanceAction::Reissued,
+                from: None,
+                to: Some(format!("{:?}", certificate.owner)),
+                reason: None,
+                note: Some(reason.to_string()),
+                block_number,
+                tx_hash: format!("{:?}", tx_hash),
+            });
+
+        Ok(tx_hash)
+    }
+
+    async fn item_history(&self, unique_id: &str) -> Result<Vec<ProvenanceEntry>, GatewayError> {
+        self.check_failure()?;
+
+        self.ledger
+            .lock()
+            .unwrap()
+            .history
+            .get(unique_id)
+            .cloned()
+            .ok_or(GatewayError::NotFound)
+    }
+
+    async fn signed_certificate(&self, unique_id: &str) -> Result<(auth_chain::Certificate, Signature, H256), GatewayError> {
+        self.check_failure()?;
+
+        let ledger = self.ledger.lock().unwrap();
+        let (certificate, signature, tx_hash) = ledger.written.get(unique_id).ok_or(GatewayError::NotFound)?;
+        Ok((certificate.clone().into(), *signature, *tx_hash))
+    }
+
+    async fn verify_asset(
+        &self,
+        asset: Asset,
+        signature: Signature,
+        signer: Address,
+    ) -> Result<bool, GatewayError> {
+        self.check_failure()?;
+
+        let digest = asset
+            .encode_eip712()
+            .map_err(|e| GatewayError::Rpc(e.to_string()))?;
+
+        // SignatureVerifier reverts instead of returning false
+        if signature.recover(digest).ok() != Some(signer) {
+            return Err(GatewayError::InvalidSignature);
+        }
+        Ok(true)
+    }
+
+    async fn register_asset(&self, asset_id: &str) -> Result<H256, GatewayError> {
+        self.check_failure()?;
+
+        let mut ledger = self.ledger.lock().unwrap();
+        if ledger.assets.contains_key(asset_id) {
+            return Err(GatewayError::Conflict("Asset already registered".to_string()));
+        }
+        ledger.assets.insert(asset_id.to_string(), self.backend);
+
+        Ok(ledger.next_tx().1)
+    }
+
+    async fn get_asset_owner(&self, asset_id: &str) -> Result<Address, GatewayError> {
+        self.check_failure()?;
+
+        self.ledger
+            .lock()
+            .unwrap()
+            .assets
+            .get(asset_id)
+            .copied()
+            .ok_or(GatewayError::NotFound)
+    }
+
+    async fn anchor_batch(&self, root: H256, _count: usize) -> Result<H256, GatewayError> {
+        self.check_failure()?;
+
+        if self.backend != self.issuer {
+            return Err(GatewayError::Unauthorized);
+        }
+        let mut ledger = self.ledger.lock().unwrap();
+        if ledger.batch_roots.contains_key(&root) {
+            return Err(GatewayError::Conflict("Batch already anchored".to_string()));
+        }
+        let (block_number, tx_hash) = ledger.next_tx();
+        ledger.batch_roots.insert(root, block_number);
+        Ok(tx_hash)
+    }
+
+    async fn batch_inclusion(&self, struct_hash: H256, proof: Vec<H256>) -> Result<(H256, Option<u64>), GatewayError> {
+        self.check_failure()?;
+
+        let root = merkle::process_proof(struct_hash, &proof);
+        Ok((root, self.ledger.lock().unwrap().batch_roots.get(&root).copied()))
+    }
+
+    // Nothing is broadcast, there is no transaction to look up
+    async fn transaction(&self, _tx_hash: H256) -> Result<(Transaction, Option<TransactionReceipt>), GatewayError> {
+        self.check_failure()?;
+        Err(GatewayError::NotFound)
+    }
+
+    // The checks of the write, without touching the ledger
+    async fn simulate(&self, write: Write) -> Result<Simulation, GatewayError> {
+        self.check_failure()?;
+
+        let ledger = self.ledger.lock().unwrap();
+        match &write {
+            Write::CreateItem { certificate, signature } => {
+                self.verify_certificate(certificate, signature)?;
+                if ledger.items.contains_key(&certificate.unique_id) {
+                    return Err(GatewayError::Conflict("Item already exists".to_string()));
+                }
+            }
+            Write::RevokeItem { unique_id, .. } => {
+                if self.backend != self.issuer {
+                    return Err(GatewayError::Unauthorized);
+                }
+                if ledger.items.get(unique_id).ok_or(GatewayError::NotFound)?.revoked {
+                    return Err(GatewayError::Conflict("Item already revoked".to_string()));
+                }
+            }
+            Write::ReissueItem { certificate, signature, .. } => {
+                if self.backend != self.issuer {
+                    return Err(GatewayError::Unauthorized);
+                }
+                self.verify_certificate(certificate, signature)?;
+                if !ledger.items.contains_key(&certificate.unique_id) {
+                    return Err(GatewayError::NotFound);
+                }
+            }
+            Write::RegisterAsset { asset_id } => {
+                if ledger.assets.contains_key(asset_id) {
+                    return Err(GatewayError::Conflict("Asset already registered".to_string()));
+                }
+            }
+            Write::AnchorBatch { root, .. } => {
+                if self.backend != self.issuer {
+                    return Err(GatewayError::Unauthorized);
+                }
+                if ledger.batch_roots.contains_key(root) {
+                    return Err(GatewayError::Conflict("Batch already anchored".to_string()));
+                }
+            }
+        }
+
+        Ok(Simulation::new(write.action(), &write.subject(), SIMULATED_GAS.into(), SIMULATED_GAS_PRICE.into()))
+    }
+}
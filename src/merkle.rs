@@ -0,0 +1,114 @@
+use ethers::types::H256;
+use ethers::utils::keccak256;
+
+// Merkle tree over certificate struct hashes, hashed the way AuthChain.verifyBatchInclusion
+// and OpenZeppelin's MerkleProof do: a leaf is keccak256(structHash), a node keccak256 of
+// its two children sorted, so a proof is just the siblings without left/right flags.
+// A leaf hashes 32 bytes and a node 64, one cannot be passed off as the other.
+pub fn leaf_hash(struct_hash: H256) -> H256 {
+    H256(keccak256(struct_hash))
+}
+
+pub fn node_hash(a: H256, b: H256) -> H256 {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    H256(keccak256([left.as_bytes(), right.as_bytes()].concat()))
+}
+
+// The root a proof leads to from a certificate struct hash
+pub fn process_proof(struct_hash: H256, proof: &[H256]) -> H256 {
+    proof
+        .iter()
+        .fold(leaf_hash(struct_hash), |node, sibling| node_hash(node, *sibling))
+}
+
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    levels: Vec<Vec<H256>>, // leaves first, the root alone last
+}
+
+impl MerkleTree {
+    // The leaves keep the order of `struct_hashes`. The last node of a level with an
+    // odd count moves up unpaired.
+    pub fn new(struct_hashes: &[H256]) -> Option<Self> {
+        if struct_hashes.is_empty() {
+            return None;
+        }
+
+        let mut levels = vec![struct_hashes.iter().copied().map(leaf_hash).collect::<Vec<_>>()];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(*left, *right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Some(Self { levels })
+    }
+
+    pub fn root(&self) -> H256 {
+        self.levels.last().unwrap()[0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    // Siblings from the leaf at `index` up to the root
+    pub fn proof(&self, mut index: usize) -> Option<Vec<H256>> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut proof = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_proof_leads_to_the_root() {
+        for count in 1..=9 {
+            let struct_hashes: Vec<H256> = (0..count).map(|_| H256::random()).collect();
+            let tree = MerkleTree::new(&struct_hashes).unwrap();
+
+            for (index, struct_hash) in struct_hashes.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert_eq!(process_proof(*struct_hash, &proof), tree.root(), "{} of {}", index, count);
+            }
+            assert_ne!(process_proof(H256::random(), &tree.proof(0).unwrap()), tree.root());
+            assert!(tree.proof(count).is_none());
+        }
+        assert!(MerkleTree::new(&[]).is_none());
+    }
+
+    #[test]
+    fn hashes_like_merkle_proof() {
+        let (a, b, c) = (H256::repeat_byte(1), H256::repeat_byte(2), H256::repeat_byte(3));
+        let tree = MerkleTree::new(&[a, b, c]).unwrap();
+
+        // sorted pairs: the root does not depend on which side a sibling is on
+        let ab = node_hash(leaf_hash(b), leaf_hash(a));
+        assert_eq!(tree.root(), node_hash(leaf_hash(c), ab));
+        assert_eq!(MerkleTree::new(&[a]).unwrap().root(), leaf_hash(a));
+    }
+}
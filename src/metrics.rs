@@ -0,0 +1,240 @@
+use crate::utility::AppState;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ethers::prelude::*;
+use ethers::utils::format_ether;
+use prometheus::{
+    CounterVec, Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use tracing::{error, warn};
+
+// A scrape should not hang on a node that does not answer
+const BALANCE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Process wide metrics, the RPC client and gateways record into it without access to the state
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: HistogramVec,    // method, route, status
+    certificates: IntCounterVec,    // chain_id, outcome
+    tx_confirmation: HistogramVec,  // chain_id
+    gas_used: IntCounterVec,        // chain_id, signer
+    gas_spent: CounterVec,          // chain_id, signer, in the chain's native currency
+    rpc_requests: IntCounterVec,    // method
+    rpc_errors: IntCounterVec,      // method
+    wallet_balance: GaugeVec,       // chain_id, address
+}
+
+// What happened to a certificate sent to POST /create_item
+#[derive(Clone, Copy, Debug)]
+pub enum CertificateOutcome {
+    Signed,
+    Anchored,
+    Failed,
+}
+
+impl CertificateOutcome {
+    fn label(self) -> &'static str {
+        match self {
+            CertificateOutcome::Signed => "signed",
+            CertificateOutcome::Anchored => "anchored",
+            CertificateOutcome::Failed => "failed",
+        }
+    }
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let http_requests = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let certificates = IntCounterVec::new(
+            Opts::new("certificates_total", "Certificates signed, anchored on chain or failed"),
+            &["chain_id", "outcome"],
+        )
+        .unwrap();
+        let tx_confirmation = HistogramVec::new(
+            HistogramOpts::new(
+                "transaction_confirmation_seconds",
+                "Time from sending a transaction to its receipt",
+            )
+            .buckets(vec![0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0]),
+            &["chain_id"],
+        )
+        .unwrap();
+        let gas_used = IntCounterVec::new(
+            Opts::new("transaction_gas_used_total", "Gas used by confirmed transactions"),
+            &["chain_id", "signer"],
+        )
+        .unwrap();
+        let gas_spent = CounterVec::new(
+            Opts::new("transaction_gas_spent_eth_total", "Gas fees paid, in the native currency"),
+            &["chain_id", "signer"],
+        )
+        .unwrap();
+        let rpc_requests = IntCounterVec::new(
+            Opts::new("rpc_requests_total", "JSON-RPC requests by method"),
+            &["method"],
+        )
+        .unwrap();
+        let rpc_errors = IntCounterVec::new(
+            Opts::new("rpc_errors_total", "JSON-RPC requests that failed, by method"),
+            &["method"],
+        )
+        .unwrap();
+        let wallet_balance = GaugeVec::new(
+            Opts::new("wallet_balance_eth", "Balance of the signing wallets, in the native currency"),
+            &["chain_id", "address"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(certificates.clone())).unwrap();
+        registry.register(Box::new(tx_confirmation.clone())).unwrap();
+        registry.register(Box::new(gas_used.clone())).unwrap();
+        registry.register(Box::new(gas_spent.clone())).unwrap();
+        registry.register(Box::new(rpc_requests.clone())).unwrap();
+        registry.register(Box::new(rpc_errors.clone())).unwrap();
+        registry.register(Box::new(wallet_balance.clone())).unwrap();
+
+        Self {
+            registry,
+            http_requests,
+            certificates,
+            tx_confirmation,
+            gas_used,
+            gas_spent,
+            rpc_requests,
+            rpc_errors,
+            wallet_balance,
+        }
+    }
+
+    pub fn certificate(&self, chain_id: u64, outcome: CertificateOutcome) {
+        self.certificates
+            .with_label_values(&[&chain_id.to_string(), outcome.label()])
+            .inc();
+    }
+
+    // A mined transaction, `sent` is when it was handed to the node
+    pub fn transaction(&self, chain_id: u64, sent: Instant, receipt: &TransactionReceipt) {
+        let chain_id = chain_id.to_string();
+        let signer = format!("{:?}", receipt.from);
+        let gas_used = receipt.gas_used.unwrap_or_default();
+
+        self.tx_confirmation
+            .with_label_values(&[&chain_id])
+            .observe(sent.elapsed().as_secs_f64());
+        self.gas_used
+            .with_label_values(&[&chain_id, &signer])
+            .inc_by(gas_used.low_u64());
+        if let Some(gas_price) = receipt.effective_gas_price {
+            self.gas_spent
+                .with_label_values(&[&chain_id, &signer])
+                .inc_by(ether(gas_used * gas_price));
+        }
+    }
+
+    pub fn rpc_request(&self, method: &str, failed: bool) {
+        self.rpc_requests.with_label_values(&[method]).inc();
+        if failed {
+            self.rpc_errors.with_label_values(&[method]).inc();
+        }
+    }
+
+    fn wallet_balance(&self, chain_id: u64, address: Address, balance: U256) {
+        self.wallet_balance
+            .with_label_values(&[&chain_id.to_string(), &format!("{:?}", address)])
+            .set(ether(balance));
+    }
+}
+
+fn ether(wei: U256) -> f64 {
+    format_ether(wei).parse().unwrap_or(f64::NAN)
+}
+
+// Middleware timing every request, by route template rather than the raw path
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+
+    METRICS
+        .http_requests
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .observe(started.elapsed().as_secs_f64());
+
+    response
+}
+
+// Handler for the Prometheus scrape, balances are read from the chains on every scrape
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain")
+    )
+)]
+pub async fn metrics(State(state): State<AppState>) -> Result<Response, StatusCode> {
+    let mut balances = JoinSet::new();
+
+    for chain in state.chains.chains() {
+        let mut signers = vec![chain.eth_client.address()];
+        if chain.relayer.address() != chain.eth_client.address() {
+            signers.push(chain.relayer.address());
+        }
+
+        for address in signers {
+            let chain = chain.clone();
+            balances.spawn(async move {
+                let balance = tokio::time::timeout(
+                    BALANCE_TIMEOUT,
+                    chain.eth_client.get_balance(address, None),
+                )
+                .await;
+                (chain.chain_id, address, balance)
+            });
+        }
+    }
+
+    while let Some(joined) = balances.join_next().await {
+        match joined {
+            Ok((chain_id, address, Ok(Ok(balance)))) => {
+                METRICS.wallet_balance(chain_id, address, balance)
+            }
+            Ok((chain_id, address, _)) => {
+                warn!(?address, chain_id, "Wallet balance unavailable")
+            }
+            Err(e) => error!(error = ?e, "Balance task failed"),
+        }
+    }
+
+    let mut body = Vec::new();
+    TextEncoder::new()
+        .encode(&METRICS.registry.gather(), &mut body)
+        .map_err(|e| {
+            error!(error = ?e, "Metrics encoding failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(([(header::CONTENT_TYPE, TextEncoder::new().format_type().to_string())], body).into_response())
+}
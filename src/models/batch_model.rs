@@ -0,0 +1,82 @@
+use crate::models::cert_model::CertificateDTO;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// Certificates to anchor together under one Merkle root
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct BatchDTO {
+    pub certificates: Vec<CertificateDTO>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct AnchoredBatch {
+    pub root: String,
+    pub count: usize,
+    pub tx_hash: String,
+    pub certificates: Vec<BatchLeaf>,
+}
+
+// A certificate of a batch, in leaf order
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+pub struct BatchLeaf {
+    pub unique_id: String,
+    pub struct_hash: String, // EIP-712 struct hash, the leaf is its keccak256
+}
+
+// Inclusion proof of a batched certificate, kept off-chain
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct CertificateProof {
+    pub unique_id: String,
+    pub struct_hash: String,
+    pub signature: Option<String>, // issuer EIP-712 signature, when the certificate was queued signed
+    pub leaf_index: usize,
+    pub proof: Vec<String>, // sibling hashes from the leaf up
+    pub root: String,
+    pub tx_hash: String, // anchorBatch transaction of the root
+}
+
+// A certificate and the proof to check against the roots anchored on-chain
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ProofVerificationDTO {
+    pub certificate: CertificateDTO,
+    pub proof: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ProofVerification {
+    pub included: bool,
+    pub struct_hash: String,
+    pub root: String, // the root the proof leads to
+    pub anchored_at: Option<u64>, // block of the anchorBatch transaction, None when never anchored
+}
+
+// A certificate to anchor in the next batch of its chain, signed offline by the issuer
+// or, without a signature, signed by the server as POST /v1/certificates would
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct QueueCertificateDTO {
+    pub certificate: CertificateDTO,
+    pub signature: Option<String>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnchoringStatus {
+    Queued,   // waiting for a batch, or for its next attempt after a failure
+    Anchored, // in a batch whose root is on-chain
+    Failed,   // gave up after the last attempt
+}
+
+// Anchoring status of a queued certificate
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct QueuedCertificate {
+    pub unique_id: String,
+    pub chain_id: u64,
+    pub struct_hash: String,
+    pub status: AnchoringStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub root: Option<String>,
+    pub tx_hash: Option<String>,
+    pub queued_at: u64,       // unix seconds
+    pub next_attempt_at: u64, // unix seconds
+}
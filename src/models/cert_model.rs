@@ -1,15 +1,67 @@
 use crate::certificate::auth_chain;
 use ethabi::ethereum_types::{Address, H256, U256};
-use ethers::contract::EthEvent;
 use ethers::types::transaction::eip712::{EIP712Domain, Eip712, Eip712Error};
 use ethers::utils::keccak256;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
-use std::env;
 use ethers::utils::hex::ToHexExt;
 use utoipa::ToSchema;
 
 
+// Must match SIGNING_DOMAIN and SIGNATURE_VERSION in AuthChain.sol
+pub const CERTIFICATE_DOMAIN_NAME: &str = "CertificateAuth";
+pub const CERTIFICATE_DOMAIN_VERSION: &str = "2";
+
+// EIP-712 domain of an AuthChain deployment, shared by every struct it verifies
+pub fn auth_chain_domain(chain_id: u64, auth_chain: Address) -> EIP712Domain {
+    EIP712Domain {
+        name: Some(CERTIFICATE_DOMAIN_NAME.to_string()),
+        version: Some(CERTIFICATE_DOMAIN_VERSION.to_string()),
+        chain_id: Some(U256::from(chain_id)),
+        verifying_contract: Some(auth_chain),
+        salt: None,
+    }
+}
+
+pub fn domain_separator(domain: &EIP712Domain) -> [u8; 32] {
+    let type_hash = keccak256(
+        "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
+    );
+
+    let name_hash = keccak256(domain.name.clone().unwrap_or_default().as_bytes());
+    let version_hash = keccak256(domain.version.clone().unwrap_or_default().as_bytes());
+    let chain_id = domain.chain_id.unwrap_or_default();
+    let verifying_contract = domain.verifying_contract.unwrap_or_default();
+
+    let encoded = ethers::abi::encode(&[
+        ethers::abi::Token::FixedBytes(type_hash.to_vec()),
+        ethers::abi::Token::FixedBytes(name_hash.to_vec()),
+        ethers::abi::Token::FixedBytes(version_hash.to_vec()),
+        ethers::abi::Token::Uint(chain_id),
+        ethers::abi::Token::Address(verifying_contract),
+    ]);
+    keccak256(&encoded)
+}
+
+// keccak256("\x19\x01" || domain_separator || struct_hash)
+pub fn typed_data_digest(domain_separator: [u8; 32], struct_hash: [u8; 32]) -> [u8; 32] {
+    let mut bytes = Vec::with_capacity(2 + 32 + 32);
+    bytes.extend_from_slice(b"\x19\x01");
+    bytes.extend_from_slice(&domain_separator);
+    bytes.extend_from_slice(&struct_hash);
+
+    keccak256(&bytes)
+}
+
+// EIP-712 encoding of string[]: keccak256(concat(keccak256(s_i)))
+pub fn hash_string_array(values: &[String]) -> [u8; 32] {
+    let concatenated: Vec<u8> = values
+        .iter()
+        .flat_map(|s| keccak256(s.as_bytes()))
+        .collect();
+    keccak256(&concatenated)
+}
+
 // Certificate struct for EIP-712
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Certificate {
@@ -19,46 +71,18 @@ pub struct Certificate {
     pub date: U256,
     pub owner: Address,
     pub metadata: Vec<String>,
+    #[serde(skip)]
+    pub domain: EIP712Domain, // AuthChain deployment the certificate is anchored on
 }
 impl Eip712 for Certificate {
     type Error = Eip712Error;
 
     fn domain_separator(&self) -> Result<[u8; 32], Self::Error> {
-        let domain = self.domain()?;
-        let type_hash = keccak256(
-            "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
-        );
-
-        let name_hash = keccak256(domain.name.unwrap_or_default().as_bytes());
-        let version_hash = keccak256(domain.version.unwrap_or_default().as_bytes());
-        let chain_id = U256::from(domain.chain_id.unwrap_or_default());
-        let verifying_contract = domain.verifying_contract.unwrap_or_default();
-
-        let encoded = ethers::abi::encode(&[
-            ethers::abi::Token::FixedBytes(type_hash.to_vec()),
-            ethers::abi::Token::FixedBytes(name_hash.to_vec()),
-            ethers::abi::Token::FixedBytes(version_hash.to_vec()),
-            ethers::abi::Token::Uint(chain_id),
-            ethers::abi::Token::Address(verifying_contract),
-        ]);
-        Ok(keccak256(&encoded))
+        Ok(domain_separator(&self.domain()?))
     }
 
     fn domain(&self) -> Result<EIP712Domain, Self::Error> {
-
-        let chain_id = env::var("CHAIN_ID").unwrap().parse::<usize>().unwrap();
-
-        Ok(EIP712Domain {
-            name: Some("CertificateAuth".to_string()),
-            version: Some("1".to_string()),
-            chain_id: Some(U256::from(chain_id)),
-            verifying_contract: Some(
-                env::var("AUTH_CHAIN_CONTRACT").unwrap()
-                    .parse()
-                    .map_err(|_| anyhow::anyhow!("Invalid contract address")).unwrap(),
-            ),
-            salt: None,
-        })
+        Ok(self.domain.clone())
     }
 
     fn type_hash() -> Result<[u8; 32], Self::Error> {
@@ -68,13 +92,7 @@ impl Eip712 for Certificate {
     }
 
     fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
-        let metadata_bytes = ethers::abi::encode(&[ethers::abi::Token::Array(
-            self.metadata
-                .iter()
-                .map(|s| ethers::abi::Token::String(s.clone()))
-                .collect(),
-        )]);
-        let metadata_hash = keccak256(&metadata_bytes);
+        let metadata_hash = hash_string_array(&self.metadata);
 
         let encoded = ethers::abi::encode(&[
             ethers::abi::Token::FixedBytes(Self::type_hash()?.to_vec()),
@@ -89,16 +107,7 @@ impl Eip712 for Certificate {
     }
 
     fn encode_eip712(&self) -> Result<[u8; 32], Self::Error> {
-        let domain_separator = self.domain_separator()?;
-        let struct_hash = self.struct_hash()?;
-
-        // EIP-712 digest: keccak256("\x19\x01" || domain_separator || struct_hash)
-        let mut bytes = Vec::with_capacity(2 + 32 + 32);
-        bytes.extend_from_slice(b"\x19\x01");
-        bytes.extend_from_slice(&domain_separator);
-        bytes.extend_from_slice(&struct_hash);
-
-        Ok(keccak256(&bytes))
+        Ok(typed_data_digest(self.domain_separator()?, self.struct_hash()?))
     }
 }
 
@@ -114,10 +123,9 @@ pub struct CertificateDTO {
     pub metadata: Vec<String>,
 }
 
-// Convert DTO to Certificate
-impl TryFrom<CertificateDTO> for Certificate {
-    type Error = anyhow::Error;
-    fn try_from(dto: CertificateDTO) -> Result<Self, Self::Error> {
+// Convert DTO to Certificate, signed under the domain of the chain it is anchored on
+impl Certificate {
+    pub fn from_dto(dto: CertificateDTO, domain: EIP712Domain) -> anyhow::Result<Self> {
         Ok(Certificate {
             name: dto.name,
             unique_id: dto.unique_id,
@@ -128,8 +136,22 @@ impl TryFrom<CertificateDTO> for Certificate {
                 .parse()
                 .map_err(|_| anyhow::anyhow!("Invalid address format"))?,
             metadata: dto.metadata,
+            domain,
         })
     }
+
+    // the certificate of an AuthChain call, e.g. decoded from calldata
+    pub fn from_contract(certificate: auth_chain::Certificate, domain: EIP712Domain) -> Self {
+        Certificate {
+            name: certificate.name,
+            unique_id: certificate.unique_id,
+            serial: certificate.serial,
+            date: certificate.date,
+            owner: certificate.owner,
+            metadata: certificate.metadata,
+            domain,
+        }
+    }
 }
 
 // Convert Certificate to contract Certificate
@@ -146,6 +168,13 @@ impl From<Certificate> for auth_chain::Certificate {
     }
 }
 
+// Path parameters of the item routes, next to an optional {chain_id}
+#[derive(Clone, Deserialize, Debug)]
+pub struct ItemPath {
+    #[serde(alias = "item_id")]
+    pub unique_id: String,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
 pub struct ItemInput {
     // #[schema(value_type = String, format = Binary)]
@@ -153,29 +182,45 @@ pub struct ItemInput {
 }
 //=======================
 
-#[derive(Debug, Clone, EthEvent)]
-#[ethevent(name = "ItemCreated", abi = "ItemCreated(string,bytes32,address)")]
-#[derive(Default)]
-pub struct ItemCreatedEvent {
+// Result of createItem, from the ItemCreated and DebugHash events of the receipt
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+pub struct CreatedItem {
+    pub unique_id: String,
     pub name: String,
-    
-    #[ethevent(indexed)]
-    pub unique_id: H256,
-    
-    #[ethevent(indexed)]
-    pub owner: Address,
+    pub hash: String, // on-chain id of the certificate, its EIP-712 struct hash
+    pub owner: String,
+    pub tx_hash: Option<String>, // unknown when the item was found already anchored
+    pub signature_check: Option<SignatureCheck>,
 }
 
-impl ItemCreatedEvent {
-    pub fn init() -> Self {
+// What AuthChain computed when it verified the issuer signature (DebugHash event)
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+pub struct SignatureCheck {
+    pub struct_hash: String,
+    pub digest: String,
+    pub signer: String,
+}
+
+impl CreatedItem {
+    pub fn new(unique_id: &str, name: String, hash: H256, owner: Address) -> Self {
         Self {
-            name: String::new(),
-            unique_id: H256::zero(),
-            owner: Address::zero(),
+            unique_id: unique_id.to_string(),
+            name,
+            hash: hash.encode_hex_with_prefix(),
+            owner: owner.encode_hex_with_prefix(),
+            tx_hash: None,
+            signature_check: None,
         }
     }
-    pub fn new(name: String, unique_id: H256, owner: Address) -> Self {
-        Self { name, unique_id, owner }
+}
+
+impl From<auth_chain::DebugHashFilter> for SignatureCheck {
+    fn from(debug: auth_chain::DebugHashFilter) -> Self {
+        Self {
+            struct_hash: H256::from(debug.struct_hash).encode_hex_with_prefix(),
+            digest: H256::from(debug.digest).encode_hex_with_prefix(),
+            signer: debug.signer.encode_hex_with_prefix(),
+        }
     }
 }
 
@@ -194,11 +239,14 @@ pub struct Item {
     pub date: String,
     pub unique_id: String,
     pub owner: String, // Address as hex string
+    pub revoked: bool,
+    pub revocation_reason: Option<RevocationReason>,
+    pub chain_id: u64, // chain the certificate is anchored on
 }
 
 
-impl From<auth_chain::Item> for Item {
-    fn from(item: auth_chain::Item) -> Self {
+impl Item {
+    pub fn from_contract(item: auth_chain::Item, chain_id: u64) -> Self {
         Self {
             name: item.name,
             unique_id: item.unique_id.encode_hex_with_prefix(),
@@ -206,29 +254,375 @@ impl From<auth_chain::Item> for Item {
             date: item.date.to_string(),
             owner: item.owner
                 .encode_hex_with_prefix(),
+            revoked: item.revoked,
+            revocation_reason: RevocationReason::try_from(item.revocation_reason).ok(),
+            chain_id,
         }
     }
 }
+
+// Mirrors the contract RevocationReason enum, `None` (0) is not a valid reason
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationReason {
+    Counterfeit = 1,
+    Recalled = 2,
+    Stolen = 3,
+    IssuedInError = 4,
+}
+
+impl TryFrom<u8> for RevocationReason {
+    type Error = anyhow::Error;
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        match code {
+            1 => Ok(Self::Counterfeit),
+            2 => Ok(Self::Recalled),
+            3 => Ok(Self::Stolen),
+            4 => Ok(Self::IssuedInError),
+            _ => Err(anyhow::anyhow!("Invalid revocation reason code: {}", code)),
+        }
+    }
+}
+
+impl From<RevocationReason> for u8 {
+    fn from(reason: RevocationReason) -> Self {
+        reason as u8
+    }
+}
+
+// Revocation request from frontend
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct RevokeDTO {
+    pub reason: RevocationReason,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct RevokedItem {
+    pub unique_id: String,
+    pub reason: RevocationReason,
+    pub tx_hash: String,
+}
+
+// Re-issuance request from frontend, replacing the certificate of an existing item
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ReissueDTO {
+    pub certificate: CertificateDTO, // its unique_id must be the one of the path
+    pub reason: String,              // kept on-chain in the ItemReissued event
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ReissuedItem {
+    pub unique_id: String,
+    pub previous_hash: String, // on-chain id of the replaced certificate
+    pub hash: String,          // on-chain id of the new one
+    pub reason: String,
+    pub tx_hash: String,
+}
+// Paths of one API version, relative to `prefix`; the chain scoped ones are also served under `chain_scope`
 #[derive(Clone, Serialize, Deserialize, Debug)]
-pub(crate) struct RouterPath {
-   pub  verify: String,
+pub struct ApiPaths {
+   pub prefix: String,
+   pub verify: String,
    pub verify_status: String,
    pub signature: String,
    pub create_item: String,
    pub get_item: String,
    pub get_owner: String,
+   pub revoke_item: String,
+   pub reissue_item: Option<String>, // v1 only
+   pub anchor_batch: Option<String>, // v1 only
+   pub certificate_proof: Option<String>, // v1 only
+   pub verify_proof: Option<String>, // v1 only
+   pub queue_certificate: Option<String>, // v1 only
+   pub anchoring_status: Option<String>, // v1 only
+   pub certificate_qr: Option<String>, // v1 only
+   pub verify_certificate_qr: Option<String>, // v1 only
+   pub certificate_document: Option<String>, // v1 only
+   pub transfer_item: String,
+   pub item_history: String,
+   pub relayer_status: String,
+   pub sign_typed_data: String,
+   pub verify_typed_data: String,
+   pub ownership: String,
+   pub transaction: Option<String>, // v1 only
+   pub tracked_transactions: Option<String>, // v1 only
+   pub chains: String,
+   pub chain_scope: String,
 }
 
-impl RouterPath {
-    pub fn init() -> Self {
+impl ApiPaths {
+    // Resource oriented layout documented by ApiDocV1
+    pub fn v1() -> Self {
+        Self {
+            prefix: "/v1".to_string(),
+            verify: "/signatures/verify".to_string(),
+            verify_status: "/signatures/verifier".to_string(),
+            signature: "/signatures".to_string(),
+            create_item: "/certificates".to_string(),
+            get_item: "/items/{unique_id}".to_string(),
+            get_owner: "/issuers".to_string(),
+            revoke_item: "/certificates/{unique_id}/revocation".to_string(),
+            reissue_item: Some("/certificates/{unique_id}/reissues".to_string()),
+            anchor_batch: Some("/certificates/batches".to_string()),
+            certificate_proof: Some("/certificates/{unique_id}/proof".to_string()),
+            verify_proof: Some("/proofs/verifications".to_string()),
+            queue_certificate: Some("/certificates/queue".to_string()),
+            anchoring_status: Some("/certificates/{unique_id}/anchoring".to_string()),
+            certificate_qr: Some("/certificates/qr".to_string()),
+            verify_certificate_qr: Some("/certificates/qr/verifications".to_string()),
+            certificate_document: Some("/certificates/{unique_id}/document".to_string()),
+            transfer_item: "/items/{unique_id}/transfers".to_string(),
+            item_history: "/items/{unique_id}/history".to_string(),
+            relayer_status: "/relayer".to_string(),
+            sign_typed_data: "/typed-data/signatures".to_string(),
+            verify_typed_data: "/typed-data/verifications".to_string(),
+            ownership: "/ownership/{asset_id}".to_string(),
+            transaction: Some("/tx/{tx_hash}".to_string()),
+            tracked_transactions: Some("/transactions".to_string()),
+            chains: "/chains".to_string(),
+            chain_scope: "/chains/{chain_id}".to_string(),
+        }
+    }
+
+    // Unversioned paths from before v1, served with a Deprecation header until clients move
+    pub fn legacy() -> Self {
         Self {
+            prefix: String::new(),
             verify: "/verify".to_string(),
             verify_status: "/verify/status".to_string(),
             signature: "/signature".to_string(),
             create_item: "/create_item".to_string(),
-            get_item: "/get_item/{item_id}".to_string(),
+            get_item: "/get_item/{unique_id}".to_string(),
             get_owner: "/get_owner".to_string(),
+            revoke_item: "/certificates/{unique_id}/revoke".to_string(),
+            reissue_item: None,
+            anchor_batch: None,
+            certificate_proof: None,
+            verify_proof: None,
+            queue_certificate: None,
+            anchoring_status: None,
+            certificate_qr: None,
+            verify_certificate_qr: None,
+            certificate_document: None,
+            transfer_item: "/items/{unique_id}/transfer".to_string(),
+            item_history: "/items/{unique_id}/history".to_string(),
+            relayer_status: "/relayer/status".to_string(),
+            sign_typed_data: "/typed-data/sign".to_string(),
+            verify_typed_data: "/typed-data/verify".to_string(),
+            ownership: "/ownership/{asset_id}".to_string(),
+            transaction: None,
+            tracked_transactions: None,
+            chains: "/chains".to_string(),
+            chain_scope: "/chains/{chain_id}".to_string(),
         }
     }
+
+    // Every route path with its prefix, in the same order for every version;
+    // None where the version does not serve the route
+    pub fn all(&self) -> Vec<Option<String>> {
+        [
+            Some(&self.verify),
+            Some(&self.verify_status),
+            Some(&self.signature),
+            Some(&self.create_item),
+            Some(&self.get_item),
+            Some(&self.get_owner),
+            Some(&self.revoke_item),
+            self.reissue_item.as_ref(),
+            self.anchor_batch.as_ref(),
+            self.certificate_proof.as_ref(),
+            self.verify_proof.as_ref(),
+            self.queue_certificate.as_ref(),
+            self.anchoring_status.as_ref(),
+            self.certificate_qr.as_ref(),
+            self.verify_certificate_qr.as_ref(),
+            self.certificate_document.as_ref(),
+            Some(&self.transfer_item),
+            Some(&self.item_history),
+            Some(&self.relayer_status),
+            Some(&self.sign_typed_data),
+            Some(&self.verify_typed_data),
+            Some(&self.ownership),
+            self.transaction.as_ref(),
+            self.tracked_transactions.as_ref(),
+            Some(&self.chains),
+            Some(&self.chain_scope),
+        ]
+        .into_iter()
+        .map(|path| path.map(|path| format!("{}{}", self.prefix, path)))
+        .collect()
+    }
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RouterPath {
+   pub v1: ApiPaths,
+   pub legacy: ApiPaths,
+   // operational routes, outside of the API versions
+   pub health_live: String,
+   pub health_ready: String,
+   pub info: String,
+   pub metrics: String,
+   pub transaction: String, // GET /tx/{tx_hash} for auditors, same report as under /v1
+}
+
+impl RouterPath {
+    pub fn init() -> Self {
+        Self {
+            v1: ApiPaths::v1(),
+            legacy: ApiPaths::legacy(),
+            health_live: "/health/live".to_string(),
+            health_ready: "/health/ready".to_string(),
+            info: "/info".to_string(),
+            metrics: "/metrics".to_string(),
+            transaction: "/tx/{tx_hash}".to_string(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::transaction::eip712::TypedData;
+    use serde_json::json;
+
+    const CHAIN_ID: u64 = 84532;
+    const AUTH_CHAIN: &str = "0xC14CDcDb51EF45111dd2024AB1c003F49144928f";
+
+    fn certificate(metadata: &[&str]) -> Certificate {
+        Certificate {
+            name: "Redmi Note 14".to_string(),
+            unique_id: "XM-543210987654321".to_string(),
+            serial: "XM12345".to_string(),
+            date: U256::from(1746057600u64),
+            owner: "0x5678901234567890123456789012345678901234".parse().unwrap(),
+            metadata: metadata.iter().map(|s| s.to_string()).collect(),
+            domain: auth_chain_domain(CHAIN_ID, AUTH_CHAIN.parse().unwrap()),
+        }
+    }
+
+    // The same certificate as eth_signTypedData_v4 JSON, as MetaMask and ethers encode it
+    fn typed_data(cert: &Certificate) -> TypedData {
+        serde_json::from_value(json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Certificate": [
+                    { "name": "name", "type": "string" },
+                    { "name": "uniqueId", "type": "string" },
+                    { "name": "serial", "type": "string" },
+                    { "name": "date", "type": "uint256" },
+                    { "name": "owner", "type": "address" },
+                    { "name": "metadata", "type": "string[]" }
+                ]
+            },
+            "primaryType": "Certificate",
+            "domain": {
+                "name": "CertificateAuth",
+                "version": CERTIFICATE_DOMAIN_VERSION,
+                "chainId": CHAIN_ID,
+                "verifyingContract": AUTH_CHAIN
+            },
+            "message": {
+                "name": cert.name,
+                "uniqueId": cert.unique_id,
+                "serial": cert.serial,
+                "date": cert.date.as_u64(),
+                "owner": format!("{:?}", cert.owner),
+                "metadata": cert.metadata
+            }
+        }))
+        .unwrap()
+    }
+
+    fn h256(hex: &str) -> [u8; 32] {
+        hex.parse::<H256>().unwrap().0
+    }
+
+    // Vectors from the Mail example in the EIP-712 reference implementation
+    #[test]
+    fn domain_separator_and_digest_match_eip712_reference() {
+        let domain = EIP712Domain {
+            name: Some("Ether Mail".to_string()),
+            version: Some("1".to_string()),
+            chain_id: Some(U256::from(1)),
+            verifying_contract: Some("0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC".parse().unwrap()),
+            salt: None,
+        };
+        let separator = domain_separator(&domain);
+        assert_eq!(
+            separator,
+            h256("0xf2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+
+        let mail_hash = h256("0xc52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e");
+        assert_eq!(
+            typed_data_digest(separator, mail_hash),
+            h256("0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+    }
+
+    #[test]
+    fn string_array_hash_follows_eip712() {
+        let values = vec!["color:black".to_string(), "storage:256GB".to_string()];
+
+        let mut concatenated = keccak256("color:black").to_vec();
+        concatenated.extend_from_slice(&keccak256("storage:256GB"));
+        assert_eq!(hash_string_array(&values), keccak256(&concatenated));
+
+        // empty array hashes the empty byte string
+        assert_eq!(hash_string_array(&[]), keccak256([]));
+
+        // abi.encode(string[]) was the pre-spec contract encoding
+        let abi_encoded = ethers::abi::encode(&[ethers::abi::Token::Array(
+            values.iter().map(|s| ethers::abi::Token::String(s.clone())).collect(),
+        )]);
+        assert_ne!(hash_string_array(&values), keccak256(abi_encoded));
+    }
+
+    #[test]
+    fn type_hash_matches_typed_data_encode_type() {
+        let typed = typed_data(&certificate(&[]));
+
+        let encoded_type = ethers::types::transaction::eip712::encode_type("Certificate", &typed.types).unwrap();
+        assert_eq!(Certificate::type_hash().unwrap(), keccak256(encoded_type));
+    }
+
+    #[test]
+    fn certificate_digest_matches_typed_data_json() {
+        let cases: [&[&str]; 4] = [
+            &[],
+            &["color:black"],
+            &["color:black", "storage:256GB", "imei:543210987654321"],
+            &["", "région:Île-de-France", "🔋 5000mAh"],
+        ];
+
+        for metadata in cases {
+            let cert = certificate(metadata);
+            let typed = typed_data(&cert);
+
+            assert_eq!(cert.domain_separator().unwrap(), typed.domain_separator().unwrap());
+            assert_eq!(cert.struct_hash().unwrap(), typed.struct_hash().unwrap(), "{:?}", metadata);
+            assert_eq!(cert.encode_eip712().unwrap(), typed.encode_eip712().unwrap(), "{:?}", metadata);
+        }
+    }
+
+    #[tokio::test]
+    async fn wallet_signature_recovers_issuer() {
+        let wallet: ethers::signers::LocalWallet =
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318".parse().unwrap();
+
+        let cert = certificate(&["color:black"]);
+        let signature = ethers::signers::Signer::sign_typed_data(&wallet, &typed_data(&cert)).await.unwrap();
+
+        assert_eq!(
+            signature.recover(cert.encode_eip712().unwrap()).unwrap(),
+            ethers::signers::Signer::address(&wallet)
+        );
+    }
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// A chain the server anchors certificates on, with its contract deployments
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ChainInfo {
+    pub chain_id: u64,
+    pub name: String,
+    pub auth_chain: String,
+    pub signature_verifier: String,
+    pub ownership: Option<String>,
+    pub default: bool, // served on the routes without a /chains/{chain_id} prefix
+}
@@ -0,0 +1,18 @@
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Clone, Copy, Deserialize, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentFormat {
+    #[default]
+    Pdf,
+    Png,
+}
+
+// Query of the document route, `?format=pdf` (the default) or `?format=png`
+#[derive(Clone, Copy, Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DocumentQuery {
+    #[serde(default)]
+    pub format: DocumentFormat,
+}
@@ -0,0 +1,35 @@
+use crate::models::chain_model::ChainInfo;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// One readiness check, `detail` says what failed or what was found
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct HealthCheck {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ChainReadiness {
+    pub chain_id: u64,
+    pub name: String,
+    pub checks: Vec<HealthCheck>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct Readiness {
+    pub ready: bool,
+    pub chains: Vec<ChainReadiness>,
+    pub database: Option<HealthCheck>, // None when no DATABASE_URL is configured
+}
+
+// Build and deployment of the running server
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ServiceInfo {
+    pub version: String,
+    pub git_hash: String,
+    pub issuer: String,
+    pub default_chain_id: u64,
+    pub chains: Vec<ChainInfo>,
+}
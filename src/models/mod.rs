@@ -1,2 +1,12 @@
+pub mod batch_model;
 pub mod cert_model;
-pub mod sig_model;
\ No newline at end of file
+pub mod chain_model;
+pub mod document_model;
+pub mod health_model;
+pub mod qr_model;
+pub mod relayer_model;
+pub mod sig_model;
+pub mod simulation_model;
+pub mod transfer_model;
+pub mod tx_model;
+pub mod typed_data_model;
\ No newline at end of file
@@ -0,0 +1,34 @@
+use crate::models::cert_model::CertificateDTO;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// A certificate to put in a QR code, with the issuer signature made offline or, without
+// one, signed by the server
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct QrCodeDTO {
+    pub certificate: CertificateDTO,
+    pub signature: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct QrPayload {
+    pub payload: String, // "AC1:" and base45, the text of the QR code
+    pub svg: String,
+}
+
+// The text read from a certificate QR code
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct QrVerificationDTO {
+    pub payload: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct QrVerification {
+    pub valid: bool, // signed by the issuer, for the AuthChain deployment of this chain
+    pub signer: String,
+    pub issuer: String,
+    pub chain_id: u64,
+    pub auth_chain: String,
+    pub struct_hash: String,
+    pub certificate: CertificateDTO,
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// Relayer wallet and gas budget, amounts in ETH
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct RelayerStatus {
+    pub address: String,
+    pub balance: String,
+    pub gas_budget: String,
+    pub gas_spent: String,
+    pub gas_remaining: String,
+}
@@ -1,22 +1,60 @@
+use crate::models::cert_model::{domain_separator, typed_data_digest};
 use crate::signature_verifier::signature_verifier;
-use ethabi::ethereum_types::Address;
-use ethers::contract::{Eip712, EthAbiType, EthEvent};
-use ethers::types::transaction::eip712::Eip712;
+use ethabi::ethereum_types::{Address, U256};
+use ethers::types::transaction::eip712::{EIP712Domain, Eip712, Eip712Error};
+use ethers::utils::keccak256;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 // data model for Ethereum Asset (internal, for EIP-712 signing)
-#[derive(Eip712, EthAbiType, Clone, Serialize, Deserialize, Debug)]
-#[eip712(
-    name = "AssetOwnership",
-    version = "1",
-    chain_id = 84532,
-    verifying_contract = "0x3b5fFD911B70ed3E166e3197880809C1e85b34B3"
-)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Asset {
     pub name: String,
     pub serial: String,
     pub owner: Address,
+    #[serde(skip)]
+    pub domain: EIP712Domain, // SignatureVerifier deployment checking the signature
+}
+
+// EIP-712 domain of a SignatureVerifier deployment
+pub fn signature_verifier_domain(chain_id: u64, signature_verifier: Address) -> EIP712Domain {
+    EIP712Domain {
+        name: Some("AssetOwnership".to_string()),
+        version: Some("1".to_string()),
+        chain_id: Some(U256::from(chain_id)),
+        verifying_contract: Some(signature_verifier),
+        salt: None,
+    }
+}
+
+impl Eip712 for Asset {
+    type Error = Eip712Error;
+
+    fn domain_separator(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(domain_separator(&self.domain()?))
+    }
+
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        Ok(self.domain.clone())
+    }
+
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(keccak256("Asset(string name,string serial,address owner)"))
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        let encoded = ethers::abi::encode(&[
+            ethers::abi::Token::FixedBytes(Self::type_hash()?.to_vec()),
+            ethers::abi::Token::FixedBytes(keccak256(self.name.as_bytes()).to_vec()),
+            ethers::abi::Token::FixedBytes(keccak256(self.serial.as_bytes()).to_vec()),
+            ethers::abi::Token::Address(self.owner),
+        ]);
+        Ok(keccak256(&encoded))
+    }
+
+    fn encode_eip712(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(typed_data_digest(self.domain_separator()?, self.struct_hash()?))
+    }
 }
 
 // data model for API (Swagger-compatible)
@@ -28,11 +66,9 @@ pub struct AssetDto {
     pub owner: String, // Address as hex string
 }
 
-// to convert AssetDto to Asset
-impl TryFrom<AssetDto> for Asset {
-    type Error = anyhow::Error;
-
-    fn try_from(dto: AssetDto) -> anyhow::Result<Self, Self::Error> {
+// to convert AssetDto to Asset, signed under the domain of the verifying chain
+impl Asset {
+    pub fn from_dto(dto: AssetDto, domain: EIP712Domain) -> anyhow::Result<Self> {
         Ok(Asset {
             name: dto.name,
             serial: dto.serial,
@@ -40,8 +76,19 @@ impl TryFrom<AssetDto> for Asset {
                 .owner
                 .parse()
                 .map_err(|_| anyhow::anyhow!("Invalid address format"))?,
+            domain,
         })
     }
+
+    // the asset of a SignatureVerifier call, e.g. decoded from calldata
+    pub fn from_contract(asset: signature_verifier::Asset, domain: EIP712Domain) -> Self {
+        Asset {
+            name: asset.name,
+            serial: asset.serial,
+            owner: asset.owner,
+            domain,
+        }
+    }
 }
 
 // to convert the Asset to the smart contract Asset data type
@@ -55,9 +102,23 @@ impl From<Asset> for signature_verifier::Asset {
     }
 }
 
+// Path parameters of the ownership routes, next to an optional {chain_id}
+#[derive(Clone, Deserialize, Debug)]
+pub struct AssetPath {
+    pub asset_id: String,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
 pub struct OwnerAddress {
     #[schema(value_type = String, format = Binary)]
     pub owner: Address, // Address as hex string
 }
 
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct RegisteredAsset {
+    pub asset_id: String,
+    #[schema(value_type = String, format = Binary)]
+    pub owner: Address,
+    pub tx_hash: String,
+}
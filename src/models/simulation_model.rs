@@ -0,0 +1,42 @@
+use ethers::types::U256;
+use ethers::utils::format_ether;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+// Query of the write routes, `?dry_run=true` simulates the transaction without sending it
+#[derive(Clone, Copy, Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DryRunQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// A write run with eth_call and eth_estimateGas, nothing was broadcast
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct Simulation {
+    pub action: String,  // contract function, e.g. "create_item"
+    pub subject: String, // unique id or asset id the transaction is about
+    pub gas_estimate: u64,
+    pub gas_price: String, // wei
+    pub fee: String,       // ETH, gas_estimate * gas_price
+}
+
+impl Simulation {
+    pub fn new(action: &str, subject: &str, gas_estimate: U256, gas_price: U256) -> Self {
+        Self {
+            action: action.to_string(),
+            subject: subject.to_string(),
+            gas_estimate: gas_estimate.as_u64(),
+            gas_price: gas_price.to_string(),
+            fee: format_ether(gas_estimate * gas_price),
+        }
+    }
+}
+
+// Response of a write route: what was sent, or with `?dry_run=true` the simulation
+#[derive(Clone, Serialize, Debug)]
+#[serde(untagged)]
+pub enum WriteResult<T> {
+    Sent(T),
+    Simulated(Simulation),
+}
@@ -0,0 +1,113 @@
+use crate::models::cert_model::{domain_separator, typed_data_digest, RevocationReason};
+use ethabi::ethereum_types::{Address, U256};
+use ethers::types::transaction::eip712::{EIP712Domain, Eip712, Eip712Error};
+use ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+
+// Transfer struct for EIP-712, signed by the current owner of an item
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Transfer {
+    pub unique_id: String,
+    pub from: Address,
+    pub to: Address,
+    pub nonce: U256,
+    pub deadline: U256,
+    #[serde(skip)]
+    pub domain: EIP712Domain, // AuthChain deployment holding the item
+}
+
+impl Eip712 for Transfer {
+    type Error = Eip712Error;
+
+    fn domain_separator(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(domain_separator(&self.domain()?))
+    }
+
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        Ok(self.domain.clone())
+    }
+
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(keccak256(
+            "Transfer(string uniqueId,address from,address to,uint256 nonce,uint256 deadline)",
+        ))
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        let encoded = ethers::abi::encode(&[
+            ethers::abi::Token::FixedBytes(Self::type_hash()?.to_vec()),
+            ethers::abi::Token::FixedBytes(keccak256(self.unique_id.as_bytes()).to_vec()),
+            ethers::abi::Token::Address(self.from),
+            ethers::abi::Token::Address(self.to),
+            ethers::abi::Token::Uint(self.nonce),
+            ethers::abi::Token::Uint(self.deadline),
+        ]);
+        Ok(keccak256(&encoded))
+    }
+
+    fn encode_eip712(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(typed_data_digest(self.domain_separator()?, self.struct_hash()?))
+    }
+}
+
+// Transfer DTO from frontend, the item's unique id comes from the path
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct TransferDTO {
+    #[schema(value_type = String, format = Binary)]
+    pub from: String,
+    #[schema(value_type = String, format = Binary)]
+    pub to: String,
+    pub nonce: u64,
+    pub deadline: u64,
+    pub signature: String, // 65-byte owner signature as hex string
+}
+
+impl Transfer {
+    pub fn from_dto(unique_id: String, dto: &TransferDTO, domain: EIP712Domain) -> anyhow::Result<Self> {
+        Ok(Transfer {
+            unique_id,
+            from: dto
+                .from
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid address format"))?,
+            to: dto
+                .to
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid address format"))?,
+            nonce: U256::from(dto.nonce),
+            deadline: U256::from(dto.deadline),
+            domain,
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct TransferredItem {
+    pub unique_id: String,
+    pub from: String,
+    pub to: String,
+    pub tx_hash: String,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProvenanceAction {
+    Created,
+    Transferred,
+    Revoked,
+    Reissued,
+}
+
+// One entry of an item's provenance chain, built from indexed contract events
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ProvenanceEntry {
+    pub action: ProvenanceAction,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub reason: Option<RevocationReason>,
+    pub note: Option<String>, // reason given for a reissue
+    pub block_number: u64,
+    pub tx_hash: String,
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+// Path parameters of the transaction route, next to an optional {chain_id}
+#[derive(Clone, Deserialize, Debug)]
+pub struct TxPath {
+    pub tx_hash: String,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TxStatus {
+    Pending,
+    Success,
+    Reverted,
+}
+
+// What a transaction did, decoded with the AuthChain, Ownership and SignatureVerifier ABIs
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct TxReport {
+    pub tx_hash: String,
+    pub chain_id: u64,
+    pub status: TxStatus,
+    pub block_number: Option<u64>,
+    pub from: String,
+    pub to: Option<String>, // None for a contract deployment
+    pub value: String,      // wei
+    pub gas_used: Option<String>,
+    pub call: Option<DecodedCall>, // None when the calldata matches none of the ABIs
+    pub logs: Vec<DecodedLog>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct DecodedCall {
+    pub contract: String,     // ABI the calldata was decoded with
+    pub known_contract: bool, // `to` is the deployment this backend is configured with
+    pub function: String,
+    #[schema(value_type = Object)]
+    pub arguments: Value,
+    pub signer: Option<String>, // recovered from the embedded certificate or asset signature
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct DecodedLog {
+    pub log_index: Option<u64>,
+    pub address: String,
+    pub contract: Option<String>,
+    pub known_contract: bool,
+    pub event: Option<String>, // None when the log matches none of the ABIs, `fields` has its raw topics and data
+    #[schema(value_type = Object)]
+    pub fields: Value,
+}
+
+// What became of a transaction a previous run left pending, found when resuming it
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TxOutcome {
+    Confirmed,
+    Reverted,
+    Dropped, // no receipt, the transaction left the mempool
+}
+
+// A transaction sent by this server, `outcome` is None while it is pending
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+pub struct TrackedTx {
+    pub tx_hash: String,
+    pub action: String,
+    pub subject: String,
+    pub sent_at: u64,
+    pub outcome: Option<TxOutcome>,
+    pub finished_at: Option<u64>,
+}
+
+// Transactions of a chain the server is waiting on, and those resumed from an earlier run
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct TrackedTransactions {
+    pub pending: Vec<TrackedTx>,
+    pub resumed: Vec<TrackedTx>,
+}
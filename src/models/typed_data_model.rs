@@ -0,0 +1,230 @@
+use crate::models::cert_model::CERTIFICATE_DOMAIN_NAME;
+use ethabi::ethereum_types::{Address, U256};
+use ethers::types::transaction::eip712::TypedData;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+// Primary type of the AuthChain certificates: signing one for a caller would hand
+// out issuer signatures that createItem accepts
+const CERTIFICATE_PRIMARY_TYPE: &str = "Certificate";
+
+// One allow-list entry, written as `name:version:PrimaryType:chainId:verifyingContract`
+// where version may be `*`; the domain is always bound to one chain and one contract
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AllowedTypedData {
+    pub domain_name: String,
+    pub domain_version: Option<String>,
+    pub primary_type: String,
+    pub chain_id: u64,
+    pub verifying_contract: Address,
+}
+
+impl FromStr for AllowedTypedData {
+    type Err = anyhow::Error;
+
+    fn from_str(entry: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!("Invalid typed data allow-list entry: {}", entry);
+        let parts: Vec<&str> = entry.trim().split(':').collect();
+
+        let [name, version, primary_type, chain_id, contract] = parts.as_slice() else {
+            return Err(invalid());
+        };
+
+        if name.is_empty() || primary_type.is_empty() {
+            return Err(invalid());
+        }
+        if *name == CERTIFICATE_DOMAIN_NAME || *primary_type == CERTIFICATE_PRIMARY_TYPE {
+            return Err(anyhow::anyhow!("AuthChain certificates cannot be allow-listed: {}", entry));
+        }
+
+        Ok(AllowedTypedData {
+            domain_name: name.to_string(),
+            domain_version: (*version != "*").then(|| version.to_string()),
+            primary_type: primary_type.to_string(),
+            chain_id: chain_id.parse().map_err(|_| invalid())?,
+            verifying_contract: contract.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+// Which typed data the issuer key may sign through /typed-data/sign
+#[derive(Clone, Debug, Default)]
+pub struct TypedDataPolicy {
+    pub allowed: Vec<AllowedTypedData>,
+}
+
+impl TypedDataPolicy {
+    // Parse a comma separated allow-list, e.g. `AssetOwnership:1:Asset:84532:0x3b5fFD911B70ed3E166e3197880809C1e85b34B3`
+    pub fn parse(list: &str) -> anyhow::Result<Self> {
+        let allowed = list
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(str::parse)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self { allowed })
+    }
+
+    // The domain must be bound to our chain and a contract, and match an allow-list entry
+    pub fn check(&self, typed_data: &TypedData, chain_id: u64) -> Result<(), String> {
+        let domain = &typed_data.domain;
+
+        if typed_data.primary_type == "EIP712Domain" {
+            return Err("EIP712Domain cannot be the primary type".to_string());
+        }
+
+        if domain.name.as_deref() == Some(CERTIFICATE_DOMAIN_NAME) || typed_data.primary_type == CERTIFICATE_PRIMARY_TYPE {
+            return Err("AuthChain certificates are only signed by the issuer endpoints".to_string());
+        }
+
+        if domain.chain_id != Some(U256::from(chain_id)) {
+            return Err(format!("Domain chainId must be {}", chain_id));
+        }
+
+        if domain.verifying_contract.is_none() {
+            return Err("Domain verifyingContract is required".to_string());
+        }
+
+        let name = domain.name.as_deref().unwrap_or_default();
+        let version = domain.version.as_deref();
+
+        let permitted = self.allowed.iter().any(|entry| {
+            entry.domain_name == name
+                && entry.primary_type == typed_data.primary_type
+                && (entry.domain_version.is_none() || entry.domain_version.as_deref() == version)
+                && entry.chain_id == chain_id
+                && Some(entry.verifying_contract) == domain.verifying_contract
+        });
+
+        if !permitted {
+            return Err(format!(
+                "{}:{}:{} is not allow-listed",
+                name,
+                version.unwrap_or_default(),
+                typed_data.primary_type
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// A domain chainId past u64 names no chain, the typed data is malformed
+pub fn check_chain_id(typed_data: &TypedData) -> Result<(), String> {
+    match typed_data.domain.chain_id {
+        Some(chain_id) if chain_id > U256::from(u64::MAX) => Err(format!("Domain chainId {} is out of range", chain_id)),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct SignedTypedData {
+    pub signature: String,
+    pub digest: String,
+    pub signer: String,
+}
+
+// Verification request, typed data in eth_signTypedData_v4 format
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyTypedDataDTO {
+    #[schema(value_type = Object)]
+    pub typed_data: TypedData,
+    pub signature: String,
+    // expected signer, defaults to the issuer wallet
+    #[schema(value_type = Option<String>, format = Binary)]
+    pub signer: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct TypedDataVerification {
+    pub valid: bool,
+    pub recovered: String,
+    pub expected: String,
+    pub digest: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn typed_data(name: &str, version: &str, chain_id: u64, primary_type: &str) -> TypedData {
+        serde_json::from_value(json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Asset": [
+                    { "name": "name", "type": "string" },
+                    { "name": "serial", "type": "string" },
+                    { "name": "owner", "type": "address" }
+                ]
+            },
+            "primaryType": primary_type,
+            "domain": {
+                "name": name,
+                "version": version,
+                "chainId": chain_id,
+                "verifyingContract": CONTRACT
+            },
+            "message": {
+                "name": "Redmi Note 14",
+                "serial": "XM12345",
+                "owner": "0x5678901234567890123456789012345678901234"
+            }
+        }))
+        .unwrap()
+    }
+
+    const CONTRACT: &str = "0x3b5fFD911B70ed3E166e3197880809C1e85b34B3";
+
+    #[test]
+    fn parses_allow_list_entries() {
+        let policy = TypedDataPolicy::parse(&format!(
+            "AssetOwnership:2:Asset:84532:{}, AssetOwnership:*:Asset:1:{}",
+            CONTRACT, CONTRACT
+        ))
+        .unwrap();
+
+        assert_eq!(policy.allowed.len(), 2);
+        assert_eq!(policy.allowed[0].domain_version.as_deref(), Some("2"));
+        assert_eq!(policy.allowed[0].chain_id, 84532);
+        assert_eq!(policy.allowed[1].domain_version, None);
+        assert_eq!(policy.allowed[1].verifying_contract, CONTRACT.parse().unwrap());
+
+        // chainId and verifyingContract are required
+        assert!(TypedDataPolicy::parse("AssetOwnership:1:Asset").is_err());
+        assert!(TypedDataPolicy::parse(&format!("AssetOwnership:1:Asset:{}", CONTRACT)).is_err());
+        assert!(TypedDataPolicy::parse("AssetOwnership:1:Asset:84532:not-an-address").is_err());
+        // the issuer key never signs AuthChain certificates for a caller
+        assert!(TypedDataPolicy::parse(&format!("CertificateAuth:*:Certificate:84532:{}", CONTRACT)).is_err());
+        assert!(TypedDataPolicy::parse(&format!("OtherDomain:1:Certificate:84532:{}", CONTRACT)).is_err());
+        assert!(TypedDataPolicy::parse("").unwrap().allowed.is_empty());
+    }
+
+    #[test]
+    fn checks_domain_chain_and_primary_type() {
+        let policy = TypedDataPolicy::parse(&format!("AssetOwnership:1:Asset:84532:{}", CONTRACT)).unwrap();
+
+        assert!(policy.check(&typed_data("AssetOwnership", "1", 84532, "Asset"), 84532).is_ok());
+        assert!(policy.check(&typed_data("AssetOwnership", "1", 1, "Asset"), 84532).is_err());
+        assert!(policy.check(&typed_data("AssetOwnership", "2", 84532, "Asset"), 84532).is_err());
+        assert!(policy.check(&typed_data("OtherDomain", "1", 84532, "Asset"), 84532).is_err());
+        assert!(TypedDataPolicy::default()
+            .check(&typed_data("AssetOwnership", "1", 84532, "Asset"), 84532)
+            .is_err());
+
+        let mut other_contract = typed_data("AssetOwnership", "1", 84532, "Asset");
+        other_contract.domain.verifying_contract = Some(Address::random());
+        assert!(policy.check(&other_contract, 84532).is_err());
+        let mut no_contract = typed_data("AssetOwnership", "1", 84532, "Asset");
+        no_contract.domain.verifying_contract = None;
+        assert!(policy.check(&no_contract, 84532).is_err());
+        assert!(policy.check(&typed_data(CERTIFICATE_DOMAIN_NAME, "2", 84532, "Certificate"), 84532).is_err());
+    }
+}
@@ -1,34 +1,335 @@
-use qrcode::QrCode;
+use crate::certificate::sign_certificate;
+use crate::chain_gateway::ApiError;
+use crate::chain_registry::Chain;
+use crate::models::cert_model::{auth_chain_domain, Certificate, CertificateDTO};
+use crate::models::qr_model::{QrCodeDTO, QrPayload, QrVerification, QrVerificationDTO};
+use anyhow::{anyhow, bail, ensure};
+use axum::{http::StatusCode, Json};
+use ethers::types::{transaction::eip712::Eip712, Address, Signature, H256, U256};
+use ethers::utils::hex::ToHexExt;
 use qrcode::render::svg;
-use serde_json::json;
-
-fn generate_qr_code(certificate: &serde_json::Value) -> Result<String, Box<dyn std::error::Error>> {
-    // Convert certificate to JSON string
-    let cert_str = serde_json::to_string(certificate)?;
-
-    // Generate QR code
-    let code = QrCode::new(cert_str.as_bytes())?;
-
-    // Render as SVG (for web frontend)
-    let svg = code.render::<svg::Color>()
-        .min_dimensions(200, 200)
-        .build();
-
-    Ok(svg)
-}
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Example certificate
-    let certificate = json!({
-        "serial": "XM12345",
-        "imei": "543210987654321",
-        "model": "Redmi Note 14",
-        "date": "2025-05-01",
-        "manufacturerAddress": "0x5678901234567890123456789012345678901234",
-        "signature": "0xabcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890"
-    });
-
-    let svg = generate_qr_code(&certificate)?;
-    println!("{}", svg); // Output SVG for frontend
-    Ok(())
-}
\ No newline at end of file
+use qrcode::QrCode;
+use tracing::instrument;
+
+// Offline form of an issuer-signed certificate, short enough for a QR code that scans
+// from a box label: "AC1:" then the base45 of a fixed binary layout. The prefix names the
+// format version, base45 keeps the whole text in the QR alphanumeric mode.
+//
+// Version 1 layout, integers as minimal unsigned LEB128:
+//   chain id | AuthChain address (20) | owner (20) | date | name | unique id | serial |
+//   metadata count, then each entry | signature r, s, v (65)
+// strings are their UTF-8 length then bytes. Every field has a single encoding, so the
+// same signed certificate always gives the same text, and the same QR code.
+pub const QR_PREFIX: &str = "AC1:";
+
+const BASE45: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+// A certificate with the issuer EIP-712 signature, as carried by a QR code
+#[derive(Clone, Debug)]
+pub struct SignedCertificate {
+    pub certificate: Certificate,
+    pub signature: Signature,
+}
+
+impl SignedCertificate {
+    // The address whose key made the signature, under the domain of the certificate
+    pub fn signer(&self) -> anyhow::Result<Address> {
+        let digest = self.certificate.encode_eip712()?;
+        Ok(self.signature.recover(digest)?)
+    }
+}
+
+pub fn encode(signed: &SignedCertificate) -> anyhow::Result<String> {
+    let certificate = &signed.certificate;
+    let chain_id = certificate.domain.chain_id.ok_or_else(|| anyhow!("Certificate without chain id"))?;
+    let auth_chain = certificate
+        .domain
+        .verifying_contract
+        .ok_or_else(|| anyhow!("Certificate without AuthChain address"))?;
+    ensure!(chain_id <= U256::from(u64::MAX), "Chain id out of range");
+    ensure!(certificate.date <= U256::from(u64::MAX), "Date out of range");
+
+    let mut bytes = Vec::with_capacity(160);
+    write_varint(&mut bytes, chain_id.as_u64());
+    bytes.extend_from_slice(auth_chain.as_bytes());
+    bytes.extend_from_slice(certificate.owner.as_bytes());
+    write_varint(&mut bytes, certificate.date.as_u64());
+    write_str(&mut bytes, &certificate.name);
+    write_str(&mut bytes, &certificate.unique_id);
+    write_str(&mut bytes, &certificate.serial);
+    write_varint(&mut bytes, certificate.metadata.len() as u64);
+    for entry in &certificate.metadata {
+        write_str(&mut bytes, entry);
+    }
+    bytes.extend_from_slice(&signed.signature.to_vec());
+
+    Ok(format!("{}{}", QR_PREFIX, base45_encode(&bytes)))
+}
+
+pub fn decode(payload: &str) -> anyhow::Result<SignedCertificate> {
+    let Some(encoded) = payload.strip_prefix(QR_PREFIX) else {
+        match payload.split_once(':') {
+            Some((version, _)) if version.starts_with("AC") => bail!("Unsupported format {}", version),
+            _ => bail!("Not a certificate QR payload"),
+        }
+    };
+    let bytes = base45_decode(encoded)?;
+
+    let mut reader = Reader { bytes: &bytes };
+    let chain_id = reader.varint()?;
+    let auth_chain = Address::from_slice(reader.take(20)?);
+    let owner = Address::from_slice(reader.take(20)?);
+    let date = reader.varint()?;
+    let name = reader.string()?;
+    let unique_id = reader.string()?;
+    let serial = reader.string()?;
+    let count = reader.varint()?;
+    let mut metadata = Vec::new();
+    for _ in 0..count {
+        metadata.push(reader.string()?);
+    }
+    let signature = Signature::try_from(reader.take(65)?)?;
+    ensure!(reader.bytes.is_empty(), "Trailing bytes after the signature");
+
+    Ok(SignedCertificate {
+        certificate: Certificate {
+            name,
+            unique_id,
+            serial,
+            date: U256::from(date),
+            owner,
+            metadata,
+            domain: auth_chain_domain(chain_id, auth_chain),
+        },
+        signature,
+    })
+}
+
+// Decode a payload and check it was signed by `issuer`; the certificate when it was
+pub fn verify(payload: &str, issuer: Address) -> anyhow::Result<SignedCertificate> {
+    let signed = decode(payload)?;
+    ensure!(signed.signer()? == issuer, "Not signed by {:?}", issuer);
+    Ok(signed)
+}
+
+// Scannable SVG of a payload, e.g. for a web page or a label printer
+pub fn render_svg(payload: &str) -> anyhow::Result<String> {
+    let code = QrCode::new(payload.as_bytes())?;
+    Ok(code.render::<svg::Color>().min_dimensions(200, 200).build())
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn write_str(bytes: &mut Vec<u8>, value: &str) {
+    write_varint(bytes, value.len() as u64);
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> anyhow::Result<&'a [u8]> {
+        ensure!(self.bytes.len() >= count, "Payload too short");
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    // only the minimal encoding is accepted, a payload has a single form
+    fn varint(&mut self) -> anyhow::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            ensure!(shift < 63 || byte <= 1, "Integer out of range");
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                ensure!(byte != 0 || shift == 0, "Integer not minimally encoded");
+                return Ok(value);
+            }
+        }
+        bail!("Integer out of range")
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        let length = self.varint()?;
+        ensure!(length <= self.bytes.len() as u64, "Payload too short");
+        Ok(String::from_utf8(self.take(length as usize)?.to_vec())?)
+    }
+}
+
+// RFC 9285: two bytes to three characters, a last odd byte to two
+pub fn base45_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(2) * 3);
+    for chunk in bytes.chunks(2) {
+        let (mut value, digits) = match chunk {
+            [a, b] => ((*a as usize) << 8 | *b as usize, 3),
+            [a] => (*a as usize, 2),
+            _ => unreachable!(),
+        };
+        for _ in 0..digits {
+            encoded.push(BASE45[value % 45] as char);
+            value /= 45;
+        }
+    }
+    encoded
+}
+
+pub fn base45_decode(encoded: &str) -> anyhow::Result<Vec<u8>> {
+    let digits = encoded
+        .bytes()
+        .map(|c| BASE45.iter().position(|&d| d == c).ok_or_else(|| anyhow!("Invalid base45 character")))
+        .collect::<anyhow::Result<Vec<usize>>>()?;
+
+    let mut bytes = Vec::with_capacity(digits.len() / 3 * 2 + 1);
+    for chunk in digits.chunks(3) {
+        match chunk {
+            [c, d, e] => {
+                let value = c + d * 45 + e * 45 * 45;
+                ensure!(value <= 0xffff, "Invalid base45 group");
+                bytes.extend_from_slice(&(value as u16).to_be_bytes());
+            }
+            [c, d] => {
+                let value = c + d * 45;
+                ensure!(value <= 0xff, "Invalid base45 group");
+                bytes.push(value as u8);
+            }
+            _ => bail!("Invalid base45 length"),
+        }
+    }
+    Ok(bytes)
+}
+
+// Handler for POST /v1/certificates/qr
+#[utoipa::path(
+    post,
+    path = "/v1/certificates/qr",
+    request_body = QrCodeDTO,
+    responses(
+        (status = 200, description = "Compact QR payload of the signed certificate and its SVG, the same for the same certificate", body = QrPayload),
+        (status = 400, description = "Invalid certificate or signature"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, unique_id = %dto.certificate.unique_id))]
+pub async fn certificate_qr(Chain(chain): Chain, Json(dto): Json<QrCodeDTO>) -> Result<Json<QrPayload>, ApiError> {
+    let certificate = Certificate::from_dto(dto.certificate, chain.auth_chain_domain()).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let signature = match dto.signature {
+        Some(signature) => signature.parse::<Signature>().map_err(|_| StatusCode::BAD_REQUEST)?,
+        // RFC 6979 signatures are deterministic, signing again gives the same QR code
+        None => sign_certificate(&chain, &certificate).await?,
+    };
+
+    let payload = encode(&SignedCertificate { certificate, signature }).map_err(bad_request)?;
+    let svg = render_svg(&payload).map_err(bad_request)?;
+    Ok(Json(QrPayload { payload, svg }))
+}
+
+// Handler for POST /v1/certificates/qr/verifications
+#[utoipa::path(
+    post,
+    path = "/v1/certificates/qr/verifications",
+    request_body = QrVerificationDTO,
+    responses(
+        (status = 200, description = "The certificate of the payload, and whether the issuer of this chain signed it", body = QrVerification),
+        (status = 400, description = "Not a certificate QR payload"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id))]
+pub async fn verify_certificate_qr(
+    Chain(chain): Chain,
+    Json(dto): Json<QrVerificationDTO>,
+) -> Result<Json<QrVerification>, ApiError> {
+    let signed = decode(dto.payload.trim()).map_err(bad_request)?;
+    let signer = signed.signer().map_err(bad_request)?;
+    let issuer = chain.gateway.get_owner().await?;
+    let certificate = &signed.certificate;
+
+    // a certificate of another deployment is not vouched for by this issuer
+    let same_domain = certificate.domain == chain.auth_chain_domain();
+    Ok(Json(QrVerification {
+        valid: same_domain && signer == issuer,
+        signer: format!("{:?}", signer),
+        issuer: format!("{:?}", issuer),
+        chain_id: certificate.domain.chain_id.unwrap_or_default().as_u64(),
+        auth_chain: format!("{:?}", certificate.domain.verifying_contract.unwrap_or_default()),
+        struct_hash: H256::from(certificate.struct_hash().map_err(|_| StatusCode::BAD_REQUEST)?).encode_hex_with_prefix(),
+        certificate: CertificateDTO {
+            name: certificate.name.clone(),
+            unique_id: certificate.unique_id.clone(),
+            serial: certificate.serial.clone(),
+            date: certificate.date.as_u64(),
+            owner: format!("{:?}", certificate.owner),
+            metadata: certificate.metadata.clone(),
+        },
+    }))
+}
+
+fn bad_request(e: anyhow::Error) -> ApiError {
+    ApiError {
+        status: StatusCode::BAD_REQUEST,
+        message: Some(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::{LocalWallet, Signer};
+
+    async fn sign(wallet: &LocalWallet) -> SignedCertificate {
+        let certificate = Certificate {
+            name: "Redmi Note 14".to_string(),
+            unique_id: "543210987654321".to_string(),
+            serial: "XM12345".to_string(),
+            date: U256::from(1746057600u64),
+            owner: "0x5678901234567890123456789012345678901234".parse().unwrap(),
+            metadata: vec!["color:black".to_string()],
+            domain: auth_chain_domain(31337, "0x5FbDB2315678afecb367f032d93F642f64180aa3".parse().unwrap()),
+        };
+        let signature = wallet.sign_typed_data(&certificate).await.unwrap();
+        SignedCertificate { certificate, signature }
+    }
+
+    #[test]
+    fn base45_matches_rfc_9285() {
+        assert_eq!(base45_encode(b"AB"), "BB8");
+        assert_eq!(base45_encode(b"Hello!!"), "%69 VD92EX0");
+        assert_eq!(base45_encode(b"base-45"), "UJCLQE7W581");
+        assert_eq!(base45_decode("QED8WEX0").unwrap(), b"ietf!");
+        assert!(base45_decode("GGW").is_err()); // 65536
+        assert!(base45_decode("BB8A").is_err());
+        assert!(base45_decode("bb8").is_err());
+    }
+
+    #[tokio::test]
+    async fn round_trips_deterministically() {
+        let issuer = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let signed = sign(&issuer).await;
+
+        let payload = encode(&signed).unwrap();
+        assert!(payload.starts_with(QR_PREFIX));
+        assert_eq!(encode(&sign(&issuer).await).unwrap(), payload);
+        assert!(payload.len() < 300, "{}", payload.len());
+
+        let decoded = verify(&payload, issuer.address()).unwrap();
+        assert_eq!(decoded.certificate.struct_hash().unwrap(), signed.certificate.struct_hash().unwrap());
+        assert_eq!(decoded.certificate.domain, signed.certificate.domain);
+        assert_eq!(decoded.signature, signed.signature);
+        assert!(verify(&payload, Address::random()).is_err());
+
+        // any change to the text is a different or an invalid certificate
+        let tampered = payload.replacen(QR_PREFIX, "AC2:", 1);
+        assert_eq!(decode(&tampered).unwrap_err().to_string(), "Unsupported format AC2");
+        assert!(decode(&payload[..payload.len() - 3]).is_err());
+        assert!(decode(&format!("{}00", payload)).is_err());
+    }
+}
@@ -0,0 +1,422 @@
+use crate::certificate::{AuthChain, AuthChainErrors};
+use crate::chain_gateway::{ApiError, GAS_MARGIN_PERCENT};
+use crate::metrics::METRICS;
+use crate::models::relayer_model::RelayerStatus;
+use crate::models::simulation_model::Simulation;
+use crate::models::transfer_model::Transfer;
+use crate::rpc_client::wait_for_receipt;
+use crate::tx_tracker::{PendingTx, TxTracker};
+use crate::chain_registry::Chain;
+use crate::utility::{to_bytes, EthClient};
+use axum::{http::StatusCode, Json};
+use ethers::types::transaction::{eip2718::TypedTransaction, eip712::Eip712};
+use ethers::{
+    prelude::*,
+    types::Signature,
+    utils::format_ether,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::{error, info, instrument, warn, Span};
+
+// Why a user-signed request was not relayed
+#[derive(Debug)]
+pub enum RelayError {
+    Expired,
+    InvalidSignature,
+    NonceMismatch,
+    NotOwner,
+    NotFound,
+    Revoked,
+    Reverted(String),
+    BudgetExceeded,
+    Rpc(String),
+}
+
+impl From<RelayError> for StatusCode {
+    fn from(err: RelayError) -> Self {
+        match err {
+            RelayError::Expired | RelayError::Reverted(_) => StatusCode::BAD_REQUEST,
+            RelayError::InvalidSignature => StatusCode::UNAUTHORIZED,
+            RelayError::NotOwner => StatusCode::FORBIDDEN,
+            RelayError::NotFound => StatusCode::NOT_FOUND,
+            RelayError::NonceMismatch | RelayError::Revoked => StatusCode::CONFLICT,
+            RelayError::BudgetExceeded => StatusCode::SERVICE_UNAVAILABLE,
+            RelayError::Rpc(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<RelayError> for ApiError {
+    fn from(err: RelayError) -> Self {
+        let message = match &err {
+            RelayError::Expired => Some("Transfer deadline passed".to_string()),
+            RelayError::InvalidSignature => Some("Signature was not made by the item owner".to_string()),
+            RelayError::NonceMismatch => Some("Nonce already used".to_string()),
+            RelayError::NotOwner => Some("Sender is not the current owner".to_string()),
+            RelayError::Revoked => Some("Item revoked".to_string()),
+            RelayError::Reverted(message) => Some(message.clone()),
+            RelayError::BudgetExceeded => Some("Relayer gas budget exhausted".to_string()),
+            RelayError::NotFound | RelayError::Rpc(_) => None,
+        };
+        Self { status: err.into(), message }
+    }
+}
+
+impl From<AuthChainErrors> for RelayError {
+    fn from(err: AuthChainErrors) -> Self {
+        match err {
+            AuthChainErrors::ItemNotFound(_) => RelayError::NotFound,
+            AuthChainErrors::ItemRevoked(_) => RelayError::Revoked,
+            AuthChainErrors::NotItemOwner(_) => RelayError::InvalidSignature,
+            AuthChainErrors::TransferExpired(_) => RelayError::Expired,
+            other => RelayError::Reverted(format!("{:?}", other)),
+        }
+    }
+}
+
+// Relayer wallet, budget and spending ledger shared by the relayers of every chain
+pub struct RelayerConfig {
+    pub wallet: Option<LocalWallet>, // falls back to the issuer wallet
+    pub gas_budget: U256,            // per chain, in that chain's native currency
+    pub ledger: Arc<GasLedger>,
+}
+
+// Gas fees charged to the relayer budget, by chain id. A relay reserves its estimated
+// cost before sending and settles to the paid fee once the receipt is in; without a
+// receipt the reservation stays charged. With a file the amounts are written on every
+// change and read back on start, so a restart does not reset the budget.
+#[derive(Default)]
+pub struct GasLedger {
+    file: Option<PathBuf>,
+    spent: Mutex<HashMap<u64, U256>>,
+}
+
+impl GasLedger {
+    pub fn open(file: PathBuf) -> anyhow::Result<Self> {
+        let spent = if file.exists() {
+            serde_json::from_str(&fs::read_to_string(&file)?)
+                .map_err(|e| anyhow::anyhow!("Invalid {}: {}", file.display(), e))?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            file: Some(file),
+            spent: Mutex::new(spent),
+        })
+    }
+
+    pub fn spent(&self, chain_id: u64) -> U256 {
+        self.spent.lock().unwrap().get(&chain_id).copied().unwrap_or_default()
+    }
+
+    // Charge `cost` to the chain, unless it would go over `budget`
+    pub fn reserve(&self, chain_id: u64, cost: U256, budget: U256) -> Result<(), RelayError> {
+        let mut spent = self.spent.lock().unwrap();
+        let charged = spent.entry(chain_id).or_default();
+
+        if *charged + cost > budget {
+            return Err(RelayError::BudgetExceeded);
+        }
+        *charged += cost;
+        self.persist(&spent);
+        Ok(())
+    }
+
+    // Replace a reservation with the fee actually paid, zero when nothing was sent
+    pub fn settle(&self, chain_id: u64, reserved: U256, paid: U256) {
+        let mut spent = self.spent.lock().unwrap();
+        let charged = spent.entry(chain_id).or_default();
+
+        *charged = charged.saturating_sub(reserved) + paid;
+        self.persist(&spent);
+    }
+
+    fn persist(&self, spent: &HashMap<u64, U256>) {
+        let Some(file) = &self.file else { return };
+
+        let written = serde_json::to_string_pretty(spent)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| fs::write(file, json).map_err(anyhow::Error::from));
+        if let Err(e) = written {
+            error!(error = %e, file = %file.display(), "Relayer gas ledger not persisted");
+        }
+    }
+}
+
+// Where the relayer gas ledger is kept between runs
+pub fn relayer_ledger_file() -> PathBuf {
+    PathBuf::from(std::env::var("RELAYER_LEDGER_FILE").unwrap_or_else(|_| "relayer_gas.json".to_string()))
+}
+
+// Submits user-signed EIP-712 requests from a backend wallet, so end users never need ETH.
+// Gas fees are charged against a budget kept in the ledger.
+pub struct Relayer {
+    client: Arc<EthClient>,
+    auth_chain: Address,
+    gas_budget: U256,
+    ledger: Arc<GasLedger>,
+    tracker: Arc<TxTracker>,
+}
+
+impl Relayer {
+    pub fn new(
+        client: Arc<EthClient>,
+        auth_chain: Address,
+        gas_budget: U256,
+        ledger: Arc<GasLedger>,
+        tracker: Arc<TxTracker>,
+    ) -> Self {
+        Self {
+            client,
+            auth_chain,
+            gas_budget,
+            ledger,
+            tracker,
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.client.signer().chain_id()
+    }
+
+    pub fn address(&self) -> Address {
+        self.client.address()
+    }
+
+    // Validate an owner-signed transfer against chain state and simulate it, without sending it
+    #[instrument(skip_all, fields(unique_id = %transfer.unique_id, from = ?transfer.from, to = ?transfer.to))]
+    pub async fn simulate_transfer(
+        &self,
+        transfer: &Transfer,
+        signature: Signature,
+    ) -> Result<Simulation, RelayError> {
+        let (_, gas, max_fee) = self.prepare(transfer, signature).await?;
+
+        info!(gas = %gas, "Transfer simulated");
+        Ok(Simulation::new("transfer_item", &transfer.unique_id, gas, max_fee))
+    }
+
+    // Validate an owner-signed transfer against chain state, simulate it, then submit it
+    #[instrument(
+        skip_all,
+        fields(unique_id = %transfer.unique_id, from = ?transfer.from, to = ?transfer.to, tx_hash)
+    )]
+    pub async fn relay_transfer(
+        &self,
+        transfer: &Transfer,
+        signature: Signature,
+    ) -> Result<TransactionReceipt, RelayError> {
+        let (call, gas, max_fee) = self.prepare(transfer, signature).await?;
+        let chain_id = self.chain_id();
+        let gas_limit = gas * GAS_MARGIN_PERCENT / 100;
+
+        // Reserve the most the transaction can cost up front, concurrent relays cannot
+        // overdraw the budget
+        let reserved = gas_limit * max_fee;
+        self.ledger.reserve(chain_id, reserved, self.gas_budget)?;
+
+        let call = call.gas(gas_limit);
+        let tx_hash = match call.send().await {
+            Ok(pending_tx) => *pending_tx,
+            Err(e) => {
+                self.ledger.settle(chain_id, reserved, U256::zero());
+                return Err(decode_revert(e));
+            }
+        };
+        let sent = Instant::now();
+        Span::current().record("tx_hash", tracing::field::debug(tx_hash));
+        info!("Transfer relayed");
+        self.tracker.track(PendingTx::new(chain_id, tx_hash, "transfer_item", &transfer.unique_id));
+
+        // Until a receipt is in, the reservation stays charged: the transaction may still be mined
+        let receipt = wait_for_receipt(self.client.provider(), tx_hash)
+            .await
+            .map_err(|e| RelayError::Rpc(e.to_string()))?;
+        self.tracker.finish(tx_hash);
+        let Some(receipt) = receipt else {
+            warn!(reserved = %format_ether(reserved), "Relayed transaction without a receipt, its reservation stays charged");
+            return Err(RelayError::Rpc("transaction dropped".to_string()));
+        };
+
+        METRICS.transaction(chain_id, sent, &receipt);
+
+        let paid = receipt.gas_used.unwrap_or(gas_limit)
+            * receipt.effective_gas_price.unwrap_or(max_fee);
+        self.ledger.settle(chain_id, reserved, paid);
+
+        if receipt.status != Some(1.into()) {
+            return Err(RelayError::Reverted(format!("{:?}", receipt.transaction_hash)));
+        }
+
+        Ok(receipt)
+    }
+
+    // The checks of a transfer and its simulation: the call to send, its gas estimate and
+    // the highest gas price it is sent with
+    async fn prepare(
+        &self,
+        transfer: &Transfer,
+        signature: Signature,
+    ) -> Result<(ContractCall<EthClient, ()>, U256, U256), RelayError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| RelayError::Rpc(e.to_string()))?
+            .as_secs();
+
+        if transfer.deadline < U256::from(now) {
+            return Err(RelayError::Expired);
+        }
+
+        let contract = AuthChain::new(self.auth_chain, self.client.clone());
+
+        let item = contract
+            .get_item(transfer.unique_id.clone())
+            .call()
+            .await
+            .map_err(|e| RelayError::Rpc(e.to_string()))?;
+
+        if item.unique_id == [0u8; 32] {
+            return Err(RelayError::NotFound);
+        }
+        if item.revoked {
+            return Err(RelayError::Revoked);
+        }
+        if item.owner != transfer.from {
+            return Err(RelayError::NotOwner);
+        }
+
+        let nonce = contract
+            .nonces(transfer.from)
+            .call()
+            .await
+            .map_err(|e| RelayError::Rpc(e.to_string()))?;
+
+        if nonce != transfer.nonce {
+            return Err(RelayError::NonceMismatch);
+        }
+
+        let digest = transfer
+            .encode_eip712()
+            .map_err(|e| RelayError::Rpc(e.to_string()))?;
+
+        if signature.recover(digest).ok() != Some(transfer.from) {
+            return Err(RelayError::InvalidSignature);
+        }
+
+        let call = contract.transfer_item(
+            transfer.unique_id.clone(),
+            transfer.to,
+            transfer.deadline,
+            to_bytes(signature),
+        );
+
+        // Simulate with eth_call first, a revert here costs nothing
+        call.call().await.map_err(decode_revert)?;
+
+        let gas = call.estimate_gas().await.map_err(decode_revert)?;
+
+        // Set the fee here rather than leave it to the middleware, it bounds what the
+        // transaction can cost
+        let mut call = call;
+        let max_fee = match &mut call.tx {
+            TypedTransaction::Eip1559(tx) => {
+                let (max_fee, max_priority_fee) = self
+                    .client
+                    .estimate_eip1559_fees(None)
+                    .await
+                    .map_err(|e| RelayError::Rpc(e.to_string()))?;
+                tx.max_fee_per_gas = Some(max_fee);
+                tx.max_priority_fee_per_gas = Some(max_priority_fee);
+                max_fee
+            }
+            tx => {
+                let gas_price = self
+                    .client
+                    .get_gas_price()
+                    .await
+                    .map_err(|e| RelayError::Rpc(e.to_string()))?;
+                tx.set_gas_price(gas_price);
+                gas_price
+            }
+        };
+
+        Ok((call, gas, max_fee))
+    }
+
+    pub async fn status(&self) -> Result<RelayerStatus, RelayError> {
+        let balance = self
+            .client
+            .get_balance(self.address(), None)
+            .await
+            .map_err(|e| RelayError::Rpc(e.to_string()))?;
+        let spent = self.ledger.spent(self.chain_id());
+
+        Ok(RelayerStatus {
+            address: format!("{:?}", self.address()),
+            balance: format_ether(balance),
+            gas_budget: format_ether(self.gas_budget),
+            gas_spent: format_ether(spent),
+            gas_remaining: format_ether(self.gas_budget.saturating_sub(spent)),
+        })
+    }
+}
+
+fn decode_revert(e: ContractError<EthClient>) -> RelayError {
+    match e.decode_contract_revert::<AuthChainErrors>() {
+        Some(err) => err.into(),
+        None => RelayError::Rpc(e.to_string()),
+    }
+}
+
+//to check the relayer wallet and its gas budget
+#[utoipa::path(
+    get,
+    path = "/v1/relayer",
+    responses(
+        (status = 200, description = "Relayer wallet and gas budget", body = RelayerStatus),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn relayer_status(
+    Chain(chain): Chain,
+) -> Result<Json<RelayerStatus>, StatusCode> {
+    let status = chain.relayer.status().await.map_err(|e| {
+        error!(error = ?e, chain_id = chain.chain_id, "Relayer status failed");
+        StatusCode::from(e)
+    })?;
+
+    Ok(Json(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reservations_are_settled_and_survive_a_restart() {
+        let file = std::env::temp_dir().join(format!("relayer-gas-test-{}.json", std::process::id()));
+        let _ = fs::remove_file(&file);
+        let budget = U256::from(100);
+
+        let ledger = GasLedger::open(file.clone()).unwrap();
+        ledger.reserve(31337, U256::from(60), budget).unwrap();
+        // the first relay is still in flight, its reservation counts
+        assert!(matches!(ledger.reserve(31337, U256::from(50), budget), Err(RelayError::BudgetExceeded)));
+        ledger.reserve(1, U256::from(50), budget).unwrap(); // budgets are per chain
+
+        ledger.settle(31337, U256::from(60), U256::from(45));
+        ledger.reserve(31337, U256::from(50), budget).unwrap();
+        ledger.settle(31337, U256::from(50), U256::zero()); // never sent
+
+        let restarted = GasLedger::open(file.clone()).unwrap();
+        assert_eq!(restarted.spent(31337), U256::from(45));
+        assert_eq!(restarted.spent(1), U256::from(50));
+        assert_eq!(restarted.spent(84532), U256::zero());
+        fs::remove_file(&file).unwrap();
+    }
+}
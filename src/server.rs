@@ -1,27 +1,77 @@
 use axum::Router;
 
 use crate::app_router::paths;
-use crate::app_state::init_app_state;
+use crate::app_state::{env_number, init_app_state};
+use crate::certificate_queue::{spawn_batch_worker, BatchWorkerConfig};
+use crate::http_config::HttpConfig;
 use crate::models::cert_model::RouterPath;
+use crate::shutdown::drain;
+use crate::telemetry::init_tracing;
+use crate::tx_tracker::pending_tx_file;
 use anyhow::Result;
 use dotenv::dotenv;
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use std::time::Duration;
 
 pub async fn server() -> Result<()> {
-    eprintln!("PROJECT STARTING...");
     // Load environment variables
     dotenv().ok();
+    let _telemetry = init_tracing()?;
+    tracing::info!("PROJECT STARTING...");
     // dotenv::from_path("../.env").ok();
   
     let state = init_app_state().await?;
 
+    // Transactions a previous run left pending
+    let resumed = state.tx_tracker.resume(&pending_tx_file(), &state.chains)?;
+    if resumed > 0 {
+        tracing::info!(resumed, "Tracking transactions pending from the last run");
+    }
+
+    // Anchors the certificates queued with POST /v1/certificates/queue in batches
+    spawn_batch_worker(state.clone(), BatchWorkerConfig::from_env()?);
+
+    // How long a shutdown waits for pending receipts, and then for the requests still running
+    let drain_timeout = Duration::from_secs(env_number("SHUTDOWN_TIMEOUT_SECS", 30)?);
+    let grace = Duration::from_secs(env_number("SHUTDOWN_GRACE_SECS", 5)?);
+
+    let http = HttpConfig::from_env()?;
+
     // Define routes
-    let app: Router = paths(state, RouterPath::init());
+    let app: Router = paths(state.clone(), RouterPath::init(), &http);
 
-    eprintln!("Project started and listening on 127.0.0.1:8080");
+    // Stop once the pending transactions are drained, requests still waiting on a receipt
+    // get `grace` to finish, their transactions are persisted either way
+    let handle = Handle::new();
+    let shutdown = handle.clone();
+    tokio::spawn(async move {
+        drain(state, drain_timeout).await;
+        shutdown.graceful_shutdown(Some(grace));
+    });
 
     // Start the server
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await?;
-    axum::serve(listener, app).await?;
+    match &http.tls {
+        Some(tls) => {
+            let _ = rustls::crypto::ring::default_provider().install_default();
+            let config = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+                .await
+                .map_err(|e| anyhow::anyhow!("Cannot load {}: {}", tls.cert_path.display(), e))?;
+
+            tracing::info!("Project started and listening on https://{}", http.bind);
+            axum_server::bind_rustls(http.bind, config)
+                .handle(handle)
+                .serve(app.into_make_service())
+                .await?;
+        }
+        None => {
+            tracing::info!("Project started and listening on http://{}", http.bind);
+            axum_server::bind(http.bind)
+                .handle(handle)
+                .serve(app.into_make_service())
+                .await?;
+        }
+    }
 
     Ok(()) // another way to say return nothing
 }
@@ -0,0 +1,81 @@
+use crate::tx_tracker::{pending_tx_file, TxTracker};
+use crate::utility::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+// Resolves on SIGTERM or Ctrl+C
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!(error = %e, "Ctrl+C handler failed");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!(error = %e, "SIGTERM handler failed");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+// Graceful shutdown: refuse new writes, wait up to `timeout` for the pending receipts and
+// persist the transactions still pending for the next start. Resolves once the server may stop.
+pub async fn drain(state: AppState, timeout: Duration) {
+    shutdown_signal().await;
+
+    let tracker = &state.tx_tracker;
+    tracker.start_draining();
+    info!(pending = tracker.pending().len(), "Shutting down, waiting for pending transactions");
+
+    if !tracker.drain(timeout).await {
+        warn!(pending = tracker.pending().len(), "Transactions still pending after {}s", timeout.as_secs());
+    }
+
+    let file = pending_tx_file();
+    match tracker.persist(&file) {
+        Ok(0) => info!("No pending transactions"),
+        Ok(count) => info!(count, file = %file.display(), "Pending transactions persisted"),
+        Err(e) => error!(error = %e, file = %file.display(), "Pending transactions not persisted"),
+    }
+}
+
+// Middleware of the routes that send a transaction, refusing them once the server is
+// draining; reads and signature checks keep being served until it stops
+pub async fn reject_writes_while_draining(
+    State(tracker): State<Arc<TxTracker>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if tracker.is_draining() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, "30")],
+            "Server is shutting down",
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
@@ -2,16 +2,16 @@ use dotenv::dotenv;
 use ethers::signers::{LocalWallet, Signer};
 use serde_json::json;
 use std::env;
-use axum::extract::State;
 use axum::Json;
 use ethers::types::Signature;
 use tiny_keccak::{Hasher, Keccak};
 use crate::models::sig_model::AssetDto;
+use tracing::debug;
 
-// Handler for POST /verify
+// Handler for POST /v1/signatures
 #[utoipa::path(
     post,
-    path = "/signature",
+    path = "/v1/signatures",
     request_body = AssetDto,
     responses(
         (status = 200, description = "To sign an object", body = String),
@@ -34,7 +34,7 @@ pub async fn signature(Json(asset_dto): Json<AssetDto>)  -> Result<Json<Signatur
 
     let asset_serialized = serde_json::to_string(&asset).unwrap();
 
-    println!("Asset JSON: {}", asset_serialized);
+    debug!(asset = %asset_serialized, "Signing asset");
 
     // hash it
     let mut keccak = Keccak::v256();
@@ -42,11 +42,10 @@ pub async fn signature(Json(asset_dto): Json<AssetDto>)  -> Result<Json<Signatur
     let mut asset_hash = [0u8; 32];
     keccak.finalize(&mut asset_hash);
 
-    println!("Asset hash: 0x{}", hex::encode(asset_hash));
+    debug!(asset_hash = %format!("0x{}", hex::encode(asset_hash)), "Asset hashed");
 
     // to sign the hash
     let signature = wallet.sign_message(asset_hash).await;
-    println!("Signature: {:?}", signature);
 
     Ok(Json(signature.unwrap()))
 }
\ No newline at end of file
@@ -1,9 +1,12 @@
-use crate::models::sig_model::{Asset, AssetDto};
-use crate::utility::{to_bytes, AppState};
+use crate::chain_gateway::GatewayError;
+use crate::chain_registry::Chain;
+use crate::models::sig_model::{self, AssetDto};
+use crate::utility::AppState;
 
 use axum::extract::{Json, State};
 
 use anyhow::Result;
+use tracing::{debug, error};
 use ethers::{
     contract::abigen,
     signers::Signer,
@@ -17,10 +20,10 @@ abigen!(
     "./artifacts/contracts/SignatureVerifier.sol/SignatureVerifier.json"
 );
 
-// Handler for POST /verify
+// Handler for POST /v1/signatures/verify
 #[utoipa::path(
     post,
-    path = "/verify",
+    path = "/v1/signatures/verify",
     request_body = AssetDto,
     responses(
         (status = 200, description = "Signature verification result", body = bool),
@@ -30,41 +33,38 @@ abigen!(
 )]
 pub async fn verify_signature(
     State(state): State<AppState>,
+    Chain(chain): Chain,
     Json(asset_dto): Json<AssetDto>,
 ) -> Result<Json<String>, axum::http::StatusCode> {
     // Convert AssetDto to Asset
-    let mut asset: Asset = asset_dto.clone()
-        .try_into()
+    let mut asset = sig_model::Asset::from_dto(asset_dto.clone(), chain.signature_verifier_domain())
         .map_err(|_| axum::http::StatusCode::BAD_REQUEST)?;
 
-    println!("backend wallet address {:?}", state.wallet_address);
+    debug!(backend_wallet = ?state.wallet_address, "Verifying asset signature");
 
     asset.owner = state.wallet_address; //backend wallet address
 
-    let contract = SignatureVerifier::new(state.signature_verifier, state.eth_client.clone());
-
     // accessing the wallet from SignerMiddleware
-    let signature: Signature = state
+    let signature: Signature = chain
         .eth_client
         .signer()
         .sign_typed_data(&asset)
         .await
         .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let user_wallet = asset_dto.owner.parse().unwrap();
-
-    println!("user wallet {:?}", user_wallet);
+    let user_wallet = asset_dto.owner.parse().map_err(|_| axum::http::StatusCode::BAD_REQUEST)?;
 
-    let contract_asset: signature_verifier::Asset = asset.into();
+    debug!(user_wallet = ?user_wallet, "Expected signer");
 
-    let is_valid = contract
-        .verify_asset_signature(contract_asset, to_bytes(signature), user_wallet) //user wallet address
-        .call()
-        .await
-        .map_err(|e| {
-            eprintln!("Contract error: {:?}", e);
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    // the contract reverts when the signer is not the expected one
+    let is_valid = match chain.gateway.verify_asset(asset, signature, user_wallet).await { //user wallet address
+        Ok(is_valid) => is_valid,
+        Err(GatewayError::InvalidSignature) => false,
+        Err(e) => {
+            error!(error = ?e, chain_id = chain.chain_id, "Signature verification failed");
+            return Err(e.into());
+        }
+    };
 
     Ok(Json(format!("Signature valid: {}", is_valid)))
 }
@@ -72,11 +72,11 @@ pub async fn verify_signature(
 //to check the contract availability
 #[utoipa::path(
     get,
-    path = "/verify/status",
+    path = "/v1/signatures/verifier",
     responses(
         (status = 200, description = "Contract status", body = String)
     )
 )]
-pub async fn check_status(State(state): State<AppState>) -> Json<String> {
-    Json(format!("Contract at {:?}", state.signature_verifier))
+pub async fn check_status(Chain(chain): Chain) -> Json<String> {
+    Json(format!("Contract at {:?}", chain.signature_verifier))
 }
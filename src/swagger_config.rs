@@ -1,16 +1,39 @@
-use crate::certificate::{__path_create_item, __path_get_item, __path_get_owner};
-use crate::models::cert_model::Item;
-use crate::models::sig_model::AssetDto;
+use crate::batch::{__path_anchor_batch, __path_anchoring_status, __path_certificate_proof, __path_queue_certificate, __path_verify_proof};
+use crate::models::batch_model::{AnchoredBatch, AnchoringStatus, BatchDTO, BatchLeaf, CertificateProof, ProofVerification, ProofVerificationDTO, QueueCertificateDTO, QueuedCertificate};
+use crate::certificate::{__path_create_item, __path_get_item, __path_get_owner, __path_reissue_item, __path_revoke_item};
+use crate::chain_registry::__path_list_chains;
+use crate::health::{__path_health_live, __path_health_ready, __path_info};
+use crate::metrics::__path_metrics;
+use crate::qr_code::{__path_certificate_qr, __path_verify_certificate_qr};
+use crate::document::__path_certificate_document;
+use crate::models::document_model::DocumentFormat;
+use crate::models::qr_model::{QrCodeDTO, QrPayload, QrVerification, QrVerificationDTO};
+use crate::models::health_model::{ChainReadiness, HealthCheck, Readiness, ServiceInfo};
+use crate::models::chain_model::ChainInfo;
+use crate::models::cert_model::{CertificateDTO, CreatedItem, Item, ReissueDTO, ReissuedItem, RevocationReason, RevokeDTO, RevokedItem, SignatureCheck};
+use crate::models::sig_model::{AssetDto, OwnerAddress, RegisteredAsset};
+use crate::models::relayer_model::RelayerStatus;
+use crate::models::simulation_model::Simulation;
+use crate::relayer::__path_relayer_status;
 use crate::signature::__path_signature;
 use crate::signature_verifier::{__path_check_status, __path_verify_signature};
+use crate::models::transfer_model::{ProvenanceAction, ProvenanceEntry, TransferDTO, TransferredItem};
+use crate::transfer::{__path_item_history, __path_transfer_item};
+use crate::models::typed_data_model::{SignedTypedData, TypedDataVerification, VerifyTypedDataDTO};
+use crate::models::tx_model::{DecodedCall, DecodedLog, TrackedTransactions, TrackedTx, TxOutcome, TxReport, TxStatus};
+use crate::tx_report::{__path_get_transaction, __path_tracked_transactions};
+use crate::typed_data::{__path_sign_typed_data, __path_verify_typed_data};
+use crate::verify_ownership::{__path_asset_owner, __path_register_asset};
+use crate::models::cert_model::ApiPaths;
+use utoipa::openapi::{Deprecated, OpenApi as OpenApiDoc};
 use utoipa::OpenApi;
 
-// Swagger/OpenAPI configuration
+// Swagger/OpenAPI configuration of the v1 API
 #[derive(OpenApi)]
 #[openapi(
-    paths(verify_signature, check_status, signature, create_item, get_item, get_owner),
+    paths(verify_signature, check_status, signature, create_item, get_item, get_owner, revoke_item, reissue_item, anchor_batch, certificate_proof, verify_proof, queue_certificate, anchoring_status, certificate_qr, verify_certificate_qr, certificate_document, transfer_item, item_history, relayer_status, sign_typed_data, verify_typed_data, register_asset, asset_owner, get_transaction, tracked_transactions, list_chains, health_live, health_ready, info, metrics),
     components(
-        schemas(AssetDto, Item),
+        schemas(AssetDto, CertificateDTO, Item, RevocationReason, RevokeDTO, RevokedItem, ReissueDTO, ReissuedItem, BatchDTO, AnchoredBatch, BatchLeaf, CertificateProof, ProofVerificationDTO, ProofVerification, QueueCertificateDTO, QueuedCertificate, AnchoringStatus, QrCodeDTO, QrPayload, QrVerificationDTO, QrVerification, DocumentFormat, CreatedItem, SignatureCheck, Simulation, TransferDTO, TransferredItem, ProvenanceAction, ProvenanceEntry, RelayerStatus, SignedTypedData, VerifyTypedDataDTO, TypedDataVerification, OwnerAddress, RegisteredAsset, TxReport, TxStatus, DecodedCall, DecodedLog, TrackedTransactions, TrackedTx, TxOutcome, ChainInfo, HealthCheck, ChainReadiness, Readiness, ServiceInfo),
         // responses(Item)
     ),
     tags(
@@ -23,7 +46,7 @@ use utoipa::OpenApi;
     // ),
     info(
         title = "ERI APIs",
-        description = "Signature Verifying Project on the Blockchain",
+        description = "Signature Verifying Project on the Blockchain. Every route is also served per chain under /v1/chains/{chain_id}, the routes without the prefix use the default chain. The unversioned routes of earlier releases are deprecated aliases, see /api-docs/openapi.json.",
         contact(name = "DEAN"),
     
     ),
@@ -37,4 +60,33 @@ use utoipa::OpenApi;
     //     )
     // )
 )]
-pub struct ApiDoc;
+pub struct ApiDocV1;
+
+// The pre-v1 routes: the v1 document under the legacy paths, every operation deprecated
+pub fn legacy_api_doc() -> OpenApiDoc {
+    let mut doc = ApiDocV1::openapi();
+    let v1 = ApiPaths::v1();
+    let legacy = ApiPaths::legacy();
+    // routes added in v1 have no legacy path, and are left out
+    let renames: Vec<(String, Option<String>)> = v1.all().into_iter().flatten().zip(legacy.all()).collect();
+
+    let paths = std::mem::take(&mut doc.paths.paths);
+    for (path, mut item) in paths {
+        let path = match renames.iter().find(|(v1, _)| *v1 == path) {
+            Some((_, None)) => continue,
+            Some((_, Some(legacy))) => {
+                for operation in [&mut item.get, &mut item.post].into_iter().flatten() {
+                    operation.deprecated = Some(Deprecated::True);
+                }
+                legacy.clone()
+            }
+            None if path.starts_with("/v1/") => continue,
+            None => path, // health, info and metrics are not versioned
+        };
+        doc.paths.paths.insert(path, item);
+    }
+
+    doc.info.title = "ERI APIs (deprecated routes)".to_string();
+    doc.info.description = Some("Routes of the releases before v1, still served with a `Deprecation: true` header. Use /api-docs/v1/openapi.json.".to_string());
+    doc
+}
@@ -0,0 +1,83 @@
+use std::env;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer, Registry};
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+// Keeps the span exporter alive, spans still buffered are flushed when it is dropped
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    tracer_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+// Install the global subscriber.
+// RUST_LOG filters (default "info"), LOG_FORMAT=json writes one JSON object per line
+// with the fields of every enclosing span, e.g. the request_id of the HTTP request.
+// Built with the `otel` feature, spans are also exported to OTEL_EXPORTER_OTLP_ENDPOINT
+// (OTLP over HTTP, e.g. http://localhost:4318) when it is set.
+pub fn init_tracing() -> anyhow::Result<Telemetry> {
+    #[cfg_attr(not(feature = "otel"), allow(unused_mut))]
+    let mut layers: Vec<BoxedLayer> = vec![match env::var("LOG_FORMAT").as_deref() {
+        Ok("json") => fmt::layer().json().with_current_span(true).with_span_list(true).boxed(),
+        _ => fmt::layer().boxed(),
+    }];
+
+    #[cfg(feature = "otel")]
+    let tracer_provider = match env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+        Ok(endpoint) => {
+            let (layer, provider) = otel::layer(&endpoint)?;
+            layers.push(layer);
+            Some(provider)
+        }
+        Err(_) => None,
+    };
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .try_init()?;
+
+    Ok(Telemetry {
+        #[cfg(feature = "otel")]
+        tracer_provider,
+    })
+}
+
+#[cfg(feature = "otel")]
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(provider) = self.tracer_provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Span export shutdown failed: {:?}", e);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use super::BoxedLayer;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use tracing_subscriber::Layer;
+
+    pub fn layer(endpoint: &str) -> anyhow::Result<(BoxedLayer, SdkTracerProvider)> {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()?;
+
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name(env!("CARGO_PKG_NAME")).build())
+            .build();
+
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        Ok((tracing_opentelemetry::layer().with_tracer(tracer).boxed(), provider))
+    }
+}
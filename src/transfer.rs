@@ -0,0 +1,95 @@
+use crate::chain_gateway::ApiError;
+use crate::chain_registry::Chain;
+use crate::models::cert_model::ItemPath;
+use crate::models::simulation_model::{DryRunQuery, WriteResult};
+use crate::models::transfer_model::{ProvenanceEntry, Transfer, TransferDTO, TransferredItem};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Json,
+};
+use ethers::types::Signature;
+use tracing::{info, instrument, warn};
+
+#[utoipa::path(
+    post,
+    path = "/v1/items/{unique_id}/transfers",
+    params(
+        ("unique_id" = String, Path, description = "Unique ID of the item to transfer"),
+        DryRunQuery
+    ),
+    request_body = TransferDTO,
+    responses(
+        (status = 200, description = "Item transferred, a Simulation with ?dry_run=true", body = TransferredItem),
+        (status = 400, description = "Invalid input, expired transfer, or the transaction would revert"),
+        (status = 401, description = "Signature was not made by the item owner"),
+        (status = 403, description = "Sender is not the current owner"),
+        (status = 404, description = "Item not found"),
+        (status = 409, description = "Item revoked or nonce already used"),
+        (status = 503, description = "Relayer gas budget exhausted"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, unique_id = %unique_id))]
+pub async fn transfer_item(
+    Chain(chain): Chain,
+    Path(ItemPath { unique_id }): Path<ItemPath>,
+    Query(query): Query<DryRunQuery>,
+    Json(dto): Json<TransferDTO>,
+) -> Result<Json<WriteResult<TransferredItem>>, ApiError> {
+    let transfer = Transfer::from_dto(unique_id.clone(), &dto, chain.auth_chain_domain())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let signature: Signature = dto.signature.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    if query.dry_run {
+        let simulation = chain.relayer.simulate_transfer(&transfer, signature).await.map_err(|e| {
+            warn!(error = ?e, "Transfer simulation failed");
+            ApiError::from(e)
+        })?;
+        return Ok(Json(WriteResult::Simulated(simulation)));
+    }
+
+    // Relay the owner-signed transfer, the owner pays no gas
+    let receipt = chain
+        .relayer
+        .relay_transfer(&transfer, signature)
+        .await
+        .map_err(|e| {
+            warn!(error = ?e, "Transfer not relayed");
+            ApiError::from(e)
+        })?;
+
+    info!(tx_hash = ?receipt.transaction_hash, "ItemTransferred");
+
+    Ok(Json(WriteResult::Sent(TransferredItem {
+        unique_id,
+        from: format!("{:?}", transfer.from),
+        to: format!("{:?}", transfer.to),
+        tx_hash: format!("{:?}", receipt.transaction_hash),
+    })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/items/{unique_id}/history",
+    params(
+        ("unique_id" = String, Path, description = "Unique ID of the item")
+    ),
+    responses(
+        (status = 200, description = "Provenance chain, oldest first", body = Vec<ProvenanceEntry>),
+        (status = 404, description = "Item not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, unique_id = %unique_id))]
+pub async fn item_history(
+    Chain(chain): Chain,
+    Path(ItemPath { unique_id }): Path<ItemPath>,
+) -> Result<Json<Vec<ProvenanceEntry>>, StatusCode> {
+    let history = chain.gateway.item_history(&unique_id).await.map_err(|e| {
+        warn!(error = ?e, "Item history unavailable");
+        StatusCode::from(e)
+    })?;
+
+    Ok(Json(history))
+}
@@ -0,0 +1,470 @@
+use crate::certificate::{auth_chain, AuthChainCalls, AuthChainEvents, AUTHCHAIN_ABI};
+use crate::chain_registry::{Chain, ChainContext};
+use crate::models::cert_model::{auth_chain_domain, Certificate, RevocationReason, SignatureCheck};
+use crate::models::sig_model::{signature_verifier_domain, Asset};
+use crate::models::tx_model::{DecodedCall, DecodedLog, TrackedTransactions, TrackedTx, TxPath, TxReport, TxStatus};
+use crate::signature_verifier::{signature_verifier, SignatureVerifierCalls, SIGNATUREVERIFIER_ABI};
+use crate::verify_ownership::{OwnershipCalls, OwnershipEvents, OWNERSHIP_ABI};
+use crate::utility::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use ethers::{
+    abi::{Abi, AbiDecode, RawLog},
+    contract::EthLogDecode,
+    types::{transaction::eip712::Eip712, Address, Bytes, Log, Signature, Transaction, TransactionReceipt, H256},
+};
+use serde_json::{json, Value};
+use tracing::instrument;
+
+// Handler for GET /v1/tx/{tx_hash}
+#[utoipa::path(
+    get,
+    path = "/v1/tx/{tx_hash}",
+    params(
+        ("tx_hash" = String, Path, description = "Hash of a transaction on the chain, sent by anyone")
+    ),
+    responses(
+        (status = 200, description = "The transaction with its calldata and logs decoded", body = TxReport),
+        (status = 400, description = "Invalid transaction hash"),
+        (status = 404, description = "Unknown transaction"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, tx_hash = %tx_hash))]
+pub async fn get_transaction(
+    Chain(chain): Chain,
+    Path(TxPath { tx_hash }): Path<TxPath>,
+) -> Result<Json<TxReport>, StatusCode> {
+    let tx_hash: H256 = tx_hash.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let (tx, receipt) = chain
+        .gateway
+        .transaction(tx_hash)
+        .await
+        .map_err(StatusCode::from)?;
+
+    Ok(Json(report(&Contracts::of(&chain), tx, receipt)))
+}
+
+// Handler for GET /v1/transactions
+#[utoipa::path(
+    get,
+    path = "/v1/transactions",
+    responses(
+        (status = 200, description = "Transactions of the chain still pending, and the outcomes of those resumed after a restart", body = TrackedTransactions)
+    )
+)]
+pub async fn tracked_transactions(
+    State(state): State<AppState>,
+    Chain(chain): Chain,
+) -> Json<TrackedTransactions> {
+    let mut pending: Vec<TrackedTx> = state
+        .tx_tracker
+        .pending()
+        .iter()
+        .filter(|tx| tx.chain_id == chain.chain_id)
+        .map(TrackedTx::from)
+        .collect();
+    pending.sort_by_key(|tx| tx.sent_at);
+
+    let resumed = state
+        .tx_tracker
+        .resumed()
+        .iter()
+        .filter(|resumed| resumed.tx.chain_id == chain.chain_id)
+        .map(TrackedTx::from)
+        .collect();
+
+    Json(TrackedTransactions { pending, resumed })
+}
+
+// Deployments of a chain, calls and logs of other addresses are still decoded
+// with the ABI they match
+pub struct Contracts {
+    pub chain_id: u64,
+    pub auth_chain: Address,
+    pub signature_verifier: Address,
+    pub ownership: Option<Address>,
+}
+
+impl Contracts {
+    pub fn of(chain: &ChainContext) -> Self {
+        Self {
+            chain_id: chain.chain_id,
+            auth_chain: chain.auth_chain,
+            signature_verifier: chain.signature_verifier,
+            ownership: chain.ownership,
+        }
+    }
+
+    // the ABIs to try on `address`, its own first: some selectors and topics are
+    // shared, e.g. eip712Domain()
+    fn abis_for(&self, address: Address) -> [KnownAbi; 3] {
+        if Some(address) == self.ownership {
+            [KnownAbi::Ownership, KnownAbi::AuthChain, KnownAbi::SignatureVerifier]
+        } else if address == self.signature_verifier {
+            [KnownAbi::SignatureVerifier, KnownAbi::AuthChain, KnownAbi::Ownership]
+        } else {
+            [KnownAbi::AuthChain, KnownAbi::Ownership, KnownAbi::SignatureVerifier]
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum KnownAbi {
+    AuthChain,
+    Ownership,
+    SignatureVerifier,
+}
+
+impl KnownAbi {
+    fn name(self) -> &'static str {
+        match self {
+            KnownAbi::AuthChain => "AuthChain",
+            KnownAbi::Ownership => "Ownership",
+            KnownAbi::SignatureVerifier => "SignatureVerifier",
+        }
+    }
+
+    fn address(self, contracts: &Contracts) -> Option<Address> {
+        match self {
+            KnownAbi::AuthChain => Some(contracts.auth_chain),
+            KnownAbi::Ownership => contracts.ownership,
+            KnownAbi::SignatureVerifier => Some(contracts.signature_verifier),
+        }
+    }
+}
+
+pub fn report(contracts: &Contracts, tx: Transaction, receipt: Option<TransactionReceipt>) -> TxReport {
+    let status = match &receipt {
+        None => TxStatus::Pending,
+        Some(receipt) if receipt.status == Some(1.into()) => TxStatus::Success,
+        Some(_) => TxStatus::Reverted,
+    };
+    let logs = receipt
+        .as_ref()
+        .map(|receipt| receipt.logs.iter().map(|log| decode_log(contracts, log)).collect())
+        .unwrap_or_default();
+
+    TxReport {
+        tx_hash: format!("{:?}", tx.hash),
+        chain_id: contracts.chain_id,
+        status,
+        block_number: receipt
+            .as_ref()
+            .and_then(|receipt| receipt.block_number)
+            .or(tx.block_number)
+            .map(|block| block.as_u64()),
+        from: format!("{:?}", tx.from),
+        to: tx.to.map(|to| format!("{:?}", to)),
+        value: tx.value.to_string(),
+        gas_used: receipt.and_then(|receipt| receipt.gas_used).map(|gas| gas.to_string()),
+        call: tx.to.and_then(|to| decode_call(contracts, to, &tx.input)),
+        logs,
+    }
+}
+
+fn decode_call(contracts: &Contracts, to: Address, input: &Bytes) -> Option<DecodedCall> {
+    contracts.abis_for(to).into_iter().find_map(|abi| {
+        let (function, arguments, signer) = match abi {
+            KnownAbi::AuthChain => auth_chain_call(contracts.chain_id, to, input)?,
+            KnownAbi::Ownership => ownership_call(input)?,
+            KnownAbi::SignatureVerifier => signature_verifier_call(contracts.chain_id, to, input)?,
+        };
+        Some(DecodedCall {
+            contract: abi.name().to_string(),
+            known_contract: abi.address(contracts) == Some(to),
+            function,
+            arguments,
+            signer: signer.map(|signer| format!("{:?}", signer)),
+        })
+    })
+}
+
+type CallParts = (String, Value, Option<Address>);
+
+// Certificate signatures are recovered under the domain of the called deployment,
+// the one the contract itself verifies them with
+fn auth_chain_call(chain_id: u64, to: Address, input: &Bytes) -> Option<CallParts> {
+    let call = AuthChainCalls::decode(input).ok()?;
+    let domain = auth_chain_domain(chain_id, to);
+    let certificate_signer = |certificate: &auth_chain::Certificate, signature: &Bytes| {
+        let digest = Certificate::from_contract(certificate.clone(), domain.clone())
+            .encode_eip712()
+            .ok()?;
+        recover(signature, digest)
+    };
+
+    let (arguments, signer) = match &call {
+        AuthChainCalls::CreateItem(c) => (
+            json!({ "certificate": certificate_json(&c.certificate), "signature": c.signature }),
+            certificate_signer(&c.certificate, &c.signature),
+        ),
+        AuthChainCalls::ReissueItem(c) => (
+            json!({ "certificate": certificate_json(&c.certificate), "signature": c.signature, "reason": c.reason }),
+            certificate_signer(&c.certificate, &c.signature),
+        ),
+        AuthChainCalls::VerifyAssetSignature(c) => (
+            json!({ "certificate": certificate_json(&c.certificate), "signature": c.signature }),
+            certificate_signer(&c.certificate, &c.signature),
+        ),
+        AuthChainCalls::RevokeItem(c) => (
+            json!({
+                "unique_id": c.unique_id,
+                "reason": RevocationReason::try_from(c.reason).map_or(json!(c.reason), |reason| json!(reason)),
+            }),
+            None,
+        ),
+        AuthChainCalls::AnchorBatch(c) => (
+            json!({ "root": H256::from(c.root), "count": c.count.to_string() }),
+            None,
+        ),
+        AuthChainCalls::TransferItem(c) => (
+            json!({
+                "unique_id": c.unique_id,
+                "to": format!("{:?}", c.to),
+                "deadline": c.deadline.to_string(),
+                "signature": c.signature,
+            }),
+            None,
+        ),
+        other => (json!(other.to_string()), None), // views, nothing anchored
+    };
+    Some((function_name(&AUTHCHAIN_ABI, input), arguments, signer))
+}
+
+fn ownership_call(input: &Bytes) -> Option<CallParts> {
+    let call = OwnershipCalls::decode(input).ok()?;
+    let arguments = match &call {
+        OwnershipCalls::RegisterAsset(c) => json!({ "asset_id": c.asset_id }),
+        OwnershipCalls::TransferAsset(c) => json!({ "asset_id": c.asset_id, "to": format!("{:?}", c.to) }),
+        other => json!(other.to_string()),
+    };
+    Some((function_name(&OWNERSHIP_ABI, input), arguments, None))
+}
+
+fn signature_verifier_call(chain_id: u64, to: Address, input: &Bytes) -> Option<CallParts> {
+    let call = SignatureVerifierCalls::decode(input).ok()?;
+    let (arguments, signer) = match &call {
+        SignatureVerifierCalls::VerifyAssetSignature(c) => {
+            let asset: &signature_verifier::Asset = &c.asset;
+            let signer = Asset::from_contract(asset.clone(), signature_verifier_domain(chain_id, to))
+                .encode_eip712()
+                .ok()
+                .and_then(|digest| recover(&c.signature, digest));
+            (
+                json!({
+                    "asset": { "name": asset.name, "serial": asset.serial, "owner": format!("{:?}", asset.owner) },
+                    "signature": c.signature,
+                    "expected_signer": format!("{:?}", c.expected_signer),
+                }),
+                signer,
+            )
+        }
+        other => (json!(other.to_string()), None),
+    };
+    Some((function_name(&SIGNATUREVERIFIER_ABI, input), arguments, signer))
+}
+
+fn function_name(abi: &Abi, input: &[u8]) -> String {
+    abi.functions()
+        .find(|function| input.starts_with(&function.short_signature()))
+        .map(|function| function.name.clone())
+        .unwrap_or_default()
+}
+
+fn recover(signature: &Bytes, digest: [u8; 32]) -> Option<Address> {
+    Signature::try_from(signature.as_ref()).ok()?.recover(digest).ok()
+}
+
+fn certificate_json(certificate: &auth_chain::Certificate) -> Value {
+    json!({
+        "name": certificate.name,
+        "unique_id": certificate.unique_id,
+        "serial": certificate.serial,
+        "date": certificate.date.to_string(),
+        "owner": format!("{:?}", certificate.owner),
+        "metadata": certificate.metadata,
+    })
+}
+
+fn decode_log(contracts: &Contracts, log: &Log) -> DecodedLog {
+    let raw_log = RawLog {
+        topics: log.topics.clone(),
+        data: log.data.to_vec(),
+    };
+    let decoded = contracts.abis_for(log.address).into_iter().find_map(|abi| {
+        let (event, fields) = match abi {
+            KnownAbi::AuthChain => auth_chain_event(AuthChainEvents::decode_log(&raw_log).ok()?),
+            KnownAbi::Ownership => ownership_event(OwnershipEvents::decode_log(&raw_log).ok()?),
+            // its only event, EIP712DomainChanged, is decoded with the AuthChain ABI
+            KnownAbi::SignatureVerifier => return None,
+        };
+        Some((abi, event, fields))
+    });
+
+    let (contract, known_contract, event, fields) = match decoded {
+        Some((abi, event, fields)) => (
+            Some(abi.name().to_string()),
+            abi.address(contracts) == Some(log.address),
+            Some(event.to_string()),
+            fields,
+        ),
+        None => (
+            None,
+            false,
+            None,
+            json!({ "topics": log.topics, "data": log.data }),
+        ),
+    };
+
+    DecodedLog {
+        log_index: log.log_index.map(|index| index.as_u64()),
+        address: format!("{:?}", log.address),
+        contract,
+        known_contract,
+        event,
+        fields,
+    }
+}
+
+fn auth_chain_event(event: AuthChainEvents) -> (&'static str, Value) {
+    match event {
+        AuthChainEvents::ItemCreatedFilter(e) => (
+            "ItemCreated",
+            json!({ "name": e.name, "unique_id": H256::from(e.unique_id), "owner": format!("{:?}", e.owner) }),
+        ),
+        AuthChainEvents::DebugHashFilter(e) => ("DebugHash", json!(SignatureCheck::from(e))),
+        AuthChainEvents::ItemReissuedFilter(e) => (
+            "ItemReissued",
+            json!({
+                "unique_id": H256::from(e.unique_id),
+                "previous_unique_id": H256::from(e.previous_unique_id),
+                "owner": format!("{:?}", e.owner),
+                "reason": e.reason,
+            }),
+        ),
+        AuthChainEvents::ItemTransferredFilter(e) => (
+            "ItemTransferred",
+            json!({
+                "unique_id": H256::from(e.unique_id),
+                "from": format!("{:?}", e.from),
+                "to": format!("{:?}", e.to),
+            }),
+        ),
+        AuthChainEvents::RevokedItemFilter(e) => (
+            "RevokedItem",
+            json!({
+                "unique_id": H256::from(e.unique_id),
+                "reason": RevocationReason::try_from(e.reason).map_or(json!(e.reason), |reason| json!(reason)),
+            }),
+        ),
+        AuthChainEvents::BatchAnchoredFilter(e) => (
+            "BatchAnchored",
+            json!({ "root": H256::from(e.root), "count": e.count.to_string() }),
+        ),
+        AuthChainEvents::Eip712DomainChangedFilter(_) => ("EIP712DomainChanged", json!({})),
+    }
+}
+
+fn ownership_event(event: OwnershipEvents) -> (&'static str, Value) {
+    match event {
+        OwnershipEvents::OwnerFilter(e) => ("Owner", json!({ "owner": format!("{:?}", e.owner) })),
+        OwnershipEvents::OwnershipTransferredFilter(e) => (
+            "OwnershipTransferred",
+            json!({
+                "asset_id": e.asset_id,
+                "from": format!("{:?}", e.from),
+                "to": format!("{:?}", e.to),
+            }),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate::CreateItemCall;
+    use ethers::abi::{AbiEncode, Token};
+    use ethers::contract::EthEvent;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::U256;
+
+    fn contracts() -> Contracts {
+        Contracts {
+            chain_id: 31337,
+            auth_chain: Address::random(),
+            signature_verifier: Address::random(),
+            ownership: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn decodes_create_item_and_recovers_the_signer() {
+        let contracts = contracts();
+        let issuer = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let certificate = Certificate {
+            name: "Redmi Note 14".to_string(),
+            unique_id: "XM-0001".to_string(),
+            serial: "XM12345".to_string(),
+            date: U256::from(1746057600u64),
+            owner: Address::random(),
+            metadata: vec!["color:black".to_string()],
+            domain: auth_chain_domain(contracts.chain_id, contracts.auth_chain),
+        };
+        let signature = issuer.sign_typed_data(&certificate).await.unwrap();
+        let call = CreateItemCall {
+            certificate: certificate.clone().into(),
+            signature: signature.to_vec().into(),
+        };
+        let tx = Transaction {
+            to: Some(contracts.auth_chain),
+            input: call.encode().into(),
+            ..Default::default()
+        };
+
+        let report = report(&contracts, tx, None);
+        assert_eq!(report.status, TxStatus::Pending);
+        let call = report.call.unwrap();
+        assert_eq!(call.contract, "AuthChain");
+        assert!(call.known_contract);
+        assert_eq!(call.function, "createItem");
+        assert_eq!(call.arguments["certificate"]["unique_id"], "XM-0001");
+        assert_eq!(call.arguments["certificate"]["date"], "1746057600");
+        assert_eq!(call.signer, Some(format!("{:?}", issuer.address())));
+    }
+
+    #[test]
+    fn decodes_logs_by_topic() {
+        let contracts = contracts();
+        let owner = Address::random();
+        let created = Log {
+            address: contracts.auth_chain,
+            topics: vec![
+                auth_chain::ItemCreatedFilter::signature(),
+                H256::repeat_byte(7),
+                H256::from(owner),
+            ],
+            data: ethers::abi::encode(&[Token::String("Redmi Note 14".to_string())]).into(),
+            ..Default::default()
+        };
+        let unknown = Log {
+            address: Address::random(),
+            topics: vec![H256::random()],
+            ..Default::default()
+        };
+
+        let created = decode_log(&contracts, &created);
+        assert_eq!(created.event.as_deref(), Some("ItemCreated"));
+        assert!(created.known_contract);
+        assert_eq!(created.fields["name"], "Redmi Note 14");
+        assert_eq!(created.fields["owner"], format!("{:?}", owner));
+
+        let unknown = decode_log(&contracts, &unknown);
+        assert_eq!(unknown.event, None);
+        assert_eq!(unknown.fields["topics"].as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::chain_registry::ChainRegistry;
+use crate::models::tx_model::{TrackedTx, TxOutcome};
+use crate::rpc_client::wait_for_receipt;
+use crate::utility::unix_now;
+use ethers::providers::Middleware;
+use ethers::types::H256;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+// A broadcast transaction whose receipt has not been seen yet
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PendingTx {
+    pub chain_id: u64,
+    pub tx_hash: H256,
+    pub action: String,  // e.g. "create_item"
+    pub subject: String, // unique id or asset id the transaction is about
+    pub sent_at: u64,    // unix seconds
+}
+
+impl PendingTx {
+    pub fn new(chain_id: u64, tx_hash: H256, action: &str, subject: &str) -> Self {
+        Self {
+            chain_id,
+            tx_hash,
+            action: action.to_string(),
+            subject: subject.to_string(),
+            sent_at: unix_now(),
+        }
+    }
+}
+
+impl From<&PendingTx> for TrackedTx {
+    fn from(tx: &PendingTx) -> Self {
+        Self {
+            tx_hash: format!("{:?}", tx.tx_hash),
+            action: tx.action.clone(),
+            subject: tx.subject.clone(),
+            sent_at: tx.sent_at,
+            outcome: None,
+            finished_at: None,
+        }
+    }
+}
+
+// A transaction picked up from an earlier run, with what became of it
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ResumedTx {
+    #[serde(flatten)]
+    pub tx: PendingTx,
+    pub outcome: TxOutcome,
+    pub finished_at: u64, // unix seconds
+}
+
+impl From<&ResumedTx> for TrackedTx {
+    fn from(resumed: &ResumedTx) -> Self {
+        Self {
+            outcome: Some(resumed.outcome),
+            finished_at: Some(resumed.finished_at),
+            ..TrackedTx::from(&resumed.tx)
+        }
+    }
+}
+
+// Transactions sent by the server and not yet mined, so a shutdown can wait for them
+// and persist the ones still pending for the next start to pick up.
+// Once draining, the server refuses requests that would send new ones.
+// The outcomes of resumed transactions are kept, in a file when there is one.
+#[derive(Default)]
+pub struct TxTracker {
+    pending: Mutex<HashMap<H256, PendingTx>>,
+    changed: Notify,
+    draining: AtomicBool,
+    resumed: Mutex<Vec<ResumedTx>>,
+    resumed_file: Option<PathBuf>,
+    pending_file: Mutex<Option<PathBuf>>, // set once transactions were resumed from it
+}
+
+impl TxTracker {
+    pub fn open(resumed_file: PathBuf) -> anyhow::Result<Self> {
+        let resumed = if resumed_file.exists() {
+            serde_json::from_str(&fs::read_to_string(&resumed_file)?)
+                .map_err(|e| anyhow::anyhow!("Invalid {}: {}", resumed_file.display(), e))?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            resumed: Mutex::new(resumed),
+            resumed_file: Some(resumed_file),
+            ..Self::default()
+        })
+    }
+
+    // Outcomes of the transactions resumed from earlier runs, oldest first
+    pub fn resumed(&self) -> Vec<ResumedTx> {
+        self.resumed.lock().unwrap().clone()
+    }
+
+    fn record(&self, tx: PendingTx, outcome: TxOutcome) {
+        let mut resumed = self.resumed.lock().unwrap();
+        resumed.push(ResumedTx {
+            tx,
+            outcome,
+            finished_at: unix_now(),
+        });
+
+        let Some(file) = &self.resumed_file else { return };
+        let written = serde_json::to_string_pretty(&*resumed)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| fs::write(file, json).map_err(anyhow::Error::from));
+        if let Err(e) = written {
+            error!(error = %e, file = %file.display(), "Resumed transaction outcomes not persisted");
+        }
+    }
+
+    pub fn track(&self, tx: PendingTx) {
+        self.pending.lock().unwrap().insert(tx.tx_hash, tx);
+    }
+
+    // The receipt is in, or the node dropped the transaction
+    pub fn finish(&self, tx_hash: H256) {
+        self.pending.lock().unwrap().remove(&tx_hash);
+        self.changed.notify_waiters();
+
+        // the file resumed from lists what is still pending, a crash before the next
+        // shutdown loses nothing
+        if let Some(file) = self.pending_file.lock().unwrap().as_ref() {
+            if let Err(e) = self.persist(file) {
+                error!(error = %e, file = %file.display(), "Pending transactions not persisted");
+            }
+        }
+    }
+
+    pub fn pending(&self) -> Vec<PendingTx> {
+        self.pending.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    // Wait until no transaction is pending, at most `timeout`; true when none is left
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            let changed = self.changed.notified();
+            if self.pending.lock().unwrap().is_empty() {
+                return true;
+            }
+            if tokio::time::timeout_at(deadline, changed).await.is_err() {
+                return self.pending.lock().unwrap().is_empty();
+            }
+        }
+    }
+
+    // Write the pending transactions to `path`, removing the file when there are none
+    pub fn persist(&self, path: &Path) -> anyhow::Result<usize> {
+        let mut pending = self.pending();
+        pending.sort_by_key(|tx| tx.sent_at);
+
+        if pending.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            return Ok(0);
+        }
+
+        fs::write(path, serde_json::to_string_pretty(&pending)?)?;
+        Ok(pending.len())
+    }
+
+    // Pick up the transactions a previous run persisted and wait for their receipts.
+    // The file is kept, and rewritten as they finish
+    pub fn resume(self: &Arc<Self>, path: &Path, chains: &ChainRegistry) -> anyhow::Result<usize> {
+        if !path.exists() {
+            return Ok(0);
+        }
+
+        let persisted: Vec<PendingTx> = serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e))?;
+        *self.pending_file.lock().unwrap() = Some(path.to_path_buf());
+
+        let mut resumed = 0;
+        for tx in persisted {
+            let Some(chain) = chains.get(tx.chain_id) else {
+                warn!(chain_id = tx.chain_id, tx_hash = ?tx.tx_hash, "Pending transaction on a chain that is no longer configured");
+                continue;
+            };
+
+            self.track(tx.clone());
+            resumed += 1;
+
+            let tracker = self.clone();
+            tokio::spawn(async move {
+                let outcome = match wait_for_receipt(chain.eth_client.provider(), tx.tx_hash).await {
+                    Ok(Some(receipt)) if receipt.status == Some(1.into()) => {
+                        info!(action = %tx.action, subject = %tx.subject, tx_hash = ?tx.tx_hash, "Pending transaction confirmed");
+                        TxOutcome::Confirmed
+                    }
+                    Ok(Some(_)) => {
+                        warn!(action = %tx.action, subject = %tx.subject, tx_hash = ?tx.tx_hash, "Pending transaction reverted");
+                        TxOutcome::Reverted
+                    }
+                    Ok(None) => {
+                        warn!(action = %tx.action, subject = %tx.subject, tx_hash = ?tx.tx_hash, "Pending transaction dropped");
+                        TxOutcome::Dropped
+                    }
+                    Err(e) => {
+                        // still pending, a later shutdown persists it again
+                        error!(tx_hash = ?tx.tx_hash, error = %e, "Pending transaction receipt unavailable");
+                        return;
+                    }
+                };
+                let tx_hash = tx.tx_hash;
+                tracker.record(tx, outcome);
+                tracker.finish(tx_hash);
+            });
+        }
+
+        Ok(resumed)
+    }
+}
+
+// Where pending transactions are kept between runs
+pub fn pending_tx_file() -> PathBuf {
+    PathBuf::from(
+        std::env::var("PENDING_TX_FILE").unwrap_or_else(|_| "pending_transactions.json".to_string()),
+    )
+}
+
+// Where the outcomes of resumed transactions are kept
+pub fn resumed_tx_file() -> PathBuf {
+    PathBuf::from(
+        std::env::var("RESUMED_TX_FILE").unwrap_or_else(|_| "resumed_transactions.json".to_string()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn drains_and_persists_what_is_left() {
+        let tracker = Arc::new(TxTracker::default());
+        let done = PendingTx::new(31337, H256::random(), "create_item", "XM-0001");
+        let stuck = PendingTx::new(31337, H256::random(), "revoke_item", "XM-0002");
+        tracker.track(done.clone());
+        tracker.track(stuck.clone());
+
+        let finisher = tracker.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            finisher.finish(done.tx_hash);
+        });
+
+        assert!(!tracker.drain(Duration::from_millis(200)).await);
+        assert_eq!(tracker.pending(), vec![stuck.clone()]);
+
+        let file = std::env::temp_dir().join(format!("pending-tx-test-{}.json", std::process::id()));
+        assert_eq!(tracker.persist(&file).unwrap(), 1);
+        let persisted: Vec<PendingTx> = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+        assert_eq!(persisted, vec![stuck.clone()]);
+
+        tracker.finish(stuck.tx_hash);
+        assert!(tracker.drain(Duration::from_millis(10)).await);
+        assert_eq!(tracker.persist(&file).unwrap(), 0);
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn resumed_outcomes_are_kept() {
+        let file = std::env::temp_dir().join(format!("resumed-tx-test-{}.json", std::process::id()));
+        let _ = fs::remove_file(&file);
+        let dropped = PendingTx::new(31337, H256::random(), "create_item", "XM-0001");
+
+        let tracker = TxTracker::open(file.clone()).unwrap();
+        tracker.record(dropped.clone(), TxOutcome::Dropped);
+
+        let restarted = TxTracker::open(file.clone()).unwrap();
+        let resumed = restarted.resumed();
+        assert_eq!(resumed.len(), 1);
+        assert_eq!(resumed[0].tx, dropped);
+        assert_eq!(resumed[0].outcome, TxOutcome::Dropped);
+        fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+use crate::chain_registry::Chain;
+use crate::models::typed_data_model::{check_chain_id, SignedTypedData, TypedDataVerification, VerifyTypedDataDTO};
+use crate::utility::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use tracing::{error, instrument, warn};
+use ethers::{
+    prelude::*,
+    types::transaction::eip712::{Eip712, TypedData},
+    types::Signature,
+};
+
+// Handler for POST /v1/typed-data/signatures
+#[utoipa::path(
+    post,
+    path = "/v1/typed-data/signatures",
+    request_body(content = Object, description = "Typed data in eth_signTypedData_v4 format"),
+    responses(
+        (status = 200, description = "Typed data signed by the issuer", body = SignedTypedData),
+        (status = 400, description = "Typed data cannot be encoded, or its chainId is out of range"),
+        (status = 403, description = "Domain or primary type is not allow-listed"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, primary_type = %typed_data.primary_type))]
+pub async fn sign_typed_data(
+    State(state): State<AppState>,
+    Chain(chain): Chain,
+    Json(typed_data): Json<TypedData>,
+) -> Result<Json<SignedTypedData>, StatusCode> {
+    check_chain_id(&typed_data).map_err(|reason| {
+        warn!(%reason, "Typed data rejected");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    state
+        .typed_data_policy
+        .check(&typed_data, chain.chain_id)
+        .map_err(|reason| {
+            warn!(%reason, "Typed data rejected");
+            StatusCode::FORBIDDEN
+        })?;
+
+    let digest = typed_data.encode_eip712().map_err(|e| {
+        warn!(error = ?e, "Typed data cannot be encoded");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let signature: Signature = chain
+        .eth_client
+        .signer()
+        .sign_typed_data(&typed_data)
+        .await
+        .map_err(|e| {
+            error!(error = ?e, "Typed data signing failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(SignedTypedData {
+        signature: format!("0x{}", signature),
+        digest: format!("{:?}", H256::from(digest)),
+        signer: format!("{:?}", state.wallet_address),
+    }))
+}
+
+// Handler for POST /v1/typed-data/verifications
+#[utoipa::path(
+    post,
+    path = "/v1/typed-data/verifications",
+    request_body = VerifyTypedDataDTO,
+    responses(
+        (status = 200, description = "Signature verification result", body = TypedDataVerification),
+        (status = 400, description = "Invalid signature, signer or typed data, or a chainId out of range")
+    )
+)]
+pub async fn verify_typed_data(
+    State(state): State<AppState>,
+    Json(dto): Json<VerifyTypedDataDTO>,
+) -> Result<Json<TypedDataVerification>, StatusCode> {
+    check_chain_id(&dto.typed_data).map_err(|reason| {
+        warn!(%reason, "Typed data rejected");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let signature: Signature = dto.signature.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let expected: Address = match dto.signer {
+        Some(signer) => signer.parse().map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => state.wallet_address,
+    };
+
+    let digest = dto.typed_data.encode_eip712().map_err(|e| {
+        warn!(error = ?e, "Typed data cannot be encoded");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let recovered = signature.recover(digest).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    Ok(Json(TypedDataVerification {
+        valid: recovered == expected,
+        recovered: format!("{:?}", recovered),
+        expected: format!("{:?}", expected),
+        digest: format!("{:?}", H256::from(digest)),
+    }))
+}
@@ -1,18 +1,42 @@
 use std::sync::Arc;
-use ethabi::ethereum_types::Address;
+use std::time::{SystemTime, UNIX_EPOCH};
+use ethabi::ethereum_types::{Address, U256};
 use ethers::middleware::SignerMiddleware;
-use ethers::prelude::{Bytes, Http, LocalWallet, Provider, Signature};
+use ethers::prelude::{Bytes, LocalWallet, Provider, Signature};
+use crate::batch_store::BatchStore;
+use crate::certificate_queue::CertificateQueue;
+use crate::chain_registry::ChainRegistry;
+use crate::idempotency::IdempotencyStore;
+use crate::models::typed_data_model::TypedDataPolicy;
+use crate::rpc_client::RpcClient;
+use crate::tx_tracker::TxTracker;
+use sqlx::PgPool;
+
+pub type EthClient = SignerMiddleware<Provider<RpcClient>, LocalWallet>;
 
 // Convert Signature to Bytes
 pub fn to_bytes(signature: Signature) -> Bytes {
     Bytes::from(signature.to_vec())
 }
 
+// Current time in unix seconds, as the stores record it
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
 // App state to hold the project state
 #[derive(Clone)]
 pub struct AppState {
-    pub eth_client: Arc<SignerMiddleware<Provider<Http>, LocalWallet>>,
-    pub signature_verifier: Address,
-    pub auth_chain: Address,
+    pub chains: Arc<ChainRegistry>,
     pub wallet_address: Address,
+    pub typed_data_policy: Arc<TypedDataPolicy>,
+    pub database: Option<PgPool>,
+    pub min_signer_balance: U256, // readiness fails below it, per signer and chain
+    pub tx_tracker: Arc<TxTracker>,
+    pub idempotency: Arc<IdempotencyStore>, // results of certificate creations, replayed to retries
+    pub batches: Arc<BatchStore>,           // proofs of the certificates anchored in batches
+    pub certificate_queue: Arc<CertificateQueue>, // signed certificates waiting for the batch worker
 }
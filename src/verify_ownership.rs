@@ -1,23 +1,24 @@
-use utoipa::{OpenApi, ToSchema};
-
+use crate::chain_gateway::{ApiError, Write};
+use crate::chain_registry::Chain;
+use crate::models::sig_model::{AssetPath, OwnerAddress, RegisteredAsset};
+use crate::models::simulation_model::{DryRunQuery, WriteResult};
+use crate::utility::AppState;
 use anyhow::Result;
+use axum::{
+    extract::{Path as AxumPath, Query, State},
+    http::StatusCode,
+    Json,
+};
 use dotenv::dotenv;
-use ethers::core::types::transaction::eip712::Eip712;
 use ethers::prelude::*;
 use ethers::{
-    abi::AbiDecode
-    ,
     middleware::SignerMiddleware,
     providers::{Http, Provider},
     signers::{LocalWallet, Signer},
     types::Address,
 };
-use serde::{Deserialize, Serialize};
-use sha3::Digest;
 use std::{env, sync::Arc, time::Duration};
-use axum::Json;
-use tiny_keccak::Hasher;
-use crate::models::sig_model::AssetDto;
+use tracing::{error, info, instrument};
 
 // Generate contract bindings from ABI
 abigen!(
@@ -41,7 +42,7 @@ pub async fn ownership() -> Result<()> {
     // Fetch chain ID from provider
     let chain_id = provider.get_chainid().await?.as_u64();
 
-    println!("Using chain ID: {}", chain_id);
+    info!(chain_id, "Using chain");
 
     // Initialize wallet with chain ID
     let wallet = private_key
@@ -65,17 +66,79 @@ pub async fn ownership() -> Result<()> {
     // contract.register_asset(item_id.to_string()).send().await
     //     .expect("Error!");
 
-    println!("Register Asset called");
+    info!("Register Asset called");
 
     match contract.get_owner(item_id.to_string()).call().await { // send().wait for update
-        Ok(owner) => println!("Owner: {:?}", owner),
-        Err(e) => eprintln!("Contract call failed: ",),
+        Ok(owner) => info!(?owner, "Owner"),
+        Err(e) => error!(error = ?e, "Contract call failed"),
     }
 
     match contract.verify_ownership(item_id.to_string()).call().await { // send().wait for update
-        Ok(result) => println!("Verification result: {:?}", result),
-        Err(e) => eprintln!("Contract call failed: ",),
+        Ok(result) => info!(result, "Verification result"),
+        Err(e) => error!(error = ?e, "Contract call failed"),
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+// Handler for POST /v1/ownership/{asset_id}
+#[utoipa::path(
+    post,
+    path = "/v1/ownership/{asset_id}",
+    params(
+        ("asset_id" = String, Path, description = "Asset ID to register to the backend wallet"),
+        DryRunQuery
+    ),
+    responses(
+        (status = 200, description = "Asset registered, a Simulation with ?dry_run=true", body = RegisteredAsset),
+        (status = 409, description = "Asset already registered"),
+        (status = 503, description = "Ownership contract not configured"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[instrument(skip_all, fields(chain_id = chain.chain_id, asset_id = %asset_id))]
+pub async fn register_asset(
+    State(state): State<AppState>,
+    Chain(chain): Chain,
+    AxumPath(AssetPath { asset_id }): AxumPath<AssetPath>,
+    Query(query): Query<DryRunQuery>,
+) -> Result<Json<WriteResult<RegisteredAsset>>, ApiError> {
+    if query.dry_run {
+        let write = Write::RegisterAsset { asset_id };
+        return Ok(Json(WriteResult::Simulated(chain.gateway.simulate(write).await?)));
+    }
+
+    let tx_hash = chain.gateway.register_asset(&asset_id).await?;
+
+    Ok(Json(WriteResult::Sent(RegisteredAsset {
+        asset_id,
+        owner: state.wallet_address,
+        tx_hash: format!("{:?}", tx_hash),
+    })))
+}
+
+// Handler for GET /v1/ownership/{asset_id}
+#[utoipa::path(
+    get,
+    path = "/v1/ownership/{asset_id}",
+    params(
+        ("asset_id" = String, Path, description = "Asset ID to look up")
+    ),
+    responses(
+        (status = 200, description = "Asset owner", body = OwnerAddress),
+        (status = 404, description = "Asset not registered"),
+        (status = 503, description = "Ownership contract not configured"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn asset_owner(
+    Chain(chain): Chain,
+    AxumPath(AssetPath { asset_id }): AxumPath<AssetPath>,
+) -> Result<Json<OwnerAddress>, StatusCode> {
+    let owner = chain
+        .gateway
+        .get_asset_owner(&asset_id)
+        .await
+        .map_err(StatusCode::from)?;
+
+    Ok(Json(OwnerAddress { owner }))
+}
@@ -0,0 +1,381 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestChain;
+use ethers::prelude::*;
+use ethers::types::transaction::eip712::TypedData;
+use rust_solidity_test::models::transfer_model::Transfer;
+use serde_json::{json, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn certificate(unique_id: &str, owner: Address) -> Value {
+    json!({
+        "name": "Redmi Note 14",
+        "unique_id": unique_id,
+        "serial": "XM12345",
+        "date": 1746057600u64,
+        "owner": format!("{:?}", owner),
+        "metadata": ["color:black", "storage:256GB"]
+    })
+}
+
+async fn create_item(chain: &TestChain, unique_id: &str, owner: Address) {
+    let (status, body) = chain.post("/v1/certificates", certificate(unique_id, owner)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    // decoded from the AuthChain logs of the receipt, never zero values
+    assert_eq!(body["unique_id"], unique_id);
+    assert_ne!(body["hash"].as_str().unwrap().parse::<H256>().unwrap(), H256::zero());
+    assert_eq!(body["owner"].as_str().unwrap().parse::<Address>().unwrap(), owner);
+    let check = &body["signature_check"];
+    assert_eq!(check["signer"].as_str().unwrap().parse::<Address>().unwrap(), chain.issuer.address());
+    assert_eq!(check["struct_hash"], body["hash"]);
+}
+
+#[tokio::test]
+#[ignore = "needs a dev chain, run with --ignored"]
+async fn status_and_owner() {
+    let chain = TestChain::start().await;
+
+    let (status, body) = chain.get("/v1/signatures/verifier").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!(format!("Contract at {:?}", chain.context().signature_verifier)));
+
+    let (status, body) = chain.get("/v1/issuers").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_str().unwrap().parse::<Address>().unwrap(), chain.issuer.address());
+}
+
+#[tokio::test]
+#[ignore = "needs a dev chain, run with --ignored"]
+async fn signature_and_verify() {
+    let chain = TestChain::start().await;
+
+    let asset = json!({
+        "name": "Redmi Note 14",
+        "serial": "XM12345",
+        "owner": format!("{:?}", chain.issuer.address())
+    });
+
+    let (status, body) = chain.post("/v1/signatures", asset.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(body.get("r").is_some());
+
+    // the asset is signed under the SignatureVerifier domain of the connected chain
+    let (status, body) = chain.post("/v1/signatures/verify", asset).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body, json!("Signature valid: true"));
+
+    let (status, _) = chain
+        .post("/v1/signatures/verify", json!({ "name": "x", "serial": "y", "owner": "not-an-address" }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[ignore = "needs a dev chain, run with --ignored"]
+async fn create_and_get_item() {
+    let chain = TestChain::start().await;
+
+    create_item(&chain, "XM-0001", chain.user.address()).await;
+
+    let (status, item) = chain.get("/v1/items/XM-0001").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(item["name"], "Redmi Note 14");
+    assert_eq!(item["serial"], "XM12345");
+    assert_eq!(item["date"], "1746057600");
+    assert_eq!(item["owner"].as_str().unwrap().parse::<Address>().unwrap(), chain.user.address());
+    assert_eq!(item["revoked"], false);
+    assert_eq!(item["revocation_reason"], Value::Null);
+
+    let (status, _) = chain
+        .post("/v1/certificates", json!({ "name": "x", "unique_id": "y", "serial": "z", "date": 0, "owner": "nope", "metadata": [] }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[ignore = "needs a dev chain, run with --ignored"]
+async fn revoke_item() {
+    let chain = TestChain::start().await;
+
+    create_item(&chain, "XM-0002", chain.user.address()).await;
+
+    let (status, body) = chain
+        .post("/v1/certificates/XM-0002/revocation", json!({ "reason": "counterfeit" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["reason"], "counterfeit");
+
+    let (_, item) = chain.get("/v1/items/XM-0002").await;
+    assert_eq!(item["revoked"], true);
+    assert_eq!(item["revocation_reason"], "counterfeit");
+
+    let (status, _) = chain
+        .post("/v1/certificates/XM-0002/revocation", json!({ "reason": "recalled" }))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = chain
+        .post("/v1/certificates/unknown/revocation", json!({ "reason": "recalled" }))
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore = "needs a dev chain, run with --ignored"]
+async fn duplicates_and_reissue() {
+    let chain = TestChain::start().await;
+
+    create_item(&chain, "XM-0006", chain.user.address()).await;
+
+    let mut other = certificate("XM-0006", chain.user.address());
+    other["serial"] = json!("XM54321");
+    let (status, _) = chain.post("/v1/certificates", other.clone()).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, reissued) = chain
+        .post("/v1/certificates/XM-0006/reissues", json!({ "certificate": other, "reason": "serial typo" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", reissued);
+    assert_ne!(reissued["hash"], reissued["previous_hash"]);
+
+    let (_, item) = chain.get("/v1/items/XM-0006").await;
+    assert_eq!(item["serial"], "XM54321");
+    assert_eq!(item["unique_id"], reissued["hash"]);
+
+    // the history follows the reissue back to the original certificate
+    let (_, history) = chain.get("/v1/items/XM-0006/history").await;
+    assert_eq!(history[0]["action"], "created");
+    assert_eq!(history[1]["action"], "reissued");
+    assert_eq!(history[1]["note"], "serial typo");
+
+    // the printed certificate is read back from the reissueItem calldata
+    let (status, pdf) = chain.get("/v1/certificates/XM-0006/document").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(pdf.as_str().unwrap().starts_with("%PDF-"));
+}
+
+#[tokio::test]
+#[ignore = "needs a dev chain, run with --ignored"]
+async fn batch_proofs_verify_on_chain() {
+    let chain = TestChain::start().await;
+
+    let ids = ["XM-0101", "XM-0102", "XM-0103"];
+    let batch = json!({ "certificates": ids.map(|id| certificate(id, chain.user.address())) });
+    let (status, anchored) = chain.post("/v1/certificates/batches", batch).await;
+    assert_eq!(status, StatusCode::OK, "{}", anchored);
+
+    // the proofs are built in Rust, AuthChain folds them to the root it stored
+    for id in ids {
+        let (status, proof) = chain.get(&format!("/v1/certificates/{}/proof", id)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, verification) = chain
+            .post(
+                "/v1/proofs/verifications",
+                json!({ "certificate": certificate(id, chain.user.address()), "proof": proof["proof"] }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(verification["included"], true, "{}", verification);
+        assert_eq!(verification["root"], anchored["root"]);
+    }
+
+    let (status, report) = chain.get(&format!("/v1/tx/{}", anchored["tx_hash"].as_str().unwrap())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["call"]["function"], "anchorBatch");
+    assert_eq!(report["logs"][0]["event"], "BatchAnchored");
+}
+
+#[tokio::test]
+#[ignore = "needs a dev chain, run with --ignored"]
+async fn decoded_transaction() {
+    let chain = TestChain::start().await;
+
+    let (status, created) = chain
+        .post("/v1/certificates", certificate("XM-0008", chain.user.address()))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", created);
+
+    let (status, report) = chain.get(&format!("/v1/tx/{}", created["tx_hash"].as_str().unwrap())).await;
+    assert_eq!(status, StatusCode::OK, "{}", report);
+    assert_eq!(report["status"], "success");
+    assert_eq!(report["call"]["contract"], "AuthChain");
+    assert_eq!(report["call"]["known_contract"], true);
+    assert_eq!(report["call"]["function"], "createItem");
+    assert_eq!(report["call"]["arguments"]["certificate"]["unique_id"], "XM-0008");
+    let signer = report["call"]["signer"].as_str().unwrap().parse::<Address>().unwrap();
+    assert_eq!(signer, chain.issuer.address());
+
+    let events: Vec<&str> = report["logs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|log| log["event"].as_str().unwrap())
+        .collect();
+    assert_eq!(events, ["DebugHash", "ItemCreated"]);
+    assert_eq!(report["logs"][1]["fields"]["unique_id"], created["hash"]);
+
+    let (status, _) = chain.get(&format!("/v1/tx/{:?}", H256::random())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore = "needs a dev chain, run with --ignored"]
+async fn dry_run() {
+    let chain = TestChain::start().await;
+
+    let (status, simulation) = chain
+        .post("/v1/certificates?dry_run=true", certificate("XM-0007", chain.user.address()))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", simulation);
+    assert!(simulation["gas_estimate"].as_u64().unwrap() > 21_000);
+    let (status, _) = chain.get("/v1/items/XM-0007").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // simulated against the contract, the revert is decoded
+    create_item(&chain, "XM-0007", chain.user.address()).await;
+    let mut other = certificate("XM-0007", chain.user.address());
+    other["serial"] = json!("XM54321");
+    let (status, body) = chain.post("/v1/certificates?dry_run=true", other).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["error"], "Item already exists");
+}
+
+#[tokio::test]
+#[ignore = "needs a dev chain, run with --ignored"]
+async fn transfer_and_history() {
+    let chain = TestChain::start().await;
+
+    create_item(&chain, "XM-0003", chain.user.address()).await;
+
+    let buyer = Address::random();
+    let deadline = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 3600;
+    let transfer = Transfer {
+        unique_id: "XM-0003".to_string(),
+        from: chain.user.address(),
+        to: buyer,
+        nonce: U256::zero(),
+        deadline: U256::from(deadline),
+        domain: chain.context().auth_chain_domain(),
+    };
+    let signature = chain.user.sign_typed_data(&transfer).await.unwrap();
+
+    let dto = json!({
+        "from": format!("{:?}", chain.user.address()),
+        "to": format!("{:?}", buyer),
+        "nonce": 0,
+        "deadline": deadline,
+        "signature": format!("0x{}", signature)
+    });
+
+    let (status, body) = chain.post("/v1/items/XM-0003/transfers", dto.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // replaying the same signature is rejected before it reaches the chain
+    let (status, _) = chain.post("/v1/items/XM-0003/transfers", dto).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (_, item) = chain.get("/v1/items/XM-0003").await;
+    assert_eq!(item["owner"].as_str().unwrap().parse::<Address>().unwrap(), buyer);
+
+    let (status, history) = chain.get("/v1/items/XM-0003/history").await;
+    assert_eq!(status, StatusCode::OK);
+    let actions: Vec<&str> = history
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["created", "transferred"]);
+
+    let (status, _) = chain.get("/v1/items/unknown/history").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore = "needs a dev chain, run with --ignored"]
+async fn relayer_status() {
+    let chain = TestChain::start().await;
+
+    let (status, body) = chain.get("/v1/relayer").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["address"].as_str().unwrap().parse::<Address>().unwrap(), chain.issuer.address());
+    assert_eq!(body["gas_spent"], "0.000000000000000000");
+}
+
+#[tokio::test]
+#[ignore = "needs a dev chain, run with --ignored"]
+async fn sign_and_verify_typed_data() {
+    let chain = TestChain::start().await;
+
+    let typed_data = json!({
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" }
+            ],
+            "Asset": [
+                { "name": "name", "type": "string" },
+                { "name": "serial", "type": "string" },
+                { "name": "owner", "type": "address" }
+            ]
+        },
+        "primaryType": "Asset",
+        "domain": {
+            "name": "AssetOwnership",
+            "version": "1",
+            "chainId": chain.context().chain_id,
+            "verifyingContract": format!("{:?}", chain.context().signature_verifier)
+        },
+        "message": {
+            "name": "Redmi Note 14",
+            "serial": "XM12345",
+            "owner": format!("{:?}", chain.user.address())
+        }
+    });
+
+    let (status, signed) = chain.post("/v1/typed-data/signatures", typed_data.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", signed);
+
+    let (status, body) = chain
+        .post("/v1/typed-data/verifications", json!({ "typedData": typed_data, "signature": signed["signature"] }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["valid"], true);
+
+    let mut other_chain: TypedData = serde_json::from_value(typed_data).unwrap();
+    other_chain.domain.chain_id = Some(U256::from(1));
+    let (status, _) = chain
+        .post("/v1/typed-data/signatures", serde_json::to_value(other_chain).unwrap())
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+#[ignore = "needs a dev chain, run with --ignored"]
+async fn ownership_register_and_transfer() {
+    let chain = TestChain::start().await;
+
+    let asset_id = "asset001".to_string();
+    let buyer = Address::random();
+
+    let (status, _) = chain.get("/v1/ownership/asset001").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = chain.post("/v1/ownership/asset001", json!(null)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = chain.get("/v1/ownership/asset001").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["owner"].as_str().unwrap().parse::<Address>().unwrap(), chain.issuer.address());
+
+    let (status, _) = chain.post("/v1/ownership/asset001", json!(null)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    assert!(chain.ownership.verify_ownership(asset_id.clone()).call().await.unwrap());
+
+    chain.ownership.transfer_asset(asset_id.clone(), buyer).send().await.unwrap().await.unwrap();
+    assert_eq!(chain.ownership.get_owner(asset_id).call().await.unwrap(), buyer);
+}
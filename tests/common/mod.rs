@@ -0,0 +1,236 @@
+// Integration test harness: a local dev chain with freshly deployed contracts and
+// the app router wired to it.
+//
+// The chain is, in order of preference:
+//   - `TEST_RPC_URL`, an already running node (e.g. `npx hardhat node`)
+//   - `anvil` from PATH
+//   - `npx hardhat node`, when `node_modules` is installed
+// The tests are #[ignore]d, `cargo test -- --ignored` runs them and fails when none is
+// available. Contracts are deployed from the bytecode abigen embeds from
+// `artifacts/contracts`, so run `npx hardhat compile` after editing a contract.
+
+#![allow(dead_code)]
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use ethers::prelude::*;
+use ethers::utils::{parse_ether, Anvil, AnvilInstance};
+use rust_solidity_test::app_router::paths;
+use rust_solidity_test::http_config::HttpConfig;
+use rust_solidity_test::batch_store::BatchStore;
+use rust_solidity_test::certificate_queue::CertificateQueue;
+use rust_solidity_test::idempotency::IdempotencyStore;
+use rust_solidity_test::certificate::AuthChain;
+use rust_solidity_test::chain_gateway::EthersGateway;
+use rust_solidity_test::chain_registry::{ChainContext, ChainRegistry};
+use rust_solidity_test::models::cert_model::RouterPath;
+use rust_solidity_test::models::typed_data_model::TypedDataPolicy;
+use rust_solidity_test::relayer::{GasLedger, Relayer};
+use rust_solidity_test::rpc_client::{RetryConfig, RpcClient};
+use rust_solidity_test::signature_verifier::SignatureVerifier;
+use rust_solidity_test::tx_tracker::TxTracker;
+use rust_solidity_test::utility::{AppState, EthClient};
+use rust_solidity_test::verify_ownership::Ownership;
+use serde_json::Value;
+use std::env;
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, MutexGuard};
+use tower::ServiceExt;
+
+// Default dev accounts #0 and #1 of both anvil and hardhat
+pub const ISSUER_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+pub const USER_KEY: &str = "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+// Tests in one binary share PRIVATE_KEY and, with TEST_RPC_URL, the node's account nonces,
+// so they run one at a time
+static CHAIN_LOCK: Mutex<()> = Mutex::const_new(());
+
+enum Node {
+    External,
+    Anvil(AnvilInstance),
+    Hardhat(Child),
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        if let Node::Hardhat(child) = self {
+            let _ = child.kill();
+        }
+    }
+}
+
+pub struct TestChain {
+    pub state: AppState,
+    pub issuer: LocalWallet,
+    pub user: LocalWallet,
+    pub ownership: Ownership<EthClient>,
+    _node: Node,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl TestChain {
+    // Start a node and deploy the contracts
+    pub async fn start() -> TestChain {
+        let guard = CHAIN_LOCK.lock().await;
+
+        let (node, rpc_url) = start_node()
+            .await
+            .expect("no dev chain: set TEST_RPC_URL, install anvil or run `npm install`");
+
+        let rpc_client = RpcClient::connect(&[rpc_url], RetryConfig::default(), 1).await.unwrap();
+        let provider = Provider::new(rpc_client).interval(Duration::from_millis(10));
+        let chain_id = provider.get_chainid().await.unwrap().as_u64();
+
+        let issuer = ISSUER_KEY.parse::<LocalWallet>().unwrap().with_chain_id(chain_id);
+        let user = USER_KEY.parse::<LocalWallet>().unwrap().with_chain_id(chain_id);
+        let eth_client = Arc::new(SignerMiddleware::new(provider, issuer.clone()));
+
+        let auth_chain = AuthChain::deploy(eth_client.clone(), issuer.address())
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        let signature_verifier = SignatureVerifier::deploy(eth_client.clone(), ())
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        let ownership = Ownership::deploy(eth_client.clone(), ())
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        env::set_var("PRIVATE_KEY", ISSUER_KEY);
+
+        let tx_tracker = Arc::new(TxTracker::default());
+
+        let context = ChainContext {
+            chain_id,
+            name: "dev".to_string(),
+            eth_client: eth_client.clone(),
+            gateway: Arc::new(EthersGateway::new(
+                eth_client.clone(),
+                auth_chain.address(),
+                0,
+                signature_verifier.address(),
+                Some(ownership.address()),
+                tx_tracker.clone(),
+            )),
+            signature_verifier: signature_verifier.address(),
+            auth_chain: auth_chain.address(),
+            ownership: Some(ownership.address()),
+            relayer: Arc::new(Relayer::new(
+                eth_client.clone(),
+                auth_chain.address(),
+                parse_ether("1").unwrap(),
+                Arc::new(GasLedger::default()),
+                tx_tracker.clone(),
+            )),
+        };
+
+        let state = AppState {
+            chains: Arc::new(ChainRegistry::new(chain_id, vec![context]).unwrap()),
+            wallet_address: issuer.address(),
+            typed_data_policy: Arc::new(
+                TypedDataPolicy::parse(&format!("AssetOwnership:1:Asset:{}:{:?}", chain_id, signature_verifier.address()))
+                    .unwrap(),
+            ),
+            database: None,
+            min_signer_balance: parse_ether("0.01").unwrap(),
+            tx_tracker,
+            idempotency: Arc::new(IdempotencyStore::default()),
+            batches: Arc::new(BatchStore::default()),
+            certificate_queue: Arc::new(CertificateQueue::default()),
+        };
+
+        TestChain {
+            state,
+            issuer,
+            user,
+            ownership,
+            _node: node,
+            _guard: guard,
+        }
+    }
+
+    // Client, contracts and EIP-712 domains of the dev chain
+    pub fn context(&self) -> Arc<ChainContext> {
+        self.state.chains.default_chain()
+    }
+
+    pub async fn request(&self, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        send(&self.state, method, uri, body).await
+    }
+
+    pub async fn get(&self, uri: &str) -> (StatusCode, Value) {
+        self.request("GET", uri, None).await
+    }
+
+    pub async fn post(&self, uri: &str, body: Value) -> (StatusCode, Value) {
+        self.request("POST", uri, Some(body)).await
+    }
+}
+
+// Send one request through a fresh router
+pub async fn send(state: &AppState, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder().method(method).uri(uri);
+    let request = match body {
+        Some(body) => request
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    let response = paths(state.clone(), RouterPath::init(), &HttpConfig::default())
+        .oneshot(request)
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let value = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).to_string()));
+
+    (status, value)
+}
+
+async fn start_node() -> Option<(Node, String)> {
+    if let Ok(url) = env::var("TEST_RPC_URL") {
+        return Some((Node::External, url));
+    }
+
+    if Command::new("anvil").arg("--version").output().is_ok() {
+        let anvil = Anvil::new().spawn();
+        let url = anvil.endpoint();
+        return Some((Node::Anvil(anvil), url));
+    }
+
+    if Path::new("node_modules/.bin/hardhat").exists() {
+        let port = TcpListener::bind("127.0.0.1:0").ok()?.local_addr().ok()?.port();
+        let child = Command::new("npx")
+            .args(["hardhat", "node", "--port", &port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let node = Node::Hardhat(child);
+        let url = format!("http://127.0.0.1:{}", port);
+
+        let provider = Provider::<Http>::try_from(url.as_str()).ok()?;
+        for _ in 0..60 {
+            if provider.get_chainid().await.is_ok() {
+                return Some((node, url));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        return None;
+    }
+
+    None
+}
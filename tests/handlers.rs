@@ -0,0 +1,851 @@
+// Handler tests against the in-memory gateway, no node required
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::{send, ISSUER_KEY};
+use ethers::prelude::*;
+use ethers::utils::parse_ether;
+use rust_solidity_test::app_router::paths;
+use rust_solidity_test::chain_gateway::GatewayError;
+use rust_solidity_test::http_config::HttpConfig;
+use rust_solidity_test::batch_store::BatchStore;
+use rust_solidity_test::certificate_queue::{anchor_due, BatchWorkerConfig, CertificateQueue};
+use rust_solidity_test::idempotency::IdempotencyStore;
+use rust_solidity_test::chain_registry::{ChainContext, ChainRegistry};
+use rust_solidity_test::memory_gateway::InMemoryGateway;
+use rust_solidity_test::models::cert_model::{Certificate, RouterPath};
+use rust_solidity_test::models::typed_data_model::TypedDataPolicy;
+use rust_solidity_test::relayer::{GasLedger, Relayer};
+use rust_solidity_test::rpc_client::{RetryConfig, RpcClient};
+use rust_solidity_test::tx_tracker::{PendingTx, TxTracker};
+use rust_solidity_test::utility::{unix_now, AppState};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+const CHAIN_ID: u64 = 31337;
+const OTHER_CHAIN_ID: u64 = 84532;
+const AUTH_CHAIN: &str = "0x5FbDB2315678afecb367f032d93F642f64180aa3";
+const SIGNATURE_VERIFIER: &str = "0xe7f1725E7734CE288F8367e1Bb143E90bb3F0512";
+
+struct Fake {
+    state: AppState,
+    gateway: Arc<InMemoryGateway>, // of the default chain
+}
+
+// A chain whose contract calls all go to an in-memory gateway
+async fn fake_chain(chain_id: u64) -> (ChainContext, Arc<InMemoryGateway>) {
+    // never contacted, every contract call goes through the gateway
+    let rpc_client = RpcClient::connect(&["http://127.0.0.1:1".to_string()], RetryConfig::default(), 1)
+        .await
+        .unwrap();
+    let provider = Provider::new(rpc_client);
+    let wallet = ISSUER_KEY.parse::<LocalWallet>().unwrap().with_chain_id(chain_id);
+    let eth_client = Arc::new(SignerMiddleware::new(provider, wallet.clone()));
+    let gateway = Arc::new(InMemoryGateway::new(chain_id, wallet.address(), wallet.address()));
+
+    let context = ChainContext {
+        chain_id,
+        name: format!("chain-{}", chain_id),
+        eth_client: eth_client.clone(),
+        gateway: gateway.clone(),
+        signature_verifier: SIGNATURE_VERIFIER.parse().unwrap(),
+        auth_chain: AUTH_CHAIN.parse().unwrap(),
+        ownership: None,
+        relayer: Arc::new(Relayer::new(
+            eth_client,
+            AUTH_CHAIN.parse().unwrap(),
+            parse_ether("1").unwrap(),
+            Arc::new(GasLedger::default()),
+            Arc::new(TxTracker::default()),
+        )),
+    };
+
+    (context, gateway)
+}
+
+impl Fake {
+    async fn new() -> Fake {
+        let (default_chain, gateway) = fake_chain(CHAIN_ID).await;
+        let (other_chain, _) = fake_chain(OTHER_CHAIN_ID).await;
+        let wallet_address = default_chain.eth_client.address();
+        let allow_list = format!("AssetOwnership:1:Asset:{}:{:?}", CHAIN_ID, default_chain.signature_verifier);
+
+        let state = AppState {
+            chains: Arc::new(ChainRegistry::new(CHAIN_ID, vec![default_chain, other_chain]).unwrap()),
+            wallet_address,
+            typed_data_policy: Arc::new(TypedDataPolicy::parse(&allow_list).unwrap()),
+            database: None,
+            min_signer_balance: parse_ether("0.01").unwrap(),
+            tx_tracker: Arc::new(TxTracker::default()),
+            idempotency: Arc::new(IdempotencyStore::default()),
+            batches: Arc::new(BatchStore::default()),
+            certificate_queue: Arc::new(CertificateQueue::default()),
+        };
+
+        Fake { state, gateway }
+    }
+
+    async fn get(&self, uri: &str) -> (StatusCode, Value) {
+        send(&self.state, "GET", uri, None).await
+    }
+
+    async fn post(&self, uri: &str, body: Value) -> (StatusCode, Value) {
+        send(&self.state, "POST", uri, Some(body)).await
+    }
+
+    async fn create_item(&self, unique_id: &str) -> Value {
+        self.create_item_at("", unique_id).await
+    }
+
+    // Certificate body of the write routes, owned by the server wallet
+    fn certificate(&self, unique_id: &str, serial: &str) -> Value {
+        json!({
+            "name": "Redmi Note 14",
+            "unique_id": unique_id,
+            "serial": serial,
+            "date": 1746057600u64,
+            "owner": format!("{:?}", self.state.wallet_address),
+            "metadata": ["color:black"]
+        })
+    }
+
+    // `scope` is "" for the default chain or "/chains/{chain_id}", under /v1
+    async fn create_item_at(&self, scope: &str, unique_id: &str) -> Value {
+        let certificate = self.certificate(unique_id, "XM12345");
+        let (status, body) = self.post(&format!("/v1{}/certificates", scope), certificate).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        body
+    }
+}
+
+#[tokio::test]
+async fn create_and_get_item() {
+    let fake = Fake::new().await;
+
+    let created = fake.create_item("XM-0001").await;
+    assert_eq!(created["unique_id"], "XM-0001");
+    assert!(created["tx_hash"].is_string());
+    let issuer = format!("{:?}", fake.state.wallet_address);
+    assert_eq!(created["signature_check"]["signer"], issuer.as_str());
+    assert_eq!(created["signature_check"]["struct_hash"], created["hash"]);
+
+    let (status, item) = fake.get("/v1/items/XM-0001").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(item["unique_id"], created["hash"]);
+    assert_eq!(item["name"], "Redmi Note 14");
+    assert_eq!(item["date"], "1746057600");
+    assert_eq!(item["revoked"], false);
+    assert_eq!(item["revocation_reason"], Value::Null);
+    assert_eq!(item["chain_id"], CHAIN_ID);
+
+    let (status, _) = fake.get("/v1/items/unknown").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn create_item_is_idempotent() {
+    let fake = Fake::new().await;
+    let create = |key: &str, unique_id: &str, serial: &str| {
+        let certificate = fake.certificate(unique_id, serial);
+        let request = Request::post("/v1/certificates")
+            .header("content-type", "application/json")
+            .header("idempotency-key", key)
+            .body(Body::from(certificate.to_string()))
+            .unwrap();
+        paths(fake.state.clone(), RouterPath::init(), &HttpConfig::default()).oneshot(request)
+    };
+
+    let first = create("retry-1", "XM-0005", "XM12345").await.unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    assert!(first.headers().get("idempotent-replayed").is_none());
+
+    let retry = create("retry-1", "XM-0005", "XM12345").await.unwrap();
+    assert_eq!(retry.status(), StatusCode::OK);
+    assert_eq!(retry.headers()["idempotent-replayed"], "true");
+
+    // natural idempotency on the unique id, without the header
+    let (status, _) = fake.post("/v1/certificates", fake.certificate("XM-0005", "XM12345")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, history) = fake.get("/v1/items/XM-0005/history").await;
+    assert_eq!(history.as_array().unwrap().len(), 1, "{}", history);
+
+    let reused = create("retry-1", "XM-0006", "XM12345").await.unwrap();
+    assert_eq!(reused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let conflict = create("retry-2", "XM-0005", "XM99999").await.unwrap();
+    assert_eq!(conflict.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn duplicates_are_refused_and_reissued() {
+    let fake = Fake::new().await;
+    let certificate = |serial: &str| fake.certificate("XM-0007", serial);
+
+    let (status, _) = fake.post("/v1/certificates", certificate("XM12345")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // after a restart the pre-flight lookup answers, without sending a transaction
+    let restarted = AppState { idempotency: Arc::new(IdempotencyStore::default()), ..fake.state.clone() };
+    let (status, _) = send(&restarted, "POST", "/v1/certificates", Some(certificate("XM12345"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&restarted, "POST", "/v1/certificates", Some(certificate("XM54321"))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, reissued) = fake
+        .post("/v1/certificates/XM-0007/reissues", json!({ "certificate": certificate("XM54321"), "reason": "serial typo" }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", reissued);
+    assert_ne!(reissued["hash"], reissued["previous_hash"]);
+
+    let (_, item) = fake.get("/v1/items/XM-0007").await;
+    assert_eq!(item["serial"], "XM54321");
+    assert_eq!(item["unique_id"], reissued["hash"]);
+
+    let (_, history) = fake.get("/v1/items/XM-0007/history").await;
+    assert_eq!(history.as_array().unwrap().len(), 2);
+    assert_eq!(history[1]["action"], "reissued");
+    assert_eq!(history[1]["note"], "serial typo");
+
+    let (status, _) = fake
+        .post("/v1/certificates/XM-0007/reissues", json!({ "certificate": certificate("XM54321"), "reason": "" }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = fake
+        .post("/v1/certificates/XM-0008/reissues", json!({ "certificate": certificate("XM54321"), "reason": "typo" }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST); // certificate of another item
+}
+
+#[tokio::test]
+async fn dry_runs_simulate_without_sending() {
+    let fake = Fake::new().await;
+    let certificate = fake.certificate("XM-0009", "XM12345");
+
+    let (status, simulation) = fake.post("/v1/certificates?dry_run=true", certificate.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", simulation);
+    assert_eq!(simulation["action"], "create_item");
+    assert_eq!(simulation["subject"], "XM-0009");
+    assert_eq!(simulation["gas_estimate"], 100_000);
+    assert_eq!(simulation["fee"], "0.000100000000000000");
+    let (status, _) = fake.get("/v1/items/XM-0009").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // a dry run does not claim the unique id either
+    let (status, _) = fake.post("/v1/certificates", certificate).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = fake
+        .post("/v1/certificates/XM-0009/revocation?dry_run=true", json!({ "reason": "stolen" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    fake.post("/v1/certificates/XM-0009/revocation", json!({ "reason": "stolen" })).await;
+
+    // the revert comes back decoded, nothing is sent
+    let (status, body) = fake
+        .post("/v1/certificates/XM-0009/revocation?dry_run=true", json!({ "reason": "stolen" }))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["error"], "Item already revoked");
+
+    let (status, simulation) = fake.post("/v1/ownership/asset002?dry_run=true", json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(simulation["action"], "register_asset");
+    let (status, _) = fake.get("/v1/ownership/asset002").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn batches_are_anchored_by_root() {
+    let fake = Fake::new().await;
+    let certificate = |unique_id: &str| fake.certificate(unique_id, "XM12345");
+    let ids = ["XM-0101", "XM-0102", "XM-0103"];
+    let batch = json!({ "certificates": ids.map(certificate) });
+
+    let (status, simulation) = fake.post("/v1/certificates/batches?dry_run=true", batch.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", simulation);
+    assert_eq!(simulation["action"], "anchor_batch");
+
+    let (status, anchored) = fake.post("/v1/certificates/batches", batch.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", anchored);
+    assert_eq!(anchored["count"], 3);
+
+    let (status, proof) = fake.get("/v1/certificates/XM-0102/proof").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(proof["root"], anchored["root"]);
+    assert_eq!(proof["struct_hash"], anchored["certificates"][1]["struct_hash"]);
+    let (status, _) = fake.get("/v1/certificates/XM-0104/proof").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, verification) = fake
+        .post("/v1/proofs/verifications", json!({ "certificate": certificate("XM-0102"), "proof": proof["proof"] }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(verification["included"], true);
+    assert_eq!(verification["root"], anchored["root"]);
+
+    // another serial is another certificate, its proof leads nowhere
+    let mut forged = certificate("XM-0102");
+    forged["serial"] = json!("XM54321");
+    let (_, verification) = fake
+        .post("/v1/proofs/verifications", json!({ "certificate": forged, "proof": proof["proof"] }))
+        .await;
+    assert_eq!(verification["included"], false);
+
+    let (status, body) = fake
+        .post("/v1/certificates/batches", json!({ "certificates": [certificate("XM-0105"), certificate("XM-0103")] }))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["error"], "XM-0103 is already batched");
+    let (status, _) = fake
+        .post("/v1/certificates/batches", json!({ "certificates": [certificate("XM-0106"), certificate("XM-0106")] }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = fake.post("/v1/certificates/batches", json!({ "certificates": [] })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn items_and_batches_never_share_a_unique_id() {
+    let fake = Fake::new().await;
+    let chain = fake.state.chains.default_chain();
+    let config = BatchWorkerConfig { max_wait: Duration::ZERO, ..BatchWorkerConfig::default() };
+    let certificate = |unique_id: &str| fake.certificate(unique_id, "XM12345");
+    let batch = |unique_ids: &[&str]| json!({ "certificates": unique_ids.iter().map(|id| certificate(id)).collect::<Vec<_>>() });
+
+    // created with createItem, then batched
+    fake.create_item("XM-0301").await;
+    let (status, body) = fake.post("/v1/certificates/batches", batch(&["XM-0302", "XM-0301"])).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["error"], "XM-0301 is already anchored as an item");
+    let (status, _) = fake.post("/v1/certificates/batches?dry_run=true", batch(&["XM-0301"])).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = fake.post("/v1/certificates/queue", json!({ "certificate": certificate("XM-0301") })).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // batched, then created with createItem
+    let (status, _) = fake.post("/v1/certificates/batches", batch(&["XM-0302"])).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = fake.post("/v1/certificates", certificate("XM-0302")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = fake.post("/v1/certificates?dry_run=true", certificate("XM-0302")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = fake.get("/v1/items/XM-0302").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // queued, then created with createItem before the worker ran: the others still go out
+    for unique_id in ["XM-0303", "XM-0304"] {
+        let (status, _) = fake.post("/v1/certificates/queue", json!({ "certificate": certificate(unique_id) })).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+    }
+    fake.create_item("XM-0303").await;
+    assert_eq!(anchor_due(&fake.state, &chain, &config, unix_now()).await, 1);
+    let (_, anchoring) = fake.get("/v1/certificates/XM-0303/anchoring").await;
+    assert_eq!(anchoring["status"], "failed");
+    assert_eq!(anchoring["last_error"], "Anchored as an item");
+    let (_, anchoring) = fake.get("/v1/certificates/XM-0304/anchoring").await;
+    assert_eq!(anchoring["status"], "anchored");
+}
+
+#[tokio::test]
+async fn queued_certificates_are_anchored_by_the_worker() {
+    let fake = Fake::new().await;
+    let chain = fake.state.chains.default_chain();
+    let config = BatchWorkerConfig { max_wait: Duration::ZERO, ..BatchWorkerConfig::default() };
+    let certificate = |unique_id: &str| fake.certificate(unique_id, "XM12345");
+
+    // signed by the server, or offline by the issuer
+    let (status, queued) = fake.post("/v1/certificates/queue", json!({ "certificate": certificate("XM-0201") })).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{}", queued);
+    assert_eq!(queued["status"], "queued");
+    let signed = Certificate::from_dto(serde_json::from_value(certificate("XM-0202")).unwrap(), chain.auth_chain_domain()).unwrap();
+    let signature = ISSUER_KEY.parse::<LocalWallet>().unwrap().sign_typed_data(&signed).await.unwrap();
+    let signature = format!("0x{}", signature);
+    let (status, _) = fake
+        .post("/v1/certificates/queue", json!({ "certificate": certificate("XM-0202"), "signature": signature }))
+        .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    let stranger = LocalWallet::new(&mut ethers::core::rand::thread_rng()).sign_typed_data(&signed).await.unwrap();
+    let (status, _) = fake
+        .post("/v1/certificates/queue", json!({ "certificate": certificate("XM-0203"), "signature": format!("0x{}", stranger) }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let mut other = certificate("XM-0201");
+    other["serial"] = json!("XM54321");
+    let (status, _) = fake.post("/v1/certificates/queue", json!({ "certificate": other })).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    assert_eq!(anchor_due(&fake.state, &chain, &config, unix_now()).await, 2);
+    let (status, anchoring) = fake.get("/v1/certificates/XM-0202/anchoring").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(anchoring["status"], "anchored");
+    let (_, proof) = fake.get("/v1/certificates/XM-0202/proof").await;
+    assert_eq!(proof["root"], anchoring["root"]);
+    assert_eq!(proof["signature"], json!(signature));
+    assert_eq!(anchor_due(&fake.state, &chain, &config, unix_now()).await, 0);
+
+    // a failed batch waits for its retry
+    let (status, _) = fake.post("/v1/certificates/queue", json!({ "certificate": certificate("XM-0204") })).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    fake.gateway.fail_with(GatewayError::Unavailable("node down".to_string()));
+    let now = unix_now();
+    assert_eq!(anchor_due(&fake.state, &chain, &config, now).await, 0);
+    let (_, anchoring) = fake.get("/v1/certificates/XM-0204/anchoring").await;
+    assert_eq!(anchoring["status"], "queued");
+    assert_eq!(anchoring["attempts"], 1);
+    assert_eq!(anchoring["last_error"], "node down");
+    assert_eq!(anchoring["next_attempt_at"], now + config.backoff.as_secs());
+    let (status, _) = fake.get("/v1/certificates/XM-0205/anchoring").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn certificate_qr_round_trip() {
+    let fake = Fake::new().await;
+    let certificate = fake.certificate("XM-0301", "XM12345");
+
+    let (status, qr) = fake.post("/v1/certificates/qr", json!({ "certificate": certificate })).await;
+    assert_eq!(status, StatusCode::OK, "{}", qr);
+    let payload = qr["payload"].as_str().unwrap();
+    assert!(payload.starts_with("AC1:"));
+    assert!(qr["svg"].as_str().unwrap().starts_with("<?xml"));
+    let (_, again) = fake.post("/v1/certificates/qr", json!({ "certificate": certificate })).await;
+    assert_eq!(again["payload"], qr["payload"]);
+
+    let (status, verification) = fake.post("/v1/certificates/qr/verifications", json!({ "payload": payload })).await;
+    assert_eq!(status, StatusCode::OK, "{}", verification);
+    assert_eq!(verification["valid"], true);
+    assert_eq!(verification["certificate"], certificate);
+    assert_eq!(verification["chain_id"], CHAIN_ID);
+
+    // signed for the deployment of another chain
+    let (_, verification) = fake
+        .post(&format!("/v1/chains/{}/certificates/qr/verifications", OTHER_CHAIN_ID), json!({ "payload": payload }))
+        .await;
+    assert_eq!(verification["valid"], false);
+    let (status, _) = fake.post("/v1/certificates/qr/verifications", json!({ "payload": &payload[..payload.len() - 3] })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn certificate_documents() {
+    let fake = Fake::new().await;
+    fake.create_item("XM-0401").await;
+    let get = |uri: &str| {
+        paths(fake.state.clone(), RouterPath::init(), &HttpConfig::default())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+    };
+
+    let response = get("/v1/certificates/XM-0401/document").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/pdf");
+    assert_eq!(response.headers()["content-disposition"], "inline; filename=\"certificate-XM-0401.pdf\"");
+    let pdf = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(pdf.starts_with(b"%PDF-"));
+
+    let response = get("/v1/certificates/XM-0401/document?format=png").await.unwrap();
+    assert_eq!(response.headers()["content-type"], "image/png");
+    let png = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(png.starts_with(b"\x89PNG"));
+
+    let (status, _) = fake.get("/v1/certificates/XM-0401/document?format=gif").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = fake.get("/v1/certificates/XM-0402/document").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn transaction_lookup() {
+    let fake = Fake::new().await;
+
+    let (status, _) = fake.get("/v1/tx/not-a-hash").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = fake.get(&format!("/v1/tx/{:?}", H256::random())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn tracked_transactions_by_chain() {
+    let fake = Fake::new().await;
+    let pending = PendingTx::new(CHAIN_ID, H256::random(), "create_item", "XM-0501");
+    fake.state.tx_tracker.track(pending.clone());
+    fake.state.tx_tracker.track(PendingTx::new(OTHER_CHAIN_ID, H256::random(), "create_item", "XM-0502"));
+
+    let (status, tracked) = fake.get("/v1/transactions").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tracked["pending"].as_array().unwrap().len(), 1);
+    assert_eq!(tracked["pending"][0]["tx_hash"], format!("{:?}", pending.tx_hash));
+    assert_eq!(tracked["pending"][0]["outcome"], Value::Null);
+    assert_eq!(tracked["resumed"], json!([]));
+}
+
+#[tokio::test]
+async fn resumed_transactions_stay_persisted_until_they_finish() {
+    let fake = Fake::new().await;
+    let tracker = &fake.state.tx_tracker;
+    let file = std::env::temp_dir().join(format!("resume-test-{}.json", std::process::id()));
+    let first = PendingTx::new(CHAIN_ID, H256::random(), "create_item", "XM-0601");
+    let second = PendingTx::new(OTHER_CHAIN_ID, H256::random(), "revoke_item", "XM-0602");
+    std::fs::write(&file, serde_json::to_string(&[&first, &second]).unwrap()).unwrap();
+
+    // the fake chains have no node, no receipt comes in
+    assert_eq!(tracker.resume(&file, &fake.state.chains).unwrap(), 2);
+    let listed = |file: &std::path::Path| -> Vec<PendingTx> {
+        serde_json::from_str(&std::fs::read_to_string(file).unwrap()).unwrap()
+    };
+    assert!(listed(&file).contains(&first));
+
+    tracker.finish(first.tx_hash);
+    assert_eq!(listed(&file), vec![second.clone()]);
+    tracker.finish(second.tx_hash);
+    assert!(!file.exists());
+}
+
+#[tokio::test]
+async fn get_owner() {
+    let fake = Fake::new().await;
+
+    let (status, body) = fake.get("/v1/issuers").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_str().unwrap().parse::<Address>().unwrap(), fake.state.wallet_address);
+}
+
+#[tokio::test]
+async fn revoke_item() {
+    let fake = Fake::new().await;
+
+    fake.create_item("XM-0002").await;
+
+    let (status, body) = fake
+        .post("/v1/certificates/XM-0002/revocation", json!({ "reason": "stolen" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["unique_id"], "XM-0002");
+    assert_eq!(body["reason"], "stolen");
+
+    let (status, _) = fake
+        .post("/v1/certificates/XM-0002/revocation", json!({ "reason": "stolen" }))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = fake
+        .post("/v1/certificates/unknown/revocation", json!({ "reason": "stolen" }))
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, history) = fake.get("/v1/items/XM-0002/history").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(history[0]["action"], "created");
+    assert_eq!(history[1]["action"], "revoked");
+    assert_eq!(history[1]["reason"], "stolen");
+}
+
+#[tokio::test]
+async fn verify_signature() {
+    let fake = Fake::new().await;
+
+    let asset = |owner: Address| {
+        json!({ "name": "Redmi Note 14", "serial": "XM12345", "owner": format!("{:?}", owner) })
+    };
+
+    let (status, body) = fake.post("/v1/signatures/verify", asset(fake.state.wallet_address)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!("Signature valid: true"));
+
+    let (status, body) = fake.post("/v1/signatures/verify", asset(Address::random())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!("Signature valid: false"));
+}
+
+#[tokio::test]
+async fn typed_data_chain_ids_out_of_range_are_refused() {
+    let fake = Fake::new().await;
+    let typed_data = |chain_id: Value| {
+        json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Asset": [{ "name": "name", "type": "string" }]
+            },
+            "primaryType": "Asset",
+            "domain": {
+                "name": "AssetOwnership",
+                "version": "1",
+                "chainId": chain_id,
+                "verifyingContract": SIGNATURE_VERIFIER
+            },
+            "message": { "name": "Redmi Note 14" }
+        })
+    };
+
+    let (status, signed) = fake.post("/v1/typed-data/signatures", typed_data(json!(CHAIN_ID))).await;
+    assert_eq!(status, StatusCode::OK, "{}", signed);
+
+    // 2^64, one past u64::MAX
+    let (status, _) = fake.post("/v1/typed-data/signatures", typed_data(json!("18446744073709551616"))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = fake
+        .post(
+            "/v1/typed-data/verifications",
+            json!({ "typedData": typed_data(json!("18446744073709551616")), "signature": signed["signature"] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn register_and_look_up_asset() {
+    let fake = Fake::new().await;
+
+    let (status, _) = fake.get("/v1/ownership/asset001").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = fake.post("/v1/ownership/asset001", json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["asset_id"], "asset001");
+
+    let (status, body) = fake.get("/v1/ownership/asset001").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["owner"].as_str().unwrap().parse::<Address>().unwrap(), fake.state.wallet_address);
+
+    let (status, _) = fake.post("/v1/ownership/asset001", json!(null)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn gateway_failures_map_to_status_codes() {
+    let fake = Fake::new().await;
+
+    fake.gateway.fail_with(GatewayError::Unavailable("node down".to_string()));
+    let (status, _) = fake.get("/v1/issuers").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    fake.gateway.fail_with(GatewayError::Rpc("timeout".to_string()));
+    let (status, _) = fake.get("/v1/items/XM-0001").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+    fake.gateway.fail_with(GatewayError::Unauthorized);
+    let (status, _) = fake
+        .post("/v1/certificates/XM-0001/revocation", json!({ "reason": "recalled" }))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = fake
+        .post("/v1/signatures/verify", json!({ "name": "x", "serial": "y", "owner": "not-an-address" }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn routes_are_scoped_by_chain() {
+    let fake = Fake::new().await;
+
+    fake.create_item_at("/chains/84532", "XM-0003").await;
+
+    let (status, item) = fake.get("/v1/chains/84532/items/XM-0003").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(item["chain_id"], OTHER_CHAIN_ID);
+
+    // the unprefixed routes use the default chain, where the item does not exist
+    let (status, _) = fake.get("/v1/items/XM-0003").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = fake.get("/v1/chains/31337/items/XM-0003").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = fake.get("/v1/chains/1/issuers").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = fake.get("/v1/chains/base/issuers").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, chains) = fake.get("/v1/chains").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(chains[0]["chain_id"], CHAIN_ID);
+    assert_eq!(chains[0]["default"], true);
+    assert_eq!(chains[1]["chain_id"], OTHER_CHAIN_ID);
+    assert_eq!(chains[1]["default"], false);
+}
+
+#[tokio::test]
+async fn health_and_info() {
+    let fake = Fake::new().await;
+
+    let (status, _) = fake.get("/health/live").await;
+    assert_eq!(status, StatusCode::OK);
+
+    // the fake chains have no node behind them
+    let (status, readiness) = fake.get("/health/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(readiness["ready"], false);
+    assert_eq!(readiness["chains"][0]["checks"][0]["name"], "rpc");
+    assert_eq!(readiness["chains"][0]["checks"][0]["ok"], false);
+    assert_eq!(readiness["database"], Value::Null);
+
+    let (status, info) = fake.get("/info").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(info["default_chain_id"], CHAIN_ID);
+    assert_eq!(info["issuer"].as_str().unwrap().parse::<Address>().unwrap(), fake.state.wallet_address);
+    assert_eq!(info["chains"][1]["auth_chain"].as_str().unwrap().parse::<Address>().unwrap(), AUTH_CHAIN.parse::<Address>().unwrap());
+}
+
+#[tokio::test]
+async fn metrics() {
+    let fake = Fake::new().await;
+
+    fake.create_item_at("/chains/84532", "XM-0004").await;
+    fake.get("/v1/chains/84532/items/XM-0004").await;
+
+    let (status, metrics) = fake.get("/metrics").await;
+    assert_eq!(status, StatusCode::OK);
+    let metrics = metrics.as_str().unwrap();
+    assert!(metrics.contains(r#"certificates_total{chain_id="84532",outcome="anchored"} 1"#), "{}", metrics);
+    assert!(metrics.contains(r#"certificates_total{chain_id="84532",outcome="signed"} 1"#));
+    // latency is recorded by route template, not by item
+    assert!(metrics.contains(r#"route="/v1/chains/{chain_id}/items/{unique_id}",status="200""#));
+    // the fake chains have no node, balance lookups fail
+    assert!(metrics.contains(r#"rpc_errors_total{method="eth_getBalance"}"#));
+}
+
+#[tokio::test]
+async fn request_ids_are_propagated() {
+    let fake = Fake::new().await;
+
+    let request_id = |request: Request<Body>| {
+        let state = fake.state.clone();
+        async move {
+            let response = paths(state, RouterPath::init(), &HttpConfig::default()).oneshot(request).await.unwrap();
+            response.headers()["x-request-id"].to_str().unwrap().to_string()
+        }
+    };
+
+    let given = Request::get("/v1/issuers").header("x-request-id", "req-42").body(Body::empty()).unwrap();
+    assert_eq!(request_id(given).await, "req-42");
+
+    let generated = request_id(Request::get("/v1/issuers").body(Body::empty()).unwrap()).await;
+    assert_eq!(generated.len(), 36); // a UUID
+}
+
+#[tokio::test]
+async fn writes_are_refused_while_draining() {
+    let fake = Fake::new().await;
+
+    fake.state.tx_tracker.start_draining();
+
+    let (status, _) = fake.post("/v1/certificates/XM-0001/revocation", json!({ "reason": "stolen" })).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let (status, _) = fake.post("/v1/chains/84532/certificates", fake.certificate("XM-0002", "XM12345")).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    // reads are still served, POST routes that only check or sign included
+    let (status, _) = fake.get("/v1/issuers").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = fake.post("/v1/certificates/qr", json!({ "certificate": fake.certificate("XM-0003", "XM12345") })).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = fake.post("/v1/proofs/verifications", json!({})).await;
+    assert_ne!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn http_layers() {
+    let fake = Fake::new().await;
+    let http = HttpConfig {
+        max_body_bytes: 256,
+        cors_origins: Some(vec!["https://shop.example.com".parse().unwrap()]),
+        ..HttpConfig::default()
+    };
+    let send = |request: Request<Body>| paths(fake.state.clone(), RouterPath::init(), &http).oneshot(request);
+
+    let response = send(Request::get("/v1/chains").header("origin", "https://shop.example.com").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-content-type-options"], "nosniff");
+    assert_eq!(response.headers()["x-frame-options"], "DENY");
+    assert_eq!(response.headers()["access-control-allow-origin"], "https://shop.example.com");
+    assert!(response.headers().get("strict-transport-security").is_none());
+
+    let response = send(Request::get("/v1/chains").header("origin", "https://evil.example.com").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert!(response.headers().get("access-control-allow-origin").is_none());
+
+    // preflight of an idempotent create from a browser
+    let response = send(
+        Request::options("/v1/certificates")
+            .header("origin", "https://shop.example.com")
+            .header("access-control-request-method", "POST")
+            .header("access-control-request-headers", "content-type,idempotency-key")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let allowed = response.headers()["access-control-allow-headers"].to_str().unwrap();
+    assert!(allowed.contains("idempotency-key"), "{}", allowed);
+
+    let response = send(Request::get("/chains").header("origin", "https://shop.example.com").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let exposed = response.headers()["access-control-expose-headers"].to_str().unwrap();
+    for header in ["x-request-id", "idempotent-replayed", "deprecation"] {
+        assert!(exposed.contains(header), "{}", exposed);
+    }
+
+    let oversized = json!({ "reason": "x".repeat(1024) }).to_string();
+    let response = send(
+        Request::post("/v1/certificates/XM-0001/revocation")
+            .header("content-type", "application/json")
+            .body(Body::from(oversized))
+            .unwrap(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn legacy_routes_are_deprecated_aliases() {
+    let fake = Fake::new().await;
+    let send = |request: Request<Body>| paths(fake.state.clone(), RouterPath::init(), &HttpConfig::default()).oneshot(request);
+
+    fake.create_item("XM-0001").await;
+
+    let response = send(Request::get("/get_item/XM-0001").body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["deprecation"], "true");
+
+    let response = send(Request::get("/chains/84532/get_owner").body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["deprecation"], "true");
+
+    let response = send(Request::get("/v1/items/XM-0001").body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("deprecation").is_none());
+
+    // GET /tx/{tx_hash} is served unversioned, it is not a deprecated alias
+    let response = send(Request::get("/tx/not-a-hash").body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.headers().get("deprecation").is_none());
+
+    // the legacy document only lists the legacy routes, all deprecated
+    let (status, doc) = fake.get("/api-docs/openapi.json").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(doc["paths"]["/get_item/{unique_id}"]["get"]["deprecated"], true);
+    assert_eq!(doc["paths"]["/health/live"]["get"]["deprecated"], Value::Null);
+    assert_eq!(doc["paths"]["/v1/items/{unique_id}"], Value::Null);
+    // routes added in v1 have no legacy alias
+    for v1_only in ["reissue", "batch", "proof", "queue", "anchoring", "qr", "document"] {
+        assert!(doc["paths"].as_object().unwrap().keys().all(|path| !path.contains(v1_only)));
+    }
+
+    let (_, doc) = fake.get("/api-docs/v1/openapi.json").await;
+    assert_eq!(doc["paths"]["/v1/items/{unique_id}"]["get"]["deprecated"], Value::Null);
+}